@@ -26,23 +26,16 @@ fn verts() -> (Vec3, Vec3, Vec3) {
     (v1, v2, v3)
 }
 
-fn triangle(pixel_buffer: &mut [Srgb]) {
+fn triangle(framebuffer: &mut OwnedFramebuffer<Srgb>) {
     let (v1, v2, v3) = verts();
     rast::rast_triangle_colored(
-        pixel_buffer,
-        WIDTH,
-        HEIGHT,
-        v1.x,
-        v1.y,
-        v2.x,
-        v2.y,
-        v3.x,
-        v3.y,
+        framebuffer,
+        [v1, v2, v3].map(|v| v.truncate().to_array()),
         Srgb::rgb(255, 255, 255),
     );
 }
 
-fn triangle_rgb(pixel_buffer: &mut [Srgb]) {
+fn triangle_rgb(framebuffer: &mut OwnedFramebuffer<Srgb>) {
     let (v1, v2, v3) = verts();
 
     let c1 = LinearRgb::rgb(black_box(1.0), black_box(0.0), black_box(0.0));
@@ -50,23 +43,17 @@ fn triangle_rgb(pixel_buffer: &mut [Srgb]) {
     let c3 = LinearRgb::rgb(black_box(0.0), black_box(0.0), black_box(1.0));
 
     rast::rast_triangle(
-        pixel_buffer,
-        WIDTH,
-        HEIGHT,
-        v1.x,
-        v1.y,
-        v2.x,
-        v2.y,
-        v3.x,
-        v3.y,
-        black_box(c1),
-        black_box(c2),
-        black_box(c3),
+        framebuffer,
+        [
+            (v1.truncate().to_array(), black_box(c1)),
+            (v2.truncate().to_array(), black_box(c2)),
+            (v3.truncate().to_array(), black_box(c3)),
+        ],
         black_box(ColorShader),
     );
 }
 
-fn triangle_texture(pixel_buffer: &mut [Srgb], texture: TextureShader<Srgb>) {
+fn triangle_texture(framebuffer: &mut OwnedFramebuffer<Srgb>, texture: TextureShader<Srgb>) {
     let (v1, v2, v3) = verts();
 
     let uv1 = (black_box(0.0), black_box(1.0));
@@ -74,23 +61,17 @@ fn triangle_texture(pixel_buffer: &mut [Srgb], texture: TextureShader<Srgb>) {
     let uv3 = (black_box(1.0), black_box(1.0));
 
     rast::rast_triangle(
-        pixel_buffer,
-        WIDTH,
-        HEIGHT,
-        v1.x,
-        v1.y,
-        v2.x,
-        v2.y,
-        v3.x,
-        v3.y,
-        black_box(uv1),
-        black_box(uv2),
-        black_box(uv3),
+        framebuffer,
+        [
+            (v1.truncate().to_array(), black_box(uv1)),
+            (v2.truncate().to_array(), black_box(uv2)),
+            (v3.truncate().to_array(), black_box(uv3)),
+        ],
         black_box(texture),
     );
 }
 
-fn triangle_rgb_checked(pixel_buffer: &mut [Srgb], depth_buffer: &mut [f32]) {
+fn triangle_rgb_checked(framebuffer: &mut OwnedFramebuffer<Srgb>) {
     let (v1, v2, v3) = verts();
 
     let c1 = LinearRgb::rgb(black_box(1.0), black_box(0.0), black_box(0.0));
@@ -98,39 +79,29 @@ fn triangle_rgb_checked(pixel_buffer: &mut [Srgb], depth_buffer: &mut [f32]) {
     let c3 = LinearRgb::rgb(black_box(0.0), black_box(0.0), black_box(1.0));
 
     rast::rast_triangle_checked(
-        pixel_buffer,
-        depth_buffer,
-        WIDTH,
-        HEIGHT,
-        v1.x,
-        v1.y,
-        v1.z,
-        v2.x,
-        v2.y,
-        v2.z,
-        v3.x,
-        v3.y,
-        v3.z,
-        black_box(c1),
-        black_box(c2),
-        black_box(c3),
+        framebuffer,
+        DrawState::default(),
+        [
+            (v1.to_array(), black_box(c1)),
+            (v2.to_array(), black_box(c2)),
+            (v3.to_array(), black_box(c3)),
+        ],
         black_box(ColorShader),
     );
 }
 
-fn bench_fn(c: &mut Criterion, name: &str, f: impl Fn(&mut [Srgb])) {
-    let mut buf = vec![Srgb::default(); WIDTH * HEIGHT];
+fn bench_fn(c: &mut Criterion, name: &str, f: impl Fn(&mut OwnedFramebuffer<Srgb>)) {
+    let mut buf = OwnedFramebuffer::new(WIDTH, HEIGHT, Srgb::default());
     c.bench_function(name, |b| {
         b.iter(|| f(black_box(&mut buf)));
     });
 }
 
-fn bench_fn_checked(c: &mut Criterion, name: &str, f: impl Fn(&mut [Srgb], &mut [f32])) {
-    let mut buf = vec![Srgb::default(); WIDTH * HEIGHT];
+fn bench_fn_checked(c: &mut Criterion, name: &str, f: impl Fn(&mut OwnedFramebuffer<Srgb>)) {
     c.bench_function(name, |b| {
         b.iter_batched(
-            || vec![1.0; WIDTH * HEIGHT],
-            |mut depth| f(black_box(&mut buf), black_box(&mut depth)),
+            || OwnedFramebuffer::new(WIDTH, HEIGHT, Srgb::default()).with_depth(1.0),
+            |mut buf| f(black_box(&mut buf)),
             criterion::BatchSize::LargeInput,
         );
     });
@@ -152,8 +123,9 @@ fn criterion_benchmark(c: &mut Criterion) {
         width: 100,
         height: 100,
         sampler: rast::Sampler::Nearest,
+        alpha_test: None,
     };
-    let mut buf = vec![Srgb::default(); WIDTH * HEIGHT];
+    let mut buf = OwnedFramebuffer::new(WIDTH, HEIGHT, Srgb::default());
     c.bench_function("triangle_texture", |b| {
         b.iter(|| triangle_texture(black_box(&mut buf), black_box(texture)));
     });
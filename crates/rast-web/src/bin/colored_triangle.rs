@@ -6,20 +6,21 @@ fn main() {
     serve(colored_triangle);
 }
 
-fn colored_triangle(pixels: &mut [Srgb], _: &mut [f32], _: f32) {
+fn colored_triangle(framebuffer: &mut OwnedFramebuffer<Srgb>, _: f32) {
+    let (width, height) = (WIDTH as f32, HEIGHT as f32);
     rast::rast_triangle(
-        pixels,
-        WIDTH,
-        HEIGHT,
-        WIDTH as f32 / 3.0,
-        HEIGHT as f32 / 3.0 * 2.0,
-        WIDTH as f32 / 2.0,
-        HEIGHT as f32 / 3.0,
-        WIDTH as f32 / 3.0 * 2.0,
-        HEIGHT as f32 / 3.0 * 2.0,
-        LinearRgb::rgb(1.0, 0.0, 0.0),
-        LinearRgb::rgb(0.0, 1.0, 0.0),
-        LinearRgb::rgb(0.0, 0.0, 1.0),
+        framebuffer,
+        [
+            (
+                [width / 3.0, height / 3.0 * 2.0],
+                LinearRgb::rgb(1.0, 0.0, 0.0),
+            ),
+            ([width / 2.0, height / 3.0], LinearRgb::rgb(0.0, 1.0, 0.0)),
+            (
+                [width / 3.0 * 2.0, height / 3.0 * 2.0],
+                LinearRgb::rgb(0.0, 0.0, 1.0),
+            ),
+        ],
         ColorShader,
     );
 }
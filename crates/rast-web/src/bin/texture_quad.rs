@@ -13,11 +13,15 @@ fn main() {
         width: 400,
         height: 400,
         sampler: rast::Sampler::Bilinear,
+        alpha_test: None,
     };
 
+    let mut msaa =
+        OwnedFramebuffer::new_multisample(WIDTH, HEIGHT, SampleCount::X4, Srgb::default());
     let mut angle = 0.0;
-    serve(move |pixel_buffer, _, dt| {
+    serve(move |framebuffer, dt| {
         angle += dt;
+        msaa.clear(Srgb::rgb(42, 42, 42), 0.0);
 
         let scale = Vec2::new(WIDTH as f32 / 3.0, HEIGHT as f32 / 3.0);
         let offset = Vec2::new(WIDTH as f32 / 2.0, HEIGHT as f32 / 2.0);
@@ -26,18 +30,12 @@ fn main() {
         let v2 = transform_vertex(Vec3::new(-0.5, -0.7, 0.0), angle, scale, offset);
         let v3 = transform_vertex(Vec3::new(0.5, 0.7, 0.0), angle, scale, offset);
         rast::rast_triangle(
-            pixel_buffer,
-            WIDTH,
-            HEIGHT,
-            v1.x,
-            v1.y,
-            v2.x,
-            v2.y,
-            v3.x,
-            v3.y,
-            (0.0, 1.0),
-            (0.0, 0.0),
-            (1.0, 1.0),
+            &mut msaa,
+            [
+                (v1.to_array(), (0.0, 1.0)),
+                (v2.to_array(), (0.0, 0.0)),
+                (v3.to_array(), (1.0, 1.0)),
+            ],
             shader,
         );
 
@@ -45,20 +43,15 @@ fn main() {
         let v2 = transform_vertex(Vec3::new(-0.5, -0.7, 0.0), angle, scale, offset);
         let v3 = transform_vertex(Vec3::new(0.5, 0.7, 0.0), angle, scale, offset);
         rast::rast_triangle(
-            pixel_buffer,
-            WIDTH,
-            HEIGHT,
-            v1.x,
-            v1.y,
-            v2.x,
-            v2.y,
-            v3.x,
-            v3.y,
-            (1.0, 0.0),
-            (0.0, 0.0),
-            (1.0, 1.0),
+            &mut msaa,
+            [
+                (v1.to_array(), (1.0, 0.0)),
+                (v2.to_array(), (0.0, 0.0)),
+                (v3.to_array(), (1.0, 1.0)),
+            ],
             shader,
         );
+        msaa.resolve(framebuffer);
     });
 }
 
@@ -6,80 +6,101 @@ use rast::*;
 use rast_web::{HEIGHT, WIDTH, serve};
 
 fn main() {
-    let utah_teapot = read_utah_teapot();
+    let (positions, indices) = read_utah_teapot();
+    let colors = vertex_colors(&positions);
+    let vertices: Vec<_> = positions.into_iter().zip(colors).collect();
     let mut angle = 0.0;
+    let shader = TeapotShader {
+        mvp: Mat4::IDENTITY,
+    };
+    let mut renderer = rast::tiled::TiledRenderer::new(WIDTH, HEIGHT, shader);
+    let mut msaa =
+        OwnedFramebuffer::new_multisample(WIDTH, HEIGHT, SampleCount::X4, Srgb::default())
+            .with_depth(0.0);
 
-    serve(move |pixel_buffer, depth_buffer, dt| {
-        fn display(mut v: Vec3) -> Vec3 {
-            if v.z < 0.0 {
-                v.z = -v.z;
-            }
+    serve(move |framebuffer, dt| {
+        let proj = Mat4::perspective_infinite_reverse_lh(
+            core::f32::consts::FRAC_PI_2,
+            WIDTH as f32 / HEIGHT as f32,
+            0.1,
+        );
+        let offset = Vec3::new(0.0, -1.5, 4.5);
+        angle = (angle + dt) % core::f32::consts::TAU;
+        renderer.shader_mut().mvp =
+            proj * Mat4::from_translation(offset) * Mat4::from_rotation_y(angle);
+        // the model is wound clockwise once mirrored into the left handed view
+        let state = DrawState {
+            subpixel: Subpixel::Fixed(8),
+            cull: CullMode::Back,
+            front_face: FrontFace::Cw,
+            depth: DepthState::REVERSE_Z,
+            ..Default::default()
+        };
+        msaa.clear(Srgb::rgb(42, 42, 42), state.depth.clear_value());
+        renderer.draw_indexed(state, &vertices, &indices);
+        renderer.render(&mut msaa);
+        msaa.resolve(framebuffer);
+    });
+}
 
-            if v.z < f32::EPSILON {
-                v.z += f32::EPSILON;
-            }
+#[derive(Clone, Copy)]
+struct TeapotShader {
+    mvp: Mat4,
+}
 
-            let proj = Vec2::new(v.x / v.z, v.y / v.z);
-            Vec3::new(
-                (proj.x + 1.0) / 2.0 * WIDTH as f32,
-                (1.0 - (proj.y + 1.0) / 2.0) * HEIGHT as f32,
-                v.z,
-            )
-        }
+impl Shader for TeapotShader {
+    type VertexData = LinearRgb;
 
-        let offset = Vec3::new(0.0, -1.5, 4.5);
-        angle = (angle + dt) % core::f32::consts::TAU;
-        for slice in utah_teapot.chunks(3) {
-            let quat = Quat::from_rotation_y(angle);
-            let v1 = quat.mul_vec3(slice[0]);
-            let v2 = quat.mul_vec3(slice[1]);
-            let v3 = quat.mul_vec3(slice[2]);
+    fn interpolate(
+        &self,
+        bcx: f32,
+        bcy: f32,
+        bcz: f32,
+        d1: Self::VertexData,
+        d2: Self::VertexData,
+        d3: Self::VertexData,
+    ) -> Self::VertexData {
+        barycentric_lerp(bcx, bcy, bcz, d1, d2, d3)
+    }
 
-            let v1 = display(v1 + offset);
-            let v2 = display(v2 + offset);
-            let v3 = display(v3 + offset);
+    fn fragment(&mut self, data: Self::VertexData) -> Fragment {
+        data.into()
+    }
+}
 
-            rast::rast_triangle_checked(
-                pixel_buffer,
-                depth_buffer,
-                WIDTH,
-                HEIGHT,
-                v1.x,
-                v1.y,
-                v1.z,
-                v2.x,
-                v2.y,
-                v2.z,
-                v3.x,
-                v3.y,
-                v3.z,
-                LinearRgb::rgb(1.0, 0.0, 0.0),
-                LinearRgb::rgb(0.0, 1.0, 0.0),
-                LinearRgb::rgb(0.0, 0.0, 1.0),
-                ColorShader,
-            );
-        }
-    });
+impl VertexShader<(Vec3, LinearRgb)> for TeapotShader {
+    fn vertex(&mut self, (pos, color): &(Vec3, LinearRgb)) -> ([f32; 4], Self::VertexData) {
+        ((self.mvp * pos.extend(1.0)).to_array(), *color)
+    }
 }
 
-fn read_utah_teapot() -> Vec<Vec3> {
-    let (model, _) = tobj::load_obj_buf(
+fn read_utah_teapot() -> (Vec<Vec3>, Vec<u32>) {
+    let (mut model, _) = tobj::load_obj_buf(
         &mut BufReader::new(include_bytes!("../../assets/utah-teapot.obj").as_slice()),
         &tobj::GPU_LOAD_OPTIONS,
         |_| tobj::MTLLoadResult::Ok(Default::default()),
     )
     .unwrap();
 
-    let mut output = Vec::new();
-    for i in model[0].mesh.indices.iter() {
-        let i = *i as usize;
-        let pos = Vec3::new(
-            model[0].mesh.positions[i * 3],
-            model[0].mesh.positions[i * 3 + 1],
-            model[0].mesh.positions[i * 3 + 2],
-        );
-        output.push(pos);
-    }
+    let mesh = model.swap_remove(0).mesh;
+    let positions = mesh
+        .positions
+        .chunks_exact(3)
+        .map(|p| Vec3::new(p[0], p[1], p[2]))
+        .collect();
+
+    (positions, mesh.indices)
+}
 
-    output
+// Maps the bounding box of the model to the rgb cube.
+fn vertex_colors(positions: &[Vec3]) -> Vec<LinearRgb> {
+    let min = positions.iter().fold(Vec3::MAX, |a, b| a.min(*b));
+    let max = positions.iter().fold(Vec3::MIN, |a, b| a.max(*b));
+    positions
+        .iter()
+        .map(|p| {
+            let c = (*p - min) / (max - min);
+            LinearRgb::rgb(c.x, c.y, c.z)
+        })
+        .collect()
 }
@@ -1,12 +1,17 @@
+use rast::OwnedFramebuffer;
 use rast::tint::*;
 use std::sync::{Arc, RwLock};
 use std::thread;
 use std::time::Duration;
 use tiny_http::{Header, Response, Server};
 
-pub fn serve(mut f: impl FnMut(&mut [Srgb], &mut [f32], f32) + Send + Sync + 'static) {
+pub fn serve(mut f: impl FnMut(&mut OwnedFramebuffer<Srgb>, f32) + Send + Sync + 'static) {
     let render_time = Arc::new(RwLock::new(0.0));
-    let double_buffer = Arc::new(RwLock::new(PixelBuffer::new(WIDTH, HEIGHT)));
+    let double_buffer = Arc::new(RwLock::new(OwnedFramebuffer::new(
+        WIDTH,
+        HEIGHT,
+        Srgb::default(),
+    )));
     thread::spawn({
         let render_time = render_time.clone();
         let double_buffer = double_buffer.clone();
@@ -15,13 +20,9 @@ pub fn serve(mut f: impl FnMut(&mut [Srgb], &mut [f32], f32) + Send + Sync + 'st
             loop {
                 let start = std::time::Instant::now();
                 {
-                    let PixelBuffer {
-                        pixels,
-                        depth_buffer,
-                    } = &mut *double_buffer.write().unwrap();
-                    pixels.fill(Srgb::rgb(42, 42, 42));
-                    depth_buffer.fill(std::f32::MAX);
-                    f(pixels.as_mut_slice(), depth_buffer.as_mut_slice(), dt);
+                    let framebuffer = &mut *double_buffer.write().unwrap();
+                    framebuffer.clear_color(Srgb::rgb(42, 42, 42));
+                    f(framebuffer, dt);
                 }
                 let end = std::time::Instant::now()
                     .duration_since(start)
@@ -40,21 +41,7 @@ pub fn serve(mut f: impl FnMut(&mut [Srgb], &mut [f32], f32) + Send + Sync + 'st
 pub const WIDTH: usize = 600;
 pub const HEIGHT: usize = 600;
 
-struct PixelBuffer {
-    pixels: Vec<Srgb>,
-    depth_buffer: Vec<f32>,
-}
-
-impl PixelBuffer {
-    fn new(width: usize, height: usize) -> Self {
-        Self {
-            pixels: vec![Srgb::default(); width * height],
-            depth_buffer: vec![0.0; width * height],
-        }
-    }
-}
-
-fn server(double_buffer: Arc<RwLock<PixelBuffer>>, render_time: Arc<RwLock<f32>>) {
+fn server(double_buffer: Arc<RwLock<OwnedFramebuffer<Srgb>>>, render_time: Arc<RwLock<f32>>) {
     let server = Server::http("localhost:3030").unwrap();
     println!("Server running at http://localhost:3030");
 
@@ -65,7 +52,7 @@ fn server(double_buffer: Arc<RwLock<PixelBuffer>>, render_time: Arc<RwLock<f32>>
                 let buffers = double_buffer.read().unwrap();
                 let pixel_data = unsafe {
                     std::slice::from_raw_parts::<u8>(
-                        buffers.pixels.as_ptr() as *const u8,
+                        buffers.color().as_ptr() as *const u8,
                         WIDTH * HEIGHT * 4,
                     )
                 };
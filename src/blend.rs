@@ -0,0 +1,292 @@
+use core::ops::{Add, Mul};
+use tint::LinearRgb;
+
+/// Linear color with straight alpha, written by the fragment stage.
+///
+/// Opaque colors convert with an alpha of `1`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Rgba {
+    pub color: LinearRgb,
+    pub alpha: f32,
+}
+
+impl Rgba {
+    pub const fn new(color: LinearRgb, alpha: f32) -> Self {
+        Self { color, alpha }
+    }
+
+    /// Multiplies the color by alpha, for [`BlendState::PREMULTIPLIED`].
+    #[inline]
+    pub fn premultiply(self) -> Self {
+        Self::new(self.color * self.alpha, self.alpha)
+    }
+}
+
+impl From<LinearRgb> for Rgba {
+    #[inline]
+    fn from(color: LinearRgb) -> Self {
+        Self::new(color, 1.0)
+    }
+}
+
+impl Add for Rgba {
+    type Output = Self;
+
+    #[inline]
+    fn add(self, rhs: Self) -> Self {
+        Self::new(self.color + rhs.color, self.alpha + rhs.alpha)
+    }
+}
+
+impl Mul<f32> for Rgba {
+    type Output = Self;
+
+    #[inline]
+    fn mul(self, rhs: f32) -> Self {
+        Self::new(self.color * rhs, self.alpha * rhs)
+    }
+}
+
+/// Weight of the source or destination color in a [`BlendState`].
+///
+/// The source is the fragment and the destination is the color in the target.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlendFactor {
+    Zero,
+    One,
+    SrcColor,
+    OneMinusSrcColor,
+    DstColor,
+    OneMinusDstColor,
+    SrcAlpha,
+    OneMinusSrcAlpha,
+}
+
+impl BlendFactor {
+    #[inline]
+    fn weigh(self, color: LinearRgb, src: Rgba, dst: LinearRgb) -> LinearRgb {
+        match self {
+            Self::Zero => color * 0.0,
+            Self::One => color,
+            Self::SrcColor => zip(color, src.color, |c, f| c * f),
+            Self::OneMinusSrcColor => zip(color, src.color, |c, f| c * (1.0 - f)),
+            Self::DstColor => zip(color, dst, |c, f| c * f),
+            Self::OneMinusDstColor => zip(color, dst, |c, f| c * (1.0 - f)),
+            Self::SrcAlpha => color * src.alpha,
+            Self::OneMinusSrcAlpha => color * (1.0 - src.alpha),
+        }
+    }
+}
+
+/// Combines the weighted source and destination colors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlendOp {
+    Add,
+    /// Source minus destination.
+    Subtract,
+    /// Destination minus source.
+    ReverseSubtract,
+    /// Per channel minimum, ignoring the factors.
+    Min,
+    /// Per channel maximum, ignoring the factors.
+    Max,
+}
+
+/// Per-draw blending of fragments with the color attachment, in linear space.
+///
+/// `op(src * src_factor, dst * dst_factor)` per channel. Targets are opaque, the alpha of
+/// the fragment only weighs colors and is not stored. The default state replaces the
+/// destination.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BlendState {
+    pub src_factor: BlendFactor,
+    pub dst_factor: BlendFactor,
+    pub op: BlendOp,
+}
+
+impl Default for BlendState {
+    fn default() -> Self {
+        Self::REPLACE
+    }
+}
+
+impl BlendState {
+    pub const REPLACE: Self = Self::new(BlendFactor::One, BlendFactor::Zero, BlendOp::Add);
+    /// Source-over with straight alpha.
+    pub const ALPHA: Self = Self::new(
+        BlendFactor::SrcAlpha,
+        BlendFactor::OneMinusSrcAlpha,
+        BlendOp::Add,
+    );
+    /// Source-over with colors already multiplied by alpha, see [`Rgba::premultiply`].
+    pub const PREMULTIPLIED: Self = Self::new(
+        BlendFactor::One,
+        BlendFactor::OneMinusSrcAlpha,
+        BlendOp::Add,
+    );
+    pub const ADDITIVE: Self = Self::new(BlendFactor::One, BlendFactor::One, BlendOp::Add);
+    pub const MULTIPLY: Self = Self::new(BlendFactor::DstColor, BlendFactor::Zero, BlendOp::Add);
+    pub const MIN: Self = Self::new(BlendFactor::One, BlendFactor::One, BlendOp::Min);
+    pub const MAX: Self = Self::new(BlendFactor::One, BlendFactor::One, BlendOp::Max);
+
+    pub const fn new(src_factor: BlendFactor, dst_factor: BlendFactor, op: BlendOp) -> Self {
+        Self {
+            src_factor,
+            dst_factor,
+            op,
+        }
+    }
+
+    /// Whether the destination affects the result.
+    #[inline]
+    pub fn is_enabled(&self) -> bool {
+        *self != Self::REPLACE
+    }
+
+    /// Blends a fragment over the `dst` color.
+    #[inline]
+    pub fn blend(&self, src: Rgba, dst: LinearRgb) -> LinearRgb {
+        let s = self.src_factor.weigh(src.color, src, dst);
+        let d = self.dst_factor.weigh(dst, src, dst);
+        match self.op {
+            BlendOp::Add => s + d,
+            BlendOp::Subtract => zip(s, d, |s, d| s - d),
+            BlendOp::ReverseSubtract => zip(s, d, |s, d| d - s),
+            BlendOp::Min => zip(src.color, dst, f32::min),
+            BlendOp::Max => zip(src.color, dst, f32::max),
+        }
+    }
+}
+
+#[inline(always)]
+fn zip(a: LinearRgb, b: LinearRgb, f: impl Fn(f32, f32) -> f32) -> LinearRgb {
+    LinearRgb::rgb(f(a.r, b.r), f(a.g, b.g), f(a.b, b.b))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{DrawState, Fragment, OwnedFramebuffer, Shader, rast_triangle_checked};
+
+    fn src() -> Rgba {
+        Rgba::new(LinearRgb::rgb(0.5, 0.25, 1.0), 0.5)
+    }
+
+    fn dst() -> LinearRgb {
+        LinearRgb::rgb(0.25, 0.75, 0.5)
+    }
+
+    fn channels(c: LinearRgb) -> [f32; 3] {
+        [c.r, c.g, c.b]
+    }
+
+    #[test]
+    fn factors_weigh_colors() {
+        let color = LinearRgb::rgb(1.0, 0.5, 0.25);
+        for (factor, expected) in [
+            (BlendFactor::Zero, [0.0, 0.0, 0.0]),
+            (BlendFactor::One, [1.0, 0.5, 0.25]),
+            (BlendFactor::SrcColor, [0.5, 0.125, 0.25]),
+            (BlendFactor::OneMinusSrcColor, [0.5, 0.375, 0.0]),
+            (BlendFactor::DstColor, [0.25, 0.375, 0.125]),
+            (BlendFactor::OneMinusDstColor, [0.75, 0.125, 0.125]),
+            (BlendFactor::SrcAlpha, [0.5, 0.25, 0.125]),
+            (BlendFactor::OneMinusSrcAlpha, [0.5, 0.25, 0.125]),
+        ] {
+            assert_eq!(
+                channels(factor.weigh(color, src(), dst())),
+                expected,
+                "{factor:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn equations_combine_the_weighted_colors() {
+        let (one, zero) = (BlendFactor::One, BlendFactor::Zero);
+        for (state, expected) in [
+            (BlendState::new(one, one, BlendOp::Add), [0.75, 1.0, 1.5]),
+            (
+                BlendState::new(one, one, BlendOp::Subtract),
+                [0.25, -0.5, 0.5],
+            ),
+            (
+                BlendState::new(one, one, BlendOp::ReverseSubtract),
+                [-0.25, 0.5, -0.5],
+            ),
+            // min and max ignore the factors
+            (BlendState::new(zero, zero, BlendOp::Min), [0.25, 0.25, 0.5]),
+            (BlendState::new(zero, zero, BlendOp::Max), [0.5, 0.75, 1.0]),
+            (BlendState::REPLACE, [0.5, 0.25, 1.0]),
+            (BlendState::MULTIPLY, [0.125, 0.1875, 0.5]),
+        ] {
+            assert_eq!(channels(state.blend(src(), dst())), expected, "{state:?}");
+        }
+    }
+
+    #[test]
+    fn premultiplied_colors_blend_like_straight_ones() {
+        for alpha in [0.0, 0.25, 0.5, 1.0] {
+            let fragment = Rgba::new(src().color, alpha);
+            let straight = BlendState::ALPHA.blend(fragment, dst());
+            let premultiplied = BlendState::PREMULTIPLIED.blend(fragment.premultiply(), dst());
+            assert_eq!(straight, premultiplied, "{alpha}");
+        }
+        assert_eq!(
+            BlendState::ALPHA.blend(Rgba::new(src().color, 0.0), dst()),
+            dst()
+        );
+        assert_eq!(
+            BlendState::ALPHA.blend(src().color.into(), dst()),
+            src().color
+        );
+        assert_eq!(
+            channels(BlendState::ALPHA.blend(src(), dst())),
+            [0.375, 0.5, 0.75]
+        );
+
+        // premultiplied colors can add light without covering anything
+        let glow = Rgba::new(LinearRgb::rgb(0.25, 0.25, 0.25), 0.0);
+        assert_eq!(
+            channels(BlendState::PREMULTIPLIED.blend(glow, dst())),
+            [0.5, 1.0, 0.75]
+        );
+    }
+
+    struct Translucent;
+
+    impl Shader for Translucent {
+        type VertexData = ();
+
+        fn interpolate(&self, _: f32, _: f32, _: f32, _: (), _: (), _: ()) {}
+
+        fn fragment(&mut self, _: ()) -> Fragment {
+            src().into()
+        }
+    }
+
+    #[test]
+    fn draws_blend_with_the_target() {
+        for (blend, expected) in [
+            (BlendState::REPLACE, src().color),
+            (BlendState::ALPHA, LinearRgb::rgb(0.375, 0.5, 0.75)),
+        ] {
+            let mut target = OwnedFramebuffer::new(8, 8, dst());
+            let state = DrawState {
+                blend,
+                ..Default::default()
+            };
+            rast_triangle_checked(
+                &mut target,
+                state,
+                [
+                    ([0.0, 0.0, 0.0], ()),
+                    ([20.0, 0.0, 0.0], ()),
+                    ([0.0, 20.0, 0.0], ()),
+                ],
+                Translucent,
+            );
+            assert!(target.color().iter().all(|&c| c == expected), "{blend:?}");
+        }
+    }
+}
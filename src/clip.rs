@@ -0,0 +1,419 @@
+use crate::Shader;
+
+/// Each clip plane can add at most one vertex to a convex polygon.
+pub const MAX_CLIP_VERTICES: usize = 3 + PLANES.len();
+
+/// A convex polygon in homogeneous clip space.
+pub struct ClipPolygon<D> {
+    pub vertices: [([f32; 4], D); MAX_CLIP_VERTICES],
+    pub len: usize,
+}
+
+impl<D: Copy> ClipPolygon<D> {
+    fn new(v: ([f32; 4], D)) -> Self {
+        Self {
+            vertices: [v; MAX_CLIP_VERTICES],
+            len: 0,
+        }
+    }
+
+    fn push(&mut self, v: ([f32; 4], D)) {
+        self.vertices[self.len] = v;
+        self.len += 1;
+    }
+}
+
+/// Signed distances to the frustum planes, positive inside:
+///
+/// `-w <= x <= w`, `-w <= y <= w`, `0 <= z <= w`
+const PLANES: [fn([f32; 4]) -> f32; 6] = [
+    |[x, _, _, w]| w + x,
+    |[x, _, _, w]| w - x,
+    |[_, y, _, w]| w + y,
+    |[_, y, _, w]| w - y,
+    |[_, _, z, _]| z,
+    |[_, _, z, w]| w - z,
+];
+
+/// Clips a triangle against the view frustum with Sutherland-Hodgman.
+///
+/// The result is a convex polygon that can be triangulated as a fan. Vertex data of new
+/// vertices is produced with [`Shader::interpolate_fragment`] from the data of the triangle,
+/// so attributes with [`Interpolation::Flat`] keep the data of `v1`. Returns `None` if the
+/// triangle is entirely outside of the frustum.
+///
+/// [`Interpolation::Flat`]: crate::Interpolation::Flat
+pub fn clip_triangle<S: Shader>(
+    shader: &S,
+    v1: ([f32; 4], S::VertexData),
+    v2: ([f32; 4], S::VertexData),
+    v3: ([f32; 4], S::VertexData),
+) -> Option<ClipPolygon<S::VertexData>> {
+    let mut outcodes = [0u8; 3];
+    for (code, v) in outcodes.iter_mut().zip([v1.0, v2.0, v3.0]) {
+        for (i, plane) in PLANES.iter().enumerate() {
+            if plane(v) < 0.0 {
+                *code |= 1 << i;
+            }
+        }
+    }
+
+    // every vertex is outside of the same plane
+    if outcodes[0] & outcodes[1] & outcodes[2] != 0 {
+        return None;
+    }
+
+    // fully inside, nothing to do
+    if outcodes[0] | outcodes[1] | outcodes[2] == 0 {
+        let mut polygon = ClipPolygon::new(v1);
+        polygon.push(v1);
+        polygon.push(v2);
+        polygon.push(v3);
+        return Some(polygon);
+    }
+
+    // vertices carry their barycentric coordinates in the triangle until the end
+    let mut polygon = ClipPolygon::new((v1.0, [1.0, 0.0, 0.0]));
+    polygon.push((v1.0, [1.0, 0.0, 0.0]));
+    polygon.push((v2.0, [0.0, 1.0, 0.0]));
+    polygon.push((v3.0, [0.0, 0.0, 1.0]));
+
+    let mut scratch = ClipPolygon::new(polygon.vertices[0]);
+    for (i, plane) in PLANES.iter().enumerate() {
+        if (outcodes[0] | outcodes[1] | outcodes[2]) & (1 << i) == 0 {
+            continue;
+        }
+
+        scratch.len = 0;
+        for j in 0..polygon.len {
+            let prev = polygon.vertices[(j + polygon.len - 1) % polygon.len];
+            let cur = polygon.vertices[j];
+            let dp = plane(prev.0);
+            let dc = plane(cur.0);
+
+            if dc >= 0.0 {
+                if dp < 0.0 {
+                    scratch.push(intersect(cur, dc, prev, dp));
+                }
+                scratch.push(cur);
+            } else if dp >= 0.0 {
+                scratch.push(intersect(prev, dp, cur, dc));
+            }
+        }
+
+        core::mem::swap(&mut polygon, &mut scratch);
+        if polygon.len < 3 {
+            return None;
+        }
+    }
+
+    let mut clipped = ClipPolygon::new(v1);
+    for &(v, [b1, b2, b3]) in &polygon.vertices[..polygon.len] {
+        let bc = (b1, b2, b3);
+        clipped.push((v, shader.interpolate_fragment(bc, bc, v1.1, v2.1, v3.1)));
+    }
+    Some(clipped)
+}
+
+// Always interpolates from the inside vertex so that an edge shared by two triangles is
+// split at exactly the same point.
+fn intersect(
+    inside: ([f32; 4], [f32; 3]),
+    din: f32,
+    outside: ([f32; 4], [f32; 3]),
+    dout: f32,
+) -> ([f32; 4], [f32; 3]) {
+    let t = din / (din - dout);
+    (lerp(inside.0, outside.0, t), lerp(inside.1, outside.1, t))
+}
+
+fn lerp<const N: usize>(a: [f32; N], b: [f32; N], t: f32) -> [f32; N] {
+    core::array::from_fn(|i| a[i] + (b[i] - a[i]) * t)
+}
+
+/// Clips a line against the view frustum with Liang-Barsky.
+///
+/// Vertex data of moved end points is produced with [`Shader::interpolate_fragment`] like
+/// in [`clip_triangle`], with the data of `v1` as the third vertex. Returns `None` if the
+/// line is entirely outside of the frustum.
+pub fn clip_line<S: Shader>(
+    shader: &S,
+    v1: ([f32; 4], S::VertexData),
+    v2: ([f32; 4], S::VertexData),
+) -> Option<[([f32; 4], S::VertexData); 2]> {
+    let (mut t0, mut t1) = (0.0f32, 1.0f32);
+    for plane in PLANES {
+        let (d1, d2) = (plane(v1.0), plane(v2.0));
+        if d1 < 0.0 && d2 < 0.0 {
+            return None;
+        } else if d1 < 0.0 {
+            t0 = t0.max(d1 / (d1 - d2));
+        } else if d2 < 0.0 {
+            t1 = t1.min(d1 / (d1 - d2));
+        }
+    }
+    if t0 > t1 {
+        return None;
+    }
+
+    let at = |t: f32| {
+        let bc = (1.0 - t, t, 0.0);
+        let data = shader.interpolate_fragment(bc, bc, v1.1, v2.1, v1.1);
+        (lerp(v1.0, v2.0, t), data)
+    };
+    let a = if t0 > 0.0 { at(t0) } else { v1 };
+    let b = if t1 < 1.0 { at(t1) } else { v2 };
+    Some([a, b])
+}
+
+/// Range of `t` where `a + (b - a) * t` is inside of `bounds`, `(minx, miny, maxx, maxy)`,
+/// with Liang-Barsky.
+pub fn clip_segment(
+    a: (f32, f32),
+    b: (f32, f32),
+    (minx, miny, maxx, maxy): (f32, f32, f32, f32),
+) -> Option<(f32, f32)> {
+    let d = (b.0 - a.0, b.1 - a.1);
+    let (mut t0, mut t1) = (0.0f32, 1.0f32);
+    for (p, q) in [
+        (-d.0, a.0 - minx),
+        (d.0, maxx - a.0),
+        (-d.1, a.1 - miny),
+        (d.1, maxy - a.1),
+    ] {
+        if p == 0.0 {
+            if q < 0.0 {
+                return None;
+            }
+        } else if p < 0.0 {
+            t0 = t0.max(q / p);
+        } else {
+            t1 = t1.min(q / p);
+        }
+    }
+    (t0 <= t1).then_some((t0, t1))
+}
+
+/// Clips a triangle and maps the result to a `width` by `height` viewport.
+///
+/// Vertices of the returned polygon are `[x, y, z / w, 1 / w]` in screen space, with `y`
+/// pointing down.
+pub fn clip_and_project<S: Shader>(
+    shader: &S,
+    width: usize,
+    height: usize,
+    v1: ([f32; 4], S::VertexData),
+    v2: ([f32; 4], S::VertexData),
+    v3: ([f32; 4], S::VertexData),
+) -> Option<ClipPolygon<S::VertexData>> {
+    let mut polygon = clip_triangle(shader, v1, v2, v3)?;
+    for (v, _) in polygon.vertices[..polygon.len].iter_mut() {
+        *v = project(*v, width, height);
+    }
+    Some(polygon)
+}
+
+/// Clips a line like [`clip_line`] and maps it to the viewport like [`clip_and_project`].
+pub fn clip_and_project_line<S: Shader>(
+    shader: &S,
+    width: usize,
+    height: usize,
+    v1: ([f32; 4], S::VertexData),
+    v2: ([f32; 4], S::VertexData),
+) -> Option<[([f32; 4], S::VertexData); 2]> {
+    let line = clip_line(shader, v1, v2)?;
+    Some(line.map(|(v, d)| (project(v, width, height), d)))
+}
+
+/// Maps a point to the viewport like [`clip_and_project`] if it is inside of the view
+/// frustum.
+pub fn clip_and_project_point(v: [f32; 4], width: usize, height: usize) -> Option<[f32; 4]> {
+    let inside = v[3] > 0.0 && PLANES.iter().all(|plane| plane(v) >= 0.0);
+    inside.then(|| project(v, width, height))
+}
+
+fn project([x, y, z, w]: [f32; 4], width: usize, height: usize) -> [f32; 4] {
+    let w = 1.0 / w;
+    [
+        (x * w * 0.5 + 0.5) * width as f32,
+        (0.5 - y * w * 0.5) * height as f32,
+        z * w,
+        w,
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Carries the clip space position as vertex data, so new vertices can be checked against
+    // their interpolated data.
+    struct PositionShader;
+
+    impl Shader for PositionShader {
+        type VertexData = [f32; 4];
+
+        fn interpolate(
+            &self,
+            bcx: f32,
+            bcy: f32,
+            bcz: f32,
+            d1: [f32; 4],
+            d2: [f32; 4],
+            d3: [f32; 4],
+        ) -> [f32; 4] {
+            core::array::from_fn(|i| d1[i] * bcx + d2[i] * bcy + d3[i] * bcz)
+        }
+    }
+
+    fn clip(v1: [f32; 4], v2: [f32; 4], v3: [f32; 4]) -> Option<ClipPolygon<[f32; 4]>> {
+        clip_triangle(&PositionShader, (v1, v1), (v2, v2), (v3, v3))
+    }
+
+    fn vertices(polygon: &ClipPolygon<[f32; 4]>) -> &[([f32; 4], [f32; 4])] {
+        &polygon.vertices[..polygon.len]
+    }
+
+    fn assert_inside(polygon: &ClipPolygon<[f32; 4]>) {
+        for &(v, _) in vertices(polygon) {
+            for plane in PLANES {
+                assert!(plane(v) >= -1e-6, "{v:?} is outside");
+            }
+        }
+    }
+
+    #[test]
+    fn triangles_inside_are_unchanged() {
+        let (v1, v2, v3) = (
+            [-0.5, -0.5, 0.2, 1.0],
+            [0.5, -0.5, 0.5, 1.0],
+            [0.0, 2.0, 1.0, 2.0],
+        );
+        let polygon = clip(v1, v2, v3).unwrap();
+        assert_eq!(vertices(&polygon), [(v1, v1), (v2, v2), (v3, v3)]);
+    }
+
+    #[test]
+    fn triangles_outside_of_a_plane_are_rejected() {
+        // right of x = w
+        assert!(
+            clip(
+                [2.0, 0.0, 0.5, 1.0],
+                [3.0, 1.0, 0.5, 1.0],
+                [2.0, -1.0, 0.5, 1.0]
+            )
+            .is_none()
+        );
+        // in front of the near plane
+        assert!(
+            clip(
+                [0.0, 0.0, -0.1, 1.0],
+                [1.0, 0.0, -0.5, 1.0],
+                [0.0, 1.0, -1.0, 1.0]
+            )
+            .is_none()
+        );
+        // behind the camera
+        assert!(
+            clip(
+                [0.0, 0.0, 0.5, -1.0],
+                [1.0, 0.0, 0.5, -1.0],
+                [0.0, 1.0, 0.5, -2.0]
+            )
+            .is_none()
+        );
+    }
+
+    #[test]
+    fn triangles_crossing_the_near_plane_are_cut_at_it() {
+        let (v1, v2, v3) = (
+            [0.0, 0.5, -0.5, 1.0],
+            [-0.5, -0.5, 0.5, 1.0],
+            [0.5, -0.5, 0.5, 1.0],
+        );
+        let polygon = clip(v1, v2, v3).unwrap();
+        assert_eq!(polygon.len, 4);
+        assert_inside(&polygon);
+
+        let cut: alloc::vec::Vec<_> = vertices(&polygon)
+            .iter()
+            .filter(|(v, _)| v[2] == 0.0)
+            .collect();
+        assert_eq!(cut.len(), 2);
+        for &&(v, data) in &cut {
+            // the vertex data of new vertices is interpolated like their positions
+            assert_eq!(v, data);
+            assert_eq!(v[1], 0.0);
+            assert!(v[0] == -0.25 || v[0] == 0.25, "{v:?}");
+        }
+    }
+
+    #[test]
+    fn vertices_behind_the_camera_are_clipped_away() {
+        // `w <= 0` is outside of some plane away from the eye, so no vertex is left to divide
+        // by zero
+        for w in [0.0, -1.0] {
+            let v1 = [0.5, 0.25, w * 0.5, w];
+            let polygon = clip(v1, [-1.0, -1.0, 0.5, 2.0], [1.0, -1.0, 0.5, 2.0]).unwrap();
+            assert_inside(&polygon);
+            for &(v, data) in vertices(&polygon) {
+                assert!(v[3] > 0.0, "{v:?}");
+                for (a, b) in v.into_iter().zip(data) {
+                    assert!((a - b).abs() < 1e-6, "{v:?} {data:?}");
+                }
+                let projected = project(v, 64, 64);
+                assert!(projected.iter().all(|c| c.is_finite()), "{projected:?}");
+            }
+        }
+    }
+
+    fn line(v1: [f32; 4], v2: [f32; 4]) -> Option<[([f32; 4], [f32; 4]); 2]> {
+        clip_line(&PositionShader, (v1, v1), (v2, v2))
+    }
+
+    #[test]
+    fn lines_are_cut_at_the_near_plane() {
+        let (v1, v2) = ([-0.5, 0.25, -1.0, 1.0], [0.5, 0.25, 1.0, 1.0]);
+        let [a, b] = line(v1, v2).unwrap();
+        assert_eq!(a, ([0.0, 0.25, 0.0, 1.0], [0.0, 0.25, 0.0, 1.0]));
+        assert_eq!(b, (v2, v2));
+
+        // either direction
+        let [a, b] = line(v2, v1).unwrap();
+        assert_eq!(a, (v2, v2));
+        assert_eq!(b.0, [0.0, 0.25, 0.0, 1.0]);
+
+        // in front of the near plane or entirely outside of another plane
+        assert!(line([0.0, 0.0, -1.0, 1.0], [0.5, 0.0, -0.1, 1.0]).is_none());
+        assert!(line([2.0, 0.0, 0.5, 1.0], [1.5, 0.9, 0.5, 1.0]).is_none());
+        // crosses two planes without ever being inside of both
+        assert!(line([-2.0, 0.0, 0.5, 1.0], [0.0, 2.5, 0.5, 1.0]).is_none());
+    }
+
+    #[test]
+    fn lines_behind_the_camera_are_clipped_away() {
+        let [a, b] = line([0.2, 0.0, -0.5, -1.0], [0.2, 0.0, 0.5, 1.0]).unwrap();
+        for (v, data) in [a, b] {
+            assert!(v[3] > 0.0, "{v:?}");
+            for plane in PLANES {
+                assert!(plane(v) >= -1e-6, "{v:?} is outside");
+            }
+            for (a, b) in v.into_iter().zip(data) {
+                assert!((a - b).abs() < 1e-6, "{v:?} {data:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn shared_edges_are_split_at_the_same_point() {
+        let (a, b) = ([-0.4, 0.3, -0.7, 1.0], [0.6, -0.2, 0.9, 1.3]);
+        let first = clip(a, b, [0.9, 0.8, 0.5, 1.0]).unwrap();
+        let second = clip(b, a, [-0.8, -0.9, 0.4, 1.0]).unwrap();
+        // both triangles are cut at the near plane, on `a b` and on one other edge
+        let split = vertices(&first)
+            .iter()
+            .filter(|(v, _)| v[2] == 0.0)
+            .filter(|vertex| vertices(&second).contains(vertex));
+        assert_eq!(split.count(), 1);
+    }
+}
@@ -0,0 +1,356 @@
+use crate::Compare;
+use crate::simd::{F32x8, LANES};
+
+/// Element of a depth attachment.
+///
+/// Depth is interpolated as `f32` and converted to the format before it is tested, so the
+/// test compares what would be stored. Integer formats map `0..=1` to their full range,
+/// rounding to the nearest value and clamping depth outside of it.
+pub trait DepthFormat: Copy {
+    /// Converts a depth to the format.
+    fn from_f32(depth: f32) -> Self;
+
+    /// Converts back to a depth. Distinct values stay distinct.
+    fn to_f32(self) -> f32;
+}
+
+impl DepthFormat for f32 {
+    #[inline(always)]
+    fn from_f32(depth: f32) -> Self {
+        depth
+    }
+
+    #[inline(always)]
+    fn to_f32(self) -> f32 {
+        self
+    }
+}
+
+/// 16-bit normalized depth.
+impl DepthFormat for u16 {
+    #[inline]
+    fn from_f32(depth: f32) -> Self {
+        libm::roundf(depth.clamp(0.0, 1.0) * u16::MAX as f32) as u16
+    }
+
+    #[inline]
+    fn to_f32(self) -> f32 {
+        self as f32 / u16::MAX as f32
+    }
+}
+
+/// 24-bit normalized depth packed into 3 little endian bytes.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+#[repr(transparent)]
+pub struct Depth24(pub [u8; 3]);
+
+impl Depth24 {
+    pub const MAX: u32 = (1 << 24) - 1;
+
+    /// Packs the low 24 bits of `bits`.
+    #[inline]
+    pub const fn from_bits(bits: u32) -> Self {
+        let [b0, b1, b2, _] = bits.to_le_bytes();
+        Self([b0, b1, b2])
+    }
+
+    #[inline]
+    pub const fn to_bits(self) -> u32 {
+        let [b0, b1, b2] = self.0;
+        u32::from_le_bytes([b0, b1, b2, 0])
+    }
+}
+
+impl DepthFormat for Depth24 {
+    #[inline]
+    fn from_f32(depth: f32) -> Self {
+        // `MAX` and every integer below it are exact in `f32`
+        Self::from_bits(libm::roundf(depth.clamp(0.0, 1.0) * Self::MAX as f32) as u32)
+    }
+
+    #[inline]
+    fn to_f32(self) -> f32 {
+        self.to_bits() as f32 / Self::MAX as f32
+    }
+}
+
+/// Loads up to [`LANES`] depth values for testing, the remaining lanes are zero.
+#[inline(always)]
+pub(crate) fn load<'a, D: DepthFormat + 'a>(depth: impl IntoIterator<Item = &'a D>) -> F32x8 {
+    let mut a = [0.0; LANES];
+    for (a, d) in a.iter_mut().zip(depth) {
+        *a = d.to_f32();
+    }
+    F32x8::from_array(a)
+}
+
+/// Converts interpolated depth to the format, returning the values to store and the values
+/// to test.
+#[inline(always)]
+pub(crate) fn quantize<D: DepthFormat>(z: F32x8) -> ([D; LANES], F32x8) {
+    let stored = z.to_array().map(D::from_f32);
+    (stored, F32x8::from_array(stored.map(D::to_f32)))
+}
+
+/// Per-draw depth test, used if the target has a depth attachment.
+///
+/// The default state keeps fragments closer than the stored depth and writes their depth.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DepthState {
+    /// The test passes if `depth compare stored` holds.
+    pub compare: Compare,
+    /// Whether fragments that pass every test write their depth.
+    pub write: bool,
+}
+
+impl Default for DepthState {
+    fn default() -> Self {
+        Self {
+            compare: Compare::Less,
+            write: true,
+        }
+    }
+}
+
+impl DepthState {
+    /// Reverse-Z keeps fragments with a greater depth, for projections that map the near
+    /// plane to `1` and the far plane to `0`.
+    ///
+    /// Floats are densest near `0`, which cancels out the `1 / z` distribution of depth
+    /// after the perspective divide and gives far away surfaces much more precision.
+    pub const REVERSE_Z: Self = Self {
+        compare: Compare::Greater,
+        write: true,
+    };
+
+    /// The far plane in normalized depth, to clear the depth attachment with.
+    ///
+    /// `0` if the test keeps greater depths and `1` otherwise.
+    pub fn clear_value(&self) -> f32 {
+        match self.compare {
+            Compare::Greater | Compare::GreaterEqual => 0.0,
+            _ => 1.0,
+        }
+    }
+
+    /// Bit `i` is set if lane `i` of `z` passes against lane `i` of `stored`.
+    #[inline]
+    pub(crate) fn test(&self, z: F32x8, stored: F32x8) -> u32 {
+        match self.compare {
+            Compare::Never => 0,
+            Compare::Less => stored.gt(z),
+            Compare::Equal => z.eq(stored),
+            Compare::LessEqual => z.le(stored),
+            Compare::Greater => z.gt(stored),
+            Compare::NotEqual => !z.eq(stored),
+            Compare::GreaterEqual => stored.le(z),
+            Compare::Always => u32::MAX,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tint::LinearRgb;
+
+    use super::*;
+    use crate::{ColorShader, DrawState, OwnedFramebuffer, StaticFramebuffer, draw_triangle};
+
+    const COMPARES: [Compare; 8] = [
+        Compare::Never,
+        Compare::Less,
+        Compare::Equal,
+        Compare::LessEqual,
+        Compare::Greater,
+        Compare::NotEqual,
+        Compare::GreaterEqual,
+        Compare::Always,
+    ];
+
+    // Draws a triangle covering the whole target at depth `z`.
+    fn draw_full(
+        target: &mut OwnedFramebuffer<LinearRgb>,
+        depth: DepthState,
+        z: f32,
+        color: LinearRgb,
+    ) {
+        let state = DrawState {
+            depth,
+            ..Default::default()
+        };
+        let (v1, v2, v3) = (
+            ([-1.0, -1.0, z, 1.0], color),
+            ([3.0, -1.0, z, 1.0], color),
+            ([-1.0, 3.0, z, 1.0], color),
+        );
+        draw_triangle(target, state, v1, v2, v3, ColorShader);
+    }
+
+    #[test]
+    fn u16_round_trips() {
+        for value in 0..=u16::MAX {
+            assert_eq!(u16::from_f32(value.to_f32()), value);
+        }
+        assert_eq!(0u16.to_f32(), 0.0);
+        assert_eq!(u16::MAX.to_f32(), 1.0);
+    }
+
+    #[test]
+    fn depth24_round_trips() {
+        for bits in (0..=Depth24::MAX)
+            .step_by(97)
+            .chain([1, Depth24::MAX - 1, Depth24::MAX])
+        {
+            let depth = Depth24::from_bits(bits);
+            assert_eq!(depth.to_bits(), bits);
+            assert_eq!(Depth24::from_f32(depth.to_f32()), depth);
+        }
+        assert_eq!(Depth24::from_bits(0x1234_5678), Depth24([0x78, 0x56, 0x34]));
+        assert_eq!(Depth24::from_bits(u32::MAX).to_bits(), Depth24::MAX);
+        assert_eq!(Depth24::from_bits(Depth24::MAX).to_f32(), 1.0);
+    }
+
+    #[test]
+    fn depth_is_rounded_to_the_nearest_value() {
+        let step = 1.0 / u16::MAX as f32;
+        assert_eq!(u16::from_f32(step * 0.49), 0);
+        assert_eq!(u16::from_f32(step * 0.51), 1);
+        assert_eq!(u16::from_f32(0.5), 32768);
+        assert_eq!(u16::from_f32(1.0 - step * 0.49), u16::MAX);
+
+        let step = 1.0 / Depth24::MAX as f32;
+        assert_eq!(Depth24::from_f32(step * 0.49).to_bits(), 0);
+        assert_eq!(Depth24::from_f32(step * 0.51).to_bits(), 1);
+        assert_eq!(Depth24::from_f32(0.5).to_bits(), 1 << 23);
+
+        for i in 0..=1000 {
+            let depth = i as f32 / 1000.0;
+            assert!((u16::from_f32(depth).to_f32() - depth).abs() <= 0.5 / u16::MAX as f32);
+            let error = (Depth24::from_f32(depth).to_f32() - depth).abs();
+            assert!(error <= 0.5 / Depth24::MAX as f32 + f32::EPSILON * 0.5);
+        }
+    }
+
+    #[test]
+    fn depth_outside_of_the_range_is_clamped() {
+        for depth in [-1.0, -f32::MIN_POSITIVE, f32::NEG_INFINITY] {
+            assert_eq!(u16::from_f32(depth), 0);
+            assert_eq!(Depth24::from_f32(depth).to_bits(), 0);
+        }
+        for depth in [1.0 + f32::EPSILON, 2.0, f32::MAX, f32::INFINITY] {
+            assert_eq!(u16::from_f32(depth), u16::MAX);
+            assert_eq!(Depth24::from_f32(depth).to_bits(), Depth24::MAX);
+        }
+    }
+
+    #[test]
+    fn clear_values_are_exact() {
+        let reverse = DepthState::REVERSE_Z.clear_value();
+        let forward = DepthState::default().clear_value();
+        assert_eq!((reverse, forward), (0.0, 1.0));
+
+        assert_eq!(u16::from_f32(reverse), 0);
+        assert_eq!(u16::from_f32(forward), u16::MAX);
+        assert_eq!(Depth24::from_f32(reverse).to_bits(), 0);
+        assert_eq!(Depth24::from_f32(forward).to_bits(), Depth24::MAX);
+        for clear in [reverse, forward] {
+            assert_eq!(u16::from_f32(clear).to_f32(), clear);
+            assert_eq!(Depth24::from_f32(clear).to_f32(), clear);
+        }
+
+        // nothing passes against the far plane in either direction, everything else does
+        let z = F32x8::from_array([0.0, 0.25, 0.5, 1.0, 0.0, 0.0, 0.0, 0.0]);
+        assert_eq!(
+            DepthState::default().test(z, F32x8::splat(forward)) & 0xf,
+            0b0111
+        );
+        assert_eq!(
+            DepthState::REVERSE_Z.test(z, F32x8::splat(reverse)) & 0xf,
+            0b1110
+        );
+    }
+
+    #[test]
+    fn every_compare_tests_depth_against_the_stored_value() {
+        let values = [0.0, 0.25, 0.5, 1.0];
+        for compare in COMPARES {
+            let state = DepthState {
+                compare,
+                write: true,
+            };
+            for stored in values {
+                let z = F32x8::from_array([0.0, 0.25, 0.5, 1.0, 0.0, 0.0, 0.0, 0.0]);
+                let passed = state.test(z, F32x8::splat(stored)) & 0xf;
+                let expected = values.iter().enumerate().fold(0, |mask, (i, &z)| {
+                    mask | (compare.test(z, stored) as u32) << i
+                });
+                assert_eq!(passed, expected, "{compare:?} {stored}");
+            }
+        }
+
+        // the fragment depth is on the left
+        assert!(Compare::Less.test(0.25, 0.5));
+        assert!(!Compare::Less.test(0.5, 0.25));
+        assert!(Compare::GreaterEqual.test(0.5, 0.5));
+        assert!(!Compare::NotEqual.test(0.5, 0.5));
+    }
+
+    #[test]
+    fn draws_keep_the_closest_fragments() {
+        let (red, green) = (LinearRgb::rgb(1.0, 0.0, 0.0), LinearRgb::rgb(0.0, 1.0, 0.0));
+        for (depth, near, far) in [
+            (DepthState::default(), 0.25, 0.75),
+            (DepthState::REVERSE_Z, 0.75, 0.25),
+        ] {
+            let black = LinearRgb::rgb(0.0, 0.0, 0.0);
+            let mut target = OwnedFramebuffer::new(8, 8, black).with_depth(depth.clear_value());
+
+            draw_full(&mut target, depth, far, green);
+            assert!(target.depth().unwrap().iter().all(|&z| z == far));
+            draw_full(&mut target, depth, near, red);
+            draw_full(&mut target, depth, far, green);
+            assert!(target.color().iter().all(|&c| c == red), "{depth:?}");
+            assert!(target.depth().unwrap().iter().all(|&z| z == near));
+
+            // the far plane itself is never drawn against the clear value
+            let mut target = OwnedFramebuffer::new(8, 8, black).with_depth(depth.clear_value());
+            draw_full(&mut target, depth, depth.clear_value(), red);
+            assert!(target.color().iter().all(|&c| c == black), "{depth:?}");
+        }
+    }
+
+    #[test]
+    fn draws_without_depth_writes_only_test() {
+        let (red, green) = (LinearRgb::rgb(1.0, 0.0, 0.0), LinearRgb::rgb(0.0, 1.0, 0.0));
+        let black = LinearRgb::rgb(0.0, 0.0, 0.0);
+        let mut target = OwnedFramebuffer::new(8, 8, black).with_depth(0.5);
+        let depth = DepthState {
+            write: false,
+            ..Default::default()
+        };
+
+        draw_full(&mut target, depth, 0.75, red);
+        assert!(target.color().iter().all(|&c| c == black));
+        draw_full(&mut target, depth, 0.25, red);
+        assert!(target.color().iter().all(|&c| c == red));
+        assert!(target.depth().unwrap().iter().all(|&z| z == 0.5));
+
+        // still tested against the stored depth
+        draw_full(&mut target, depth, 0.375, green);
+        assert!(target.color().iter().all(|&c| c == green));
+    }
+
+    #[test]
+    fn static_framebuffers_only_store_the_chosen_format() {
+        use core::mem::size_of;
+
+        type Target<D, S = ()> = StaticFramebuffer<u32, 8, 8, D, S>;
+        assert_eq!(size_of::<Target<()>>(), 8 * 8 * 4);
+        assert_eq!(size_of::<Target<[[u16; 8]; 8]>>(), 8 * 8 * 6);
+        assert_eq!(size_of::<Target<[[Depth24; 8]; 8]>>(), 8 * 8 * 7);
+        assert_eq!(
+            size_of::<Target<[[Depth24; 8]; 8], [[u8; 8]; 8]>>(),
+            8 * 8 * 8
+        );
+    }
+}
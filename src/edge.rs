@@ -0,0 +1,687 @@
+use crate::simd::{F32x8, LANES};
+use core::ops::{Add, AddAssign, Mul, Neg, Sub};
+
+/// Number type edge functions are evaluated in.
+pub trait EdgeValue:
+    Copy
+    + PartialOrd
+    + Add<Output = Self>
+    + AddAssign
+    + Sub<Output = Self>
+    + Mul<Output = Self>
+    + Neg<Output = Self>
+{
+    const ZERO: Self;
+
+    fn from_usize(v: usize) -> Self;
+
+    /// Rounds to the nearest value.
+    fn from_f32(v: f32) -> Self;
+
+    fn to_f32(self) -> f32;
+}
+
+impl EdgeValue for f32 {
+    const ZERO: Self = 0.0;
+
+    #[inline]
+    fn from_usize(v: usize) -> Self {
+        v as f32
+    }
+
+    #[inline]
+    fn from_f32(v: f32) -> Self {
+        v
+    }
+
+    #[inline]
+    fn to_f32(self) -> f32 {
+        self
+    }
+}
+
+impl EdgeValue for i64 {
+    const ZERO: Self = 0;
+
+    #[inline]
+    fn from_usize(v: usize) -> Self {
+        v as i64
+    }
+
+    #[inline]
+    fn from_f32(v: f32) -> Self {
+        libm::roundf(v) as i64
+    }
+
+    #[inline]
+    fn to_f32(self) -> f32 {
+        self as f32
+    }
+}
+
+/// Edge function `dx * (py - ay) - dy * (px - ax)` of one triangle edge.
+#[derive(Debug, Clone, Copy)]
+pub struct Edge<T> {
+    /// Vertex the edge function is evaluated from, see [`edge_function`].
+    pub a: (T, T),
+    pub dx: T,
+    pub dy: T,
+    /// The edge was flipped to evaluate it in canonical order.
+    pub negate: bool,
+    pub top_left: bool,
+}
+
+impl<T: EdgeValue> Edge<T> {
+    #[inline]
+    fn new(a: (T, T), b: (T, T)) -> Self {
+        let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+        let top_left = (dy == T::ZERO && dx > T::ZERO) || dy < T::ZERO;
+        let negate = canonical_swap(a, b);
+        let (a, dx, dy) = if negate { (b, -dx, -dy) } else { (a, dx, dy) };
+        Self {
+            a,
+            dx,
+            dy,
+            negate,
+            top_left,
+        }
+    }
+
+    #[inline]
+    fn row(&self, py: T) -> T {
+        self.dx * (py - self.a.1)
+    }
+
+    #[inline]
+    fn eval(&self, row: T, px: T) -> T {
+        let e = row - self.dy * (px - self.a.0);
+        if self.negate { -e } else { e }
+    }
+
+    /// Change of the edge function when the sample position moves by `offset`.
+    #[inline]
+    fn offset(&self, offset: (T, T)) -> T {
+        let e = self.dx * offset.1 - self.dy * offset.0;
+        if self.negate { -e } else { e }
+    }
+
+    /// Top-left fill rule.
+    #[inline]
+    pub fn covers(&self, value: T) -> bool {
+        value > T::ZERO || (value == T::ZERO && self.top_left)
+    }
+}
+
+/// Edge functions of a triangle, set up once and stepped across pixels.
+#[derive(Debug, Clone, Copy)]
+pub struct TriangleSetup<T> {
+    /// Edges opposite of the first, second and third vertex.
+    pub edges: [Edge<T>; 3],
+    pub inv_area: f32,
+    origin: (T, T),
+    one: T,
+}
+
+impl<T: EdgeValue> TriangleSetup<T> {
+    /// `origin` is the sample position of pixel `(0, 0)` and `one` the distance between
+    /// pixels. Either winding order is accepted. Returns `None` for degenerate triangles.
+    #[inline]
+    pub fn new(v1: (T, T), v2: (T, T), v3: (T, T), origin: (T, T), one: T) -> Option<Self> {
+        let area = edge_function(v1, v2, v3);
+        if area == T::ZERO {
+            return None;
+        }
+
+        // normalize to a positive winding so that the inside of every edge is positive
+        let edge = |a: (T, T), b: (T, T)| {
+            if area < T::ZERO {
+                Edge::new(b, a)
+            } else {
+                Edge::new(a, b)
+            }
+        };
+
+        let area = if area < T::ZERO { -area } else { area };
+        Some(Self {
+            edges: [edge(v2, v3), edge(v3, v1), edge(v1, v2)],
+            inv_area: 1.0 / area.to_f32(),
+            origin,
+            one,
+        })
+    }
+
+    /// Barycentric coordinates at pixel `(x, y)`, if it is covered.
+    #[inline]
+    pub fn barycentric(&self, x: usize, y: usize) -> Option<(f32, f32, f32)> {
+        let px = self.origin.0 + self.one * T::from_usize(x);
+        let py = self.origin.1 + self.one * T::from_usize(y);
+        let [e1, e2, e3] = &self.edges;
+        let w1 = e1.eval(e1.row(py), px);
+        let w2 = e2.eval(e2.row(py), px);
+        let w3 = e3.eval(e3.row(py), px);
+        (e1.covers(w1) && e2.covers(w2) && e3.covers(w3)).then(|| self.normalize(w1, w2, w3))
+    }
+
+    #[inline]
+    fn normalize(&self, w1: T, w2: T, w3: T) -> (f32, f32, f32) {
+        (
+            w1.to_f32() * self.inv_area,
+            w2.to_f32() * self.inv_area,
+            w3.to_f32() * self.inv_area,
+        )
+    }
+
+    // Edge function offsets of every sample, with `offsets` in pixels from the origin.
+    #[inline]
+    fn sample_offsets(&self, offsets: &[(f32, f32)]) -> [[T; 3]; MAX_SAMPLES] {
+        let mut deltas = [[T::ZERO; 3]; MAX_SAMPLES];
+        let one = self.one.to_f32();
+        for (delta, &(ox, oy)) in deltas.iter_mut().zip(offsets) {
+            let offset = (T::from_f32(ox * one), T::from_f32(oy * one));
+            *delta = self.edges.map(|e| e.offset(offset));
+        }
+        deltas
+    }
+}
+
+/// Blocks of [`LANES`] horizontally adjacent pixels.
+///
+/// Coverage is a bit mask and barycentric coordinates are normalized, see [`TriangleSetup::barycentric`].
+pub type Block = (u32, [F32x8; 3]);
+
+/// Most samples per pixel of a multisampled block.
+pub const MAX_SAMPLES: usize = 8;
+
+impl TriangleSetup<i64> {
+    /// Steps the edge functions over the pixels `minx..maxx` by `miny..maxy` and calls `f`
+    /// with the position of the first pixel of every block with covered pixels.
+    #[inline]
+    pub fn for_each_block(
+        &self,
+        minx: usize,
+        miny: usize,
+        maxx: usize,
+        maxy: usize,
+        mut f: impl FnMut(usize, usize, Block),
+    ) {
+        let [e1, e2, e3] = &self.edges;
+        let px = self.origin.0 + self.one * minx as i64;
+        let py = self.origin.1 + self.one * miny as i64;
+        let step_x = |e: &Edge<i64>| {
+            let step = -e.dy * self.one;
+            if e.negate { -step } else { step }
+        };
+        let step_y = |e: &Edge<i64>| {
+            let step = e.dx * self.one;
+            if e.negate { -step } else { step }
+        };
+        let (sx1, sx2, sx3) = (step_x(e1), step_x(e2), step_x(e3));
+        let (sy1, sy2, sy3) = (step_y(e1), step_y(e2), step_y(e3));
+
+        // Integer arithmetic is exact, so values are accumulated with one addition per edge
+        // and pixel.
+        let mut r1 = e1.eval(e1.row(py), px);
+        let mut r2 = e2.eval(e2.row(py), px);
+        let mut r3 = e3.eval(e3.row(py), px);
+        for y in miny..maxy {
+            let (mut w1, mut w2, mut w3) = (r1, r2, r3);
+            for x in (minx..maxx).step_by(LANES) {
+                let mut mask = 0;
+                let mut bc = [(0.0, 0.0, 0.0); LANES];
+                for (lane, b) in bc.iter_mut().enumerate().take(maxx - x) {
+                    if e1.covers(w1) && e2.covers(w2) && e3.covers(w3) {
+                        // only normalized for covered pixels
+                        mask |= 1 << lane;
+                        *b = self.normalize(w1, w2, w3);
+                    }
+                    w1 += sx1;
+                    w2 += sx2;
+                    w3 += sx3;
+                }
+                if mask != 0 {
+                    f(x, y, (mask, to_lanes(&bc)));
+                }
+            }
+            r1 += sy1;
+            r2 += sy2;
+            r3 += sy3;
+        }
+    }
+
+    /// Like [`TriangleSetup::for_each_block`], but covers pixels with any of the samples at
+    /// `offsets` from the origin covered.
+    ///
+    /// `f` is called with the barycentric coordinates at the origin for every pixel with a
+    /// covered sample, which may be outside of the triangle, and a block per sample.
+    #[inline]
+    pub fn for_each_multisample_block(
+        &self,
+        minx: usize,
+        miny: usize,
+        maxx: usize,
+        maxy: usize,
+        offsets: &[(f32, f32)],
+        mut f: impl FnMut(usize, usize, Block, &[Block]),
+    ) {
+        debug_assert!(offsets.len() <= MAX_SAMPLES);
+        let [e1, e2, e3] = &self.edges;
+        let deltas = self.sample_offsets(offsets);
+        let px = self.origin.0 + self.one * minx as i64;
+        let py = self.origin.1 + self.one * miny as i64;
+        let step_x = |e: &Edge<i64>| {
+            let step = -e.dy * self.one;
+            if e.negate { -step } else { step }
+        };
+        let step_y = |e: &Edge<i64>| {
+            let step = e.dx * self.one;
+            if e.negate { -step } else { step }
+        };
+        let (sx1, sx2, sx3) = (step_x(e1), step_x(e2), step_x(e3));
+        let (sy1, sy2, sy3) = (step_y(e1), step_y(e2), step_y(e3));
+
+        let mut r1 = e1.eval(e1.row(py), px);
+        let mut r2 = e2.eval(e2.row(py), px);
+        let mut r3 = e3.eval(e3.row(py), px);
+        let mut blocks = [(0, [F32x8::splat(0.0); 3]); MAX_SAMPLES];
+        for y in miny..maxy {
+            let (mut w1, mut w2, mut w3) = (r1, r2, r3);
+            for x in (minx..maxx).step_by(LANES) {
+                let mut coverage = 0;
+                let mut masks = [0; MAX_SAMPLES];
+                let mut bc = [(0.0, 0.0, 0.0); LANES];
+                let mut sample_bc = [[(0.0, 0.0, 0.0); LANES]; MAX_SAMPLES];
+                for lane in 0..LANES.min(maxx - x) {
+                    for (s, [d1, d2, d3]) in deltas.iter().take(offsets.len()).enumerate() {
+                        let (w1, w2, w3) = (w1 + *d1, w2 + *d2, w3 + *d3);
+                        if e1.covers(w1) && e2.covers(w2) && e3.covers(w3) {
+                            masks[s] |= 1 << lane;
+                            sample_bc[s][lane] = self.normalize(w1, w2, w3);
+                        }
+                    }
+                    if masks.iter().any(|mask| mask & (1 << lane) != 0) {
+                        coverage |= 1 << lane;
+                        bc[lane] = self.normalize(w1, w2, w3);
+                    }
+                    w1 += sx1;
+                    w2 += sx2;
+                    w3 += sx3;
+                }
+                if coverage != 0 {
+                    for (block, (mask, bc)) in blocks.iter_mut().zip(masks.iter().zip(&sample_bc)) {
+                        *block = (*mask, to_lanes(bc));
+                    }
+                    f(x, y, (coverage, to_lanes(&bc)), &blocks[..offsets.len()]);
+                }
+            }
+            r1 += sy1;
+            r2 += sy2;
+            r3 += sy3;
+        }
+    }
+}
+
+#[inline(always)]
+fn to_lanes(bc: &[(f32, f32, f32); LANES]) -> [F32x8; 3] {
+    [
+        F32x8::from_array(bc.map(|b| b.0)),
+        F32x8::from_array(bc.map(|b| b.1)),
+        F32x8::from_array(bc.map(|b| b.2)),
+    ]
+}
+
+impl TriangleSetup<f32> {
+    /// Evaluates the edge functions over the pixels `minx..maxx` by `miny..maxy` and calls
+    /// `f` with the position of the first pixel of every block with covered pixels.
+    ///
+    /// Unlike in integer arithmetic, the edge functions are not stepped, see
+    /// [`crate::Subpixel::Float`].
+    #[inline]
+    pub fn for_each_block(
+        &self,
+        minx: usize,
+        miny: usize,
+        maxx: usize,
+        maxy: usize,
+        mut f: impl FnMut(usize, usize, Block),
+    ) {
+        // Accumulating rounded values would make coverage depend on where the bounding box
+        // starts, so only the row terms are hoisted and the rest is evaluated per pixel in
+        // the same order as `edge_function`. Shared edges and vertices stay exact.
+        let edges = self.edges.map(|e| {
+            let top_left = if e.top_left { u32::MAX } else { 0 };
+            (e, F32x8::splat(e.a.0), F32x8::splat(e.dy), top_left)
+        });
+        let lanes = F32x8::from_array(F32x8::lanes());
+        let (origin, one) = (F32x8::splat(self.origin.0), F32x8::splat(self.one));
+        let inv_area = F32x8::splat(self.inv_area);
+        let zero = F32x8::splat(0.0);
+
+        for y in miny..maxy {
+            let py = self.origin.1 + self.one * y as f32;
+            let rows = edges.map(|(e, ..)| F32x8::splat(e.row(py)));
+            for x in (minx..maxx).step_by(LANES) {
+                let px = origin + one * (F32x8::splat(x as f32) + lanes);
+                let mut mask = u32::MAX >> (32 - LANES.min(maxx - x));
+                let mut w = [zero; 3];
+                for (i, (e, ax, dy, top_left)) in edges.iter().enumerate() {
+                    let v = rows[i] - *dy * (px - *ax);
+                    let v = if e.negate { -v } else { v };
+                    mask &= v.gt(zero) | (v.eq(zero) & top_left);
+                    w[i] = v;
+                }
+                if mask != 0 {
+                    // only used for covered pixels
+                    f(x, y, (mask, w.map(|w| w * inv_area)));
+                }
+            }
+        }
+    }
+
+    /// Like [`TriangleSetup::for_each_block`], but covers pixels with any of the samples at
+    /// `offsets` from the origin covered.
+    ///
+    /// `f` is called with the barycentric coordinates at the origin for every pixel with a
+    /// covered sample, which may be outside of the triangle, and a block per sample.
+    #[inline]
+    pub fn for_each_multisample_block(
+        &self,
+        minx: usize,
+        miny: usize,
+        maxx: usize,
+        maxy: usize,
+        offsets: &[(f32, f32)],
+        mut f: impl FnMut(usize, usize, Block, &[Block]),
+    ) {
+        debug_assert!(offsets.len() <= MAX_SAMPLES);
+        // Offsets are added to the edge functions at the origin in the same order for every
+        // triangle, which keeps shared edges exact like in `for_each_block`.
+        let edges = self.edges.map(|e| {
+            let top_left = if e.top_left { u32::MAX } else { 0 };
+            (e, F32x8::splat(e.a.0), F32x8::splat(e.dy), top_left)
+        });
+        let deltas = self.sample_offsets(offsets).map(|d| d.map(F32x8::splat));
+        let lanes = F32x8::from_array(F32x8::lanes());
+        let (origin, one) = (F32x8::splat(self.origin.0), F32x8::splat(self.one));
+        let inv_area = F32x8::splat(self.inv_area);
+        let zero = F32x8::splat(0.0);
+
+        let mut blocks = [(0, [zero; 3]); MAX_SAMPLES];
+        for y in miny..maxy {
+            let py = self.origin.1 + self.one * y as f32;
+            let rows = edges.map(|(e, ..)| F32x8::splat(e.row(py)));
+            for x in (minx..maxx).step_by(LANES) {
+                let px = origin + one * (F32x8::splat(x as f32) + lanes);
+                let lanes = u32::MAX >> (32 - LANES.min(maxx - x));
+                let w: [F32x8; 3] = core::array::from_fn(|i| {
+                    let (e, ax, dy, _) = edges[i];
+                    let v = rows[i] - dy * (px - ax);
+                    if e.negate { -v } else { v }
+                });
+                let mut coverage = 0;
+                for (block, delta) in blocks.iter_mut().zip(&deltas).take(offsets.len()) {
+                    let mut mask = lanes;
+                    let mut ws = [zero; 3];
+                    for (i, (.., top_left)) in edges.iter().enumerate() {
+                        let v = w[i] + delta[i];
+                        mask &= v.gt(zero) | (v.eq(zero) & top_left);
+                        ws[i] = v * inv_area;
+                    }
+                    coverage |= mask;
+                    *block = (mask, ws);
+                }
+                if coverage != 0 {
+                    // only used for covered pixels
+                    let bc = w.map(|w| w * inv_area);
+                    f(x, y, (coverage, bc), &blocks[..offsets.len()]);
+                }
+            }
+        }
+    }
+}
+
+/// Edge functions evaluated in either precision, see [`crate::Subpixel`].
+pub enum Setup {
+    Float(TriangleSetup<f32>),
+    Fixed(TriangleSetup<i64>),
+}
+
+impl Setup {
+    #[inline]
+    pub fn for_each_block(
+        &self,
+        minx: usize,
+        miny: usize,
+        maxx: usize,
+        maxy: usize,
+        f: impl FnMut(usize, usize, Block),
+    ) {
+        match self {
+            Self::Float(setup) => setup.for_each_block(minx, miny, maxx, maxy, f),
+            Self::Fixed(setup) => setup.for_each_block(minx, miny, maxx, maxy, f),
+        }
+    }
+
+    #[inline]
+    pub fn for_each_multisample_block(
+        &self,
+        minx: usize,
+        miny: usize,
+        maxx: usize,
+        maxy: usize,
+        offsets: &[(f32, f32)],
+        f: impl FnMut(usize, usize, Block, &[Block]),
+    ) {
+        match self {
+            Self::Float(setup) => {
+                setup.for_each_multisample_block(minx, miny, maxx, maxy, offsets, f)
+            }
+            Self::Fixed(setup) => {
+                setup.for_each_multisample_block(minx, miny, maxx, maxy, offsets, f)
+            }
+        }
+    }
+}
+
+#[inline]
+fn canonical_swap<T: EdgeValue>(a: (T, T), b: (T, T)) -> bool {
+    a.1 > b.1 || (a.1 == b.1 && a.0 > b.0)
+}
+
+/// Signed area of the parallelogram spanned by `a -> b` and `a -> p`.
+///
+/// Positive if `p` is to the right of `a -> b` with `y` pointing down.
+#[inline]
+pub fn edge_function<T: EdgeValue>(a: (T, T), b: (T, T), p: (T, T)) -> T {
+    // Always evaluated in the same vertex order so that an edge shared by two triangles
+    // produces exactly opposite values, otherwise rounding can open cracks along it.
+    if canonical_swap(a, b) {
+        -((a.0 - b.0) * (p.1 - b.1) - (a.1 - b.1) * (p.0 - b.0))
+    } else {
+        (b.0 - a.0) * (p.1 - a.1) - (b.1 - a.1) * (p.0 - a.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+
+    use super::*;
+    use crate::{SampleCount, barycentric_coordinates, testing};
+
+    // Bounds of the stepped pixels, not a multiple of the block size.
+    const MINX: usize = 3;
+    const MINY: usize = 2;
+    const MAXX: usize = 46;
+    const MAXY: usize = 37;
+
+    fn triangles() -> Vec<[(f32, f32); 3]> {
+        let mut random = testing::random(11);
+        let mut random = move || random() * 70.0 - 10.0;
+        let mut triangles: Vec<_> = (0..300)
+            .map(|_| [(); 3].map(|_| (random(), random())))
+            .collect();
+        // edges through pixel centers
+        triangles.push([(4.5, 2.5), (30.5, 2.5), (4.5, 30.5)]);
+        triangles.push([(30.5, 2.5), (30.5, 30.5), (4.5, 30.5)]);
+        triangles
+    }
+
+    // Four fractional bits.
+    fn snap(v: f32) -> i64 {
+        libm::roundf(v * 16.0) as i64
+    }
+
+    // Covered pixels and their barycentric coordinates, in the order they are stepped.
+    fn blocks(
+        for_each_block: impl FnOnce(&mut dyn FnMut(usize, usize, Block)),
+    ) -> Vec<(usize, usize, [f32; 3])> {
+        let mut pixels = Vec::new();
+        for_each_block(&mut |x, y, (mask, bc)| {
+            let bc = bc.map(F32x8::to_array);
+            for lane in (0..LANES).filter(|lane| mask & (1 << lane) != 0) {
+                pixels.push((x + lane, y, [bc[0][lane], bc[1][lane], bc[2][lane]]));
+            }
+        });
+        pixels
+    }
+
+    // Covered pixels evaluated one by one.
+    fn pixels(
+        mut f: impl FnMut(usize, usize) -> Option<(f32, f32, f32)>,
+    ) -> Vec<(usize, usize, [f32; 3])> {
+        let mut pixels = Vec::new();
+        for y in MINY..MAXY {
+            for x in MINX..MAXX {
+                if let Some((b1, b2, b3)) = f(x, y) {
+                    pixels.push((x, y, [b1, b2, b3]));
+                }
+            }
+        }
+        pixels
+    }
+
+    #[test]
+    fn float_blocks_match_every_pixel() {
+        for [a, b, c] in triangles() {
+            let Some(setup) = TriangleSetup::new(a, b, c, (0.5, 0.5), 1.0) else {
+                continue;
+            };
+            let blocks = blocks(|f| setup.for_each_block(MINX, MINY, MAXX, MAXY, f));
+            let expected = pixels(|x, y| {
+                let (px, py) = (x as f32 + 0.5, y as f32 + 0.5);
+                barycentric_coordinates([px, py], [a, b, c].map(|(x, y)| [x, y]))
+            });
+            assert_eq!(blocks, expected, "{a:?} {b:?} {c:?}");
+        }
+    }
+
+    #[test]
+    fn fixed_blocks_match_every_pixel() {
+        for triangle in triangles() {
+            let [a, b, c] = triangle.map(|(x, y)| (snap(x), snap(y)));
+            let Some(setup) = TriangleSetup::new(a, b, c, (8, 8), 16) else {
+                continue;
+            };
+            let blocks = blocks(|f| setup.for_each_block(MINX, MINY, MAXX, MAXY, f));
+            let expected = pixels(|x, y| setup.barycentric(x, y));
+            assert_eq!(blocks, expected, "{a:?} {b:?} {c:?}");
+        }
+    }
+
+    #[test]
+    fn float_and_fixed_blocks_agree_on_shared_edges() {
+        // cells of a mesh with corners on the grid of four fractional bits, where `f32`
+        // arithmetic is exact and snapping changes nothing
+        let mut random = testing::random(5);
+        let mut corner = |i: usize, j: usize| {
+            let at = |k: usize, r: f32| k as f32 * 6.0 + libm::roundf(r * 48.0) / 16.0;
+            (at(i, random()), at(j, random()))
+        };
+        let corners: Vec<Vec<(f32, f32)>> = (0..=8)
+            .map(|j| (0..=8).map(|i| corner(i, j)).collect())
+            .collect();
+        let mut covered = [[0; MAXX]; MAXY];
+        for j in 0..8 {
+            for i in 0..8 {
+                let [a, b, c, d] = [
+                    corners[j][i],
+                    corners[j][i + 1],
+                    corners[j + 1][i + 1],
+                    corners[j + 1][i],
+                ];
+                // the cell split along either diagonal, only the first split is counted
+                let split = [[a, b, c], [a, c, d], [b, a, d], [b, d, c]];
+                for (k, triangle) in split.into_iter().enumerate() {
+                    let [p1, p2, p3] = triangle;
+                    let float = TriangleSetup::new(p1, p2, p3, (0.5, 0.5), 1.0).unwrap();
+                    let [p1, p2, p3] = triangle.map(|(x, y)| (snap(x), snap(y)));
+                    let fixed = TriangleSetup::new(p1, p2, p3, (8, 8), 16).unwrap();
+                    let float = blocks(|f| float.for_each_block(MINX, MINY, MAXX, MAXY, f));
+                    let fixed = blocks(|f| fixed.for_each_block(MINX, MINY, MAXX, MAXY, f));
+                    assert_eq!(float, fixed, "{triangle:?}");
+                    if k < 2 {
+                        for (x, y, _) in float {
+                            covered[y][x] += 1;
+                        }
+                    }
+                }
+            }
+        }
+
+        // the mesh covers the stepped pixels inside of it exactly once
+        for (y, row) in covered.iter().enumerate().skip(MINY) {
+            for (x, &count) in row.iter().enumerate().skip(MINX) {
+                if x < 42 && y < 42 && x > 3 && y > 3 {
+                    assert_eq!(count, 1, "{x}, {y}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn multisample_blocks_match_every_sample() {
+        for samples in [SampleCount::X2, SampleCount::X4, SampleCount::X8] {
+            let offsets = samples.positions();
+            for triangle in triangles() {
+                let [a, b, c] = triangle.map(|(x, y)| (snap(x), snap(y)));
+                let Some(setup) = TriangleSetup::new(a, b, c, (8, 8), 16) else {
+                    continue;
+                };
+                let mut covered = Vec::new();
+                setup.for_each_multisample_block(
+                    MINX,
+                    MINY,
+                    MAXX,
+                    MAXY,
+                    offsets,
+                    |x, y, _, blocks| {
+                        for (s, (mask, bc)) in blocks.iter().enumerate() {
+                            let bc = bc.map(F32x8::to_array);
+                            for lane in (0..LANES).filter(|lane| mask & (1 << lane) != 0) {
+                                covered.push((
+                                    s,
+                                    x + lane,
+                                    y,
+                                    [bc[0][lane], bc[1][lane], bc[2][lane]],
+                                ));
+                            }
+                        }
+                    },
+                );
+                covered.sort_by_key(|&(s, x, y, _)| (s, y, x));
+
+                let mut expected = Vec::new();
+                for (s, &(ox, oy)) in offsets.iter().enumerate() {
+                    let origin = (8 + snap(ox), 8 + snap(oy));
+                    let sample = TriangleSetup::new(a, b, c, origin, 16).unwrap();
+                    expected.extend(
+                        pixels(|x, y| sample.barycentric(x, y))
+                            .into_iter()
+                            .map(|(x, y, bc)| (s, x, y, bc)),
+                    );
+                }
+                assert_eq!(covered, expected, "{samples:?} {a:?} {b:?} {c:?}");
+            }
+        }
+    }
+}
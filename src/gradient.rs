@@ -0,0 +1,410 @@
+use core::f32::consts::TAU;
+
+use tint::LinearRgb;
+
+use crate::{Fragment, Rgba, Shader, barycentric_lerp};
+
+/// Geometry of a [`Gradient`], in the space of its positions.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GradientKind {
+    /// Offsets run from `start` to `end` and are constant across the line between them.
+    Linear { start: (f32, f32), end: (f32, f32) },
+    /// Offsets run from `center` to the circle of `radius` around it.
+    Radial { center: (f32, f32), radius: f32 },
+    /// Offsets run once around `center`, clockwise on screen from the angle `angle` in
+    /// radians from the positive `x` axis.
+    Conic { center: (f32, f32), angle: f32 },
+}
+
+/// How offsets outside of `0..=1` continue the gradient.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum SpreadMode {
+    /// Extends the colors of the first and last stops.
+    #[default]
+    Pad,
+    /// Starts the gradient over.
+    Repeat,
+    /// Alternates between the gradient and its mirror image.
+    Reflect,
+}
+
+/// Space in which the colors of a [`Gradient`] are blended between stops.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ColorSpace {
+    /// Linear light, physically correct and brighter in the middle.
+    #[default]
+    Linear,
+    /// sRGB encoded, like CSS and most image editors.
+    Srgb,
+}
+
+/// Color of a [`Gradient`] at an offset.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GradientStop {
+    pub offset: f32,
+    pub color: Rgba,
+}
+
+impl GradientStop {
+    pub const fn new(offset: f32, color: Rgba) -> Self {
+        Self { offset, color }
+    }
+}
+
+/// Gradient paint, a [`Shader`] whose vertex data are positions.
+///
+/// Fragments are painted at their position interpolated linearly in screen space. Gradients
+/// fill triangles with their screen positions as vertex data, shapes with the corners of
+/// their bounds and polygons with [`rast_polygon_shaded`].
+///
+/// Stops should be sorted by offset, an offset smaller than the one before acts like it.
+/// Colors blend premultiplied by alpha, and gradients without stops discard every fragment.
+///
+/// [`rast_polygon_shaded`]: crate::rast_polygon_shaded
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Gradient<'a> {
+    pub kind: GradientKind,
+    pub stops: &'a [GradientStop],
+    pub spread: SpreadMode,
+    pub space: ColorSpace,
+}
+
+impl<'a> Gradient<'a> {
+    pub const fn new(kind: GradientKind, stops: &'a [GradientStop]) -> Self {
+        Self {
+            kind,
+            stops,
+            spread: SpreadMode::Pad,
+            space: ColorSpace::Linear,
+        }
+    }
+
+    /// Color of the gradient at `x`, `y`, or `None` without stops.
+    pub fn color(&self, x: f32, y: f32) -> Option<Rgba> {
+        let (first, last) = (self.stops.first()?, self.stops.last()?);
+
+        // degenerate gradients are painted with the last stop
+        let offset = match self.kind {
+            GradientKind::Linear { start, end } => {
+                let (dx, dy) = (end.0 - start.0, end.1 - start.1);
+                let length = dx * dx + dy * dy;
+                if !(length > 0.0 && length.is_finite()) {
+                    return Some(last.color);
+                }
+                ((x - start.0) * dx + (y - start.1) * dy) / length
+            }
+            GradientKind::Radial { center, radius } => {
+                if !(radius > 0.0 && radius.is_finite()) {
+                    return Some(last.color);
+                }
+                libm::hypotf(x - center.0, y - center.1) / radius
+            }
+            GradientKind::Conic { center, angle } => {
+                let turn = (libm::atan2f(y - center.1, x - center.0) - angle) / TAU;
+                turn - libm::floorf(turn)
+            }
+        };
+        let offset = match self.spread {
+            SpreadMode::Pad => offset,
+            SpreadMode::Repeat => offset - libm::floorf(offset),
+            SpreadMode::Reflect => {
+                let t = offset * 0.5;
+                1.0 - (2.0 * (t - libm::floorf(t)) - 1.0).abs()
+            }
+        };
+        if offset.is_nan() || offset <= first.offset {
+            return Some(first.color);
+        }
+
+        let mut prev = first;
+        let mut prev_offset = first.offset;
+        for stop in &self.stops[1..] {
+            let stop_offset = stop.offset.max(prev_offset);
+            if offset < stop_offset {
+                let t = (offset - prev_offset) / (stop_offset - prev_offset);
+                return Some(self.mix(prev.color, stop.color, t));
+            }
+            prev = stop;
+            prev_offset = stop_offset;
+        }
+        Some(last.color)
+    }
+
+    // Blends premultiplied colors in the color space of the gradient.
+    fn mix(&self, a: Rgba, b: Rgba, t: f32) -> Rgba {
+        let alpha = a.alpha + (b.alpha - a.alpha) * t;
+        if alpha <= 0.0 {
+            return Rgba::new(LinearRgb::rgb(0.0, 0.0, 0.0), 0.0);
+        }
+        let map = |c: LinearRgb, f: fn(f32) -> f32| match self.space {
+            ColorSpace::Linear => c,
+            ColorSpace::Srgb => LinearRgb::rgb(f(c.r), f(c.g), f(c.b)),
+        };
+        let a = map(a.color, srgb_encode) * a.alpha;
+        let b = map(b.color, srgb_encode) * b.alpha;
+        let color = (a * (1.0 - t) + b * t) * (1.0 / alpha);
+        Rgba::new(map(color, srgb_decode), alpha)
+    }
+}
+
+impl Shader for Gradient<'_> {
+    type VertexData = (f32, f32);
+
+    #[inline]
+    fn interpolate(
+        &self,
+        bcx: f32,
+        bcy: f32,
+        bcz: f32,
+        d1: Self::VertexData,
+        d2: Self::VertexData,
+        d3: Self::VertexData,
+    ) -> Self::VertexData {
+        (
+            barycentric_lerp(bcx, bcy, bcz, d1.0, d2.0, d3.0),
+            barycentric_lerp(bcx, bcy, bcz, d1.1, d2.1, d3.1),
+        )
+    }
+
+    /// Positions are interpolated in screen space, also across triangles in perspective.
+    #[inline]
+    fn interpolate_fragment(
+        &self,
+        _: (f32, f32, f32),
+        (bcx, bcy, bcz): (f32, f32, f32),
+        d1: Self::VertexData,
+        d2: Self::VertexData,
+        d3: Self::VertexData,
+    ) -> Self::VertexData {
+        self.interpolate(bcx, bcy, bcz, d1, d2, d3)
+    }
+
+    fn fragment(&mut self, (x, y): Self::VertexData) -> Fragment {
+        self.color(x, y).into()
+    }
+}
+
+// https://en.wikipedia.org/wiki/SRGB#Transfer_function_(%22gamma%22)
+fn srgb_encode(c: f32) -> f32 {
+    if c <= 0.0031308 {
+        c * 12.92
+    } else {
+        1.055 * libm::powf(c, 1.0 / 2.4) - 0.055
+    }
+}
+
+fn srgb_decode(c: f32) -> f32 {
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        libm::powf((c + 0.055) / 1.055, 2.4)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+
+    use super::*;
+    use crate::{FillRule, OwnedFramebuffer, rast_polygon_shaded};
+
+    fn gray(g: f32, alpha: f32) -> Rgba {
+        Rgba::new(LinearRgb::rgb(g, g, g), alpha)
+    }
+
+    // From opaque black at offset 0 to opaque white at offset 1.
+    const STOPS: [GradientStop; 2] = [
+        GradientStop::new(
+            0.0,
+            Rgba::new(
+                LinearRgb {
+                    r: 0.0,
+                    g: 0.0,
+                    b: 0.0,
+                },
+                1.0,
+            ),
+        ),
+        GradientStop::new(
+            1.0,
+            Rgba::new(
+                LinearRgb {
+                    r: 1.0,
+                    g: 1.0,
+                    b: 1.0,
+                },
+                1.0,
+            ),
+        ),
+    ];
+
+    const LINEAR: GradientKind = GradientKind::Linear {
+        start: (0.0, 0.0),
+        end: (10.0, 0.0),
+    };
+
+    #[track_caller]
+    fn assert_gray(gradient: &Gradient, (x, y): (f32, f32), expected: f32) {
+        let color = gradient.color(x, y).unwrap();
+        let LinearRgb { r, g, b } = color.color;
+        assert!(
+            [r, g, b].iter().all(|c| (c - expected).abs() < 1e-4) && color.alpha == 1.0,
+            "{x}, {y}: {color:?} instead of {expected}"
+        );
+    }
+
+    #[test]
+    fn kinds_map_positions_to_offsets() {
+        let linear = Gradient::new(LINEAR, &STOPS);
+        for (position, expected) in [((5.0, 3.0), 0.5), ((2.5, -7.0), 0.25), ((10.0, 1.0), 1.0)] {
+            assert_gray(&linear, position, expected);
+        }
+        let diagonal = GradientKind::Linear {
+            start: (2.0, 2.0),
+            end: (6.0, 6.0),
+        };
+        let diagonal = Gradient::new(diagonal, &STOPS);
+        for (position, expected) in [((4.0, 4.0), 0.5), ((2.0, 6.0), 0.5), ((5.0, 3.0), 0.5)] {
+            assert_gray(&diagonal, position, expected);
+        }
+        assert_gray(&diagonal, (3.0, 3.0), 0.25);
+
+        let radial = GradientKind::Radial {
+            center: (1.0, 1.0),
+            radius: 10.0,
+        };
+        let radial = Gradient::new(radial, &STOPS);
+        for (position, expected) in [((1.0, 1.0), 0.0), ((4.0, 5.0), 0.5), ((-5.0, 9.0), 1.0)] {
+            assert_gray(&radial, position, expected);
+        }
+
+        // clockwise on screen, where y points down
+        let conic = GradientKind::Conic {
+            center: (0.0, 0.0),
+            angle: 0.0,
+        };
+        let conic = Gradient::new(conic, &STOPS);
+        for (position, expected) in [((1.0, 0.0), 0.0), ((0.0, 1.0), 0.25), ((-1.0, 0.0), 0.5)] {
+            assert_gray(&conic, position, expected);
+        }
+        assert_gray(&conic, (0.0, -1.0), 0.75);
+        let rotated = GradientKind::Conic {
+            center: (0.0, 0.0),
+            angle: core::f32::consts::FRAC_PI_2,
+        };
+        assert_gray(&Gradient::new(rotated, &STOPS), (-1.0, 0.0), 0.25);
+    }
+
+    #[test]
+    fn spread_modes_continue_outside_of_the_stops() {
+        for (spread, expected) in [
+            (SpreadMode::Pad, [0.0, 0.0, 1.0, 1.0]),
+            (SpreadMode::Repeat, [0.25, 0.75, 0.25, 0.25]),
+            (SpreadMode::Reflect, [0.25, 0.25, 0.75, 0.25]),
+        ] {
+            let gradient = Gradient {
+                spread,
+                ..Gradient::new(LINEAR, &STOPS)
+            };
+            // offsets -1.75, -0.25, 1.25 and 2.25
+            for (x, expected) in [-17.5, -2.5, 12.5, 22.5].into_iter().zip(expected) {
+                assert_gray(&gradient, (x, 0.0), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn stops_are_interpolated_in_order() {
+        let red = Rgba::new(LinearRgb::rgb(1.0, 0.0, 0.0), 1.0);
+        let stops = [
+            GradientStop::new(0.2, gray(0.0, 1.0)),
+            GradientStop::new(0.6, gray(0.8, 1.0)),
+            // a hard stop, and one before it acting like one at the same offset
+            GradientStop::new(0.6, red),
+            GradientStop::new(0.4, gray(1.0, 1.0)),
+        ];
+        let gradient = Gradient::new(LINEAR, &stops);
+        assert_gray(&gradient, (1.0, 0.0), 0.0);
+        assert_gray(&gradient, (4.0, 0.0), 0.4);
+        assert_gray(&gradient, (5.9, 0.0), 0.78);
+        assert_gray(&gradient, (6.0, 0.0), 1.0);
+        assert_gray(&gradient, (9.0, 0.0), 1.0);
+
+        // degenerate gradients are painted with the last stop
+        let point = GradientKind::Linear {
+            start: (1.0, 1.0),
+            end: (1.0, 1.0),
+        };
+        let empty = GradientKind::Radial {
+            center: (1.0, 1.0),
+            radius: 0.0,
+        };
+        for kind in [point, empty] {
+            assert_gray(&Gradient::new(kind, &STOPS), (3.0, 0.0), 1.0);
+        }
+
+        // and ones without stops discard everything
+        let mut gradient = Gradient::new(LINEAR, &[]);
+        assert_eq!(gradient.color(1.0, 0.0), None);
+        assert_eq!(gradient.fragment((1.0, 0.0)), Fragment::Discard);
+    }
+
+    #[test]
+    fn colors_are_mixed_in_their_space() {
+        let linear = Gradient::new(LINEAR, &STOPS);
+        assert_gray(&linear, (5.0, 0.0), 0.5);
+        let srgb = Gradient {
+            space: ColorSpace::Srgb,
+            ..linear
+        };
+        assert_gray(&srgb, (5.0, 0.0), srgb_decode(0.5));
+        assert_gray(&srgb, (2.0, 0.0), srgb_decode(0.2));
+        // the stops keep their colors
+        assert_gray(&srgb, (0.0, 0.0), 0.0);
+        assert_gray(&srgb, (10.0, 0.0), 1.0);
+        for c in [0.0, 0.001, 0.04, 0.2, 0.5, 1.0] {
+            assert!((srgb_decode(srgb_encode(c)) - c).abs() < 1e-5, "{c}");
+        }
+
+        // colors are premultiplied, so transparent white doesn't brighten black
+        let stops = [
+            GradientStop::new(0.0, gray(1.0, 0.0)),
+            GradientStop::new(1.0, gray(0.0, 1.0)),
+        ];
+        for space in [ColorSpace::Linear, ColorSpace::Srgb] {
+            let gradient = Gradient {
+                space,
+                ..Gradient::new(LINEAR, &stops)
+            };
+            let color = gradient.color(5.0, 0.0).unwrap();
+            assert_eq!(color.color, LinearRgb::rgb(0.0, 0.0, 0.0), "{space:?}");
+            assert!((color.alpha - 0.5).abs() < 1e-6, "{space:?}");
+        }
+    }
+
+    #[test]
+    fn polygons_are_painted_at_their_pixel_centers() {
+        let black = LinearRgb::rgb(0.0, 0.0, 0.0);
+        let mut target = OwnedFramebuffer::new(16, 4, black);
+        let kind = GradientKind::Linear {
+            start: (0.0, 0.0),
+            end: (16.0, 0.0),
+        };
+        let rect: &[(f32, f32)] = &[(0.0, 0.0), (16.0, 0.0), (16.0, 4.0), (0.0, 4.0)];
+        rast_polygon_shaded(
+            &mut target,
+            &[rect],
+            FillRule::NonZero,
+            Gradient::new(kind, &STOPS),
+        );
+        let row: Vec<f32> = target.color()[..16].iter().map(|c| c.r).collect();
+        for (x, &c) in row.iter().enumerate() {
+            let expected = (x as f32 + 0.5) / 16.0;
+            assert!(
+                (c - expected).abs() < 1e-4,
+                "{x}: {c} instead of {expected}"
+            );
+        }
+        assert_eq!(&target.color()[48..], &target.color()[..16]);
+    }
+}
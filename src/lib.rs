@@ -1,7 +1,7 @@
 // TODO: There is either a precision issue in the zbuffer or a bug somewhere upstream
 // causing z fighting.
 
-#![no_std]
+#![cfg_attr(not(feature = "std"), no_std)]
 extern crate alloc;
 
 use core::marker::PhantomData;
@@ -9,86 +9,315 @@ use tint::*;
 
 pub use tint;
 
+mod blend;
+mod clip;
+mod depth;
+mod edge;
+mod gradient;
+mod line;
+mod multisample;
+mod path;
+mod point;
+mod polygon;
+mod shape;
+mod simd;
+mod stencil;
+mod stroke;
+mod target;
+#[cfg(test)]
+mod testing;
+#[cfg(feature = "parallel")]
+pub mod tiled;
+mod vertex;
+
+pub use blend::{BlendFactor, BlendOp, BlendState, Rgba};
+pub use depth::{Depth24, DepthFormat, DepthState};
+pub use gradient::{ColorSpace, Gradient, GradientKind, GradientStop, SpreadMode};
+pub use line::LineTopology;
+pub use multisample::SampleCount;
+pub use path::{Path, Transform};
+pub use point::{PointShape, PointStyle};
+pub use polygon::FillRule;
+pub use shape::Shape;
+pub use stencil::{StencilFace, StencilOp, StencilState};
+pub use stroke::{LineCap, LineJoin, StrokeStyle};
+pub use target::{
+    Framebuffer, OwnedFramebuffer, RenderTarget, SizeError, StaticDepth, StaticFramebuffer,
+    StaticStencil,
+};
+
+use edge::{MAX_SAMPLES, TriangleSetup};
+use polygon::Polygon;
+use shape::Analytic;
+use simd::F32x8;
+use stroke::Stroker;
+use vertex::VertexCache;
+
+/// Rasterizes a triangle between `vertices` at `[x, y]` in screen space without depth
+/// testing.
 pub fn rast_triangle<S: Shader, Pixel: Color>(
-    pixels: &mut [Pixel],
-    width: usize,
-    height: usize,
-    v1x: f32,
-    v1y: f32,
-    v2x: f32,
-    v2y: f32,
-    v3x: f32,
-    v3y: f32,
-    d1: S::VertexData,
-    d2: S::VertexData,
-    d3: S::VertexData,
-    shader: S,
+    target: &mut impl RenderTarget<Pixel = Pixel>,
+    vertices: [([f32; 2], S::VertexData); 3],
+    mut shader: S,
 ) {
-    #[rustfmt::skip]
+    let mut target = target.framebuffer().color_only();
+    let tile = Tile::full(target.width, target.height);
     rast_triangle_inner(
-        pixels,
-        &mut [],
-        width,
-        height,
-        v1x, v1y, 0.0,
-        v2x, v2y, 0.0,
-        v3x, v3y, 0.0,
-        d1,
-        d2,
-        d3,
-        shader,
-        false,
+        &mut target,
+        vertices.map(|([x, y], d)| ([x, y, 0.0, 1.0], d)),
+        &mut shader,
+        DrawState::default(),
+        true,
+        tile,
     );
 }
 
-pub fn rast_triangle_checked<S: Shader>(
-    pixels: &mut [Srgb],
-    zbuffer: &mut [f32],
-    width: usize,
-    height: usize,
-    v1x: f32,
-    v1y: f32,
-    v1z: f32,
-    v2x: f32,
-    v2y: f32,
-    v2z: f32,
-    v3x: f32,
-    v3y: f32,
-    v3z: f32,
-    d1: S::VertexData,
-    d2: S::VertexData,
-    d3: S::VertexData,
-    shader: S,
+/// Rasterizes a triangle between `vertices` at `[x, y, z]` in screen space, testing `z`
+/// against the depth attachment and running the stencil test of the `state` on the stencil
+/// attachment if the target has them.
+///
+/// Facing is determined as if `y` pointed up, like in normalized device coordinates.
+pub fn rast_triangle_checked<S: Shader, Pixel: Color>(
+    target: &mut impl RenderTarget<Pixel = Pixel>,
+    state: DrawState,
+    vertices: [([f32; 3], S::VertexData); 3],
+    mut shader: S,
 ) {
-    #[rustfmt::skip]
+    let [v1, v2, v3] = vertices.map(|([x, y, _], _)| [x, -y, 0.0, 1.0]);
+    let Some(front_facing) = state.facing(v1, v2, v3) else {
+        return;
+    };
+
+    let mut target = target.framebuffer();
+    let tile = Tile::full(target.width, target.height);
     rast_triangle_inner(
-        pixels,
-        zbuffer,
-        width,
-        height,
-        v1x, v1y, v1z,
-        v2x, v2y, v2z,
-        v3x, v3y, v3z,
-        d1,
-        d2,
-        d3,
-        shader,
-        true,
+        &mut target,
+        vertices.map(|([x, y, z], d)| ([x, y, z, 1.0], d)),
+        &mut shader,
+        state,
+        front_facing,
+        tile,
     );
 }
 
+/// Rasterizes a triangle, running the vertex stage of the `shader` on each vertex.
+///
+/// [`VertexShader::vertex`] outputs positions in homogeneous clip space, `[x, y, z, w]`
+/// before the perspective divide. The triangle is culled according to the `state`, clipped
+/// against the view frustum (`-w <= x <= w`, `-w <= y <= w`, `0 <= z <= w`), divided by
+/// `w` and mapped to the viewport with `y` pointing down. `z / w` is tested against the
+/// depth attachment and the stencil test runs on the stencil attachment if the target has
+/// them.
+pub fn draw_triangle<V, S: VertexShader<V>, Pixel: Color>(
+    target: &mut impl RenderTarget<Pixel = Pixel>,
+    state: DrawState,
+    v1: V,
+    v2: V,
+    v3: V,
+    mut shader: S,
+) {
+    let vertices = [v1, v2, v3].map(|v| shader.vertex(&v));
+    draw_clip_triangle(&mut target.framebuffer(), state, vertices, &mut shader);
+}
+
+/// Draws a triangle list of `vertices` in homogeneous clip space.
+///
+/// Every three `indices` form a triangle, a trailing incomplete triangle is ignored. The
+/// vertex stage runs once for every distinct index, transformed vertices are kept in a
+/// buffer with an entry for every index up to the largest one. Otherwise the same as
+/// [`draw_triangle`].
+///
+/// Panics if an index is out of bounds of `vertices`.
+pub fn draw_indexed<V, S: VertexShader<V>, Pixel: Color>(
+    target: &mut impl RenderTarget<Pixel = Pixel>,
+    state: DrawState,
+    vertices: &[V],
+    indices: &[u32],
+    mut shader: S,
+) {
+    let mut target = target.framebuffer();
+    let mut cache = VertexCache::new(indices);
+    for triangle in indices.chunks_exact(3) {
+        let vertices = [triangle[0], triangle[1], triangle[2]]
+            .map(|i| cache.get_or_insert(i, || shader.vertex(&vertices[i as usize])));
+        draw_clip_triangle(&mut target, state, vertices, &mut shader);
+    }
+}
+
+// Culls, clips and rasterizes a triangle that went through the vertex stage.
+fn draw_clip_triangle<S: Shader, Pixel: Color, D: DepthFormat>(
+    target: &mut Framebuffer<'_, Pixel, D>,
+    state: DrawState,
+    [v1, v2, v3]: [([f32; 4], S::VertexData); 3],
+    shader: &mut S,
+) {
+    let Some(front_facing) = state.facing(v1.0, v2.0, v3.0) else {
+        return;
+    };
+
+    let (width, height) = (target.width, target.height);
+    let Some(polygon) = clip::clip_and_project(shader, width, height, v1, v2, v3) else {
+        return;
+    };
+
+    for i in 1..polygon.len - 1 {
+        let vertices = [
+            polygon.vertices[0],
+            polygon.vertices[i],
+            polygon.vertices[i + 1],
+        ];
+        let tile = Tile::full(width, height);
+        rast_triangle_inner(target, vertices, shader, state, front_facing, tile);
+    }
+}
+
+/// Rasterizes a line, running the vertex stage of the `shader` on each vertex.
+///
+/// The line is clipped against the view frustum and mapped to the viewport like in
+/// [`draw_triangle`], then shaded with the barycentric coordinates `(1 - t, t, 0)` along the
+/// line and the data of the first vertex repeated as the third. The start pixel is drawn and
+/// the end pixel is not, so lines that continue each other don't overlap. Depth, stencil and
+/// blend states apply as for front facing triangles.
+pub fn draw_line<V, S: VertexShader<V>, Pixel: Color>(
+    target: &mut impl RenderTarget<Pixel = Pixel>,
+    state: DrawState,
+    v1: V,
+    v2: V,
+    mut shader: S,
+) {
+    let vertices = [v1, v2].map(|v| shader.vertex(&v));
+    draw_clip_line(&mut target.framebuffer(), state, vertices, &mut shader);
+}
+
+/// Draws lines between `vertices` connected by the `topology`, running the vertex stage
+/// once per vertex. Otherwise the same as [`draw_line`].
+pub fn draw_lines<V, S: VertexShader<V>, Pixel: Color>(
+    target: &mut impl RenderTarget<Pixel = Pixel>,
+    state: DrawState,
+    topology: LineTopology,
+    vertices: &[V],
+    mut shader: S,
+) {
+    let mut target = target.framebuffer();
+    match topology {
+        LineTopology::List => {
+            for line in vertices.chunks_exact(2) {
+                let vertices = [shader.vertex(&line[0]), shader.vertex(&line[1])];
+                draw_clip_line(&mut target, state, vertices, &mut shader);
+            }
+        }
+        LineTopology::Strip => {
+            let Some((first, rest)) = vertices.split_first() else {
+                return;
+            };
+            let mut v1 = shader.vertex(first);
+            for vertex in rest {
+                let v2 = shader.vertex(vertex);
+                draw_clip_line(&mut target, state, [v1, v2], &mut shader);
+                v1 = v2;
+            }
+        }
+    }
+}
+
+// Clips and rasterizes a line that went through the vertex stage.
+fn draw_clip_line<S: Shader, Pixel: Color, D: DepthFormat>(
+    target: &mut Framebuffer<'_, Pixel, D>,
+    state: DrawState,
+    [v1, v2]: [([f32; 4], S::VertexData); 2],
+    shader: &mut S,
+) {
+    let (width, height) = (target.width, target.height);
+    let Some(vertices) = clip::clip_and_project_line(shader, width, height, v1, v2) else {
+        return;
+    };
+    line::rast_line_shaded(target, vertices, shader, state);
+}
+
+/// Rasterizes a point centered at `[x, y]` in screen space without depth testing, see
+/// [`draw_point`].
+pub fn rast_point<S: Shader, Pixel: Color>(
+    target: &mut impl RenderTarget<Pixel = Pixel>,
+    style: PointStyle,
+    [x, y]: [f32; 2],
+    d: S::VertexData,
+    mut shader: S,
+) {
+    let mut target = target.framebuffer().color_only();
+    point::rast_point_inner(
+        &mut target,
+        DrawState::default(),
+        style,
+        [x, y, 0.0],
+        d,
+        &mut shader,
+    );
+}
+
+/// Rasterizes a point centered at `[x, y, z]` in screen space, testing `z` against the
+/// depth attachment and running the stencil test like [`rast_triangle_checked`].
+pub fn rast_point_checked<S: Shader, Pixel: Color>(
+    target: &mut impl RenderTarget<Pixel = Pixel>,
+    state: DrawState,
+    style: PointStyle,
+    position: [f32; 3],
+    d: S::VertexData,
+    mut shader: S,
+) {
+    let mut target = target.framebuffer();
+    point::rast_point_inner(&mut target, state, style, position, d, &mut shader);
+}
+
+/// Draws a point, running the vertex stage of the `shader` on its vertex.
+///
+/// Points whose center is outside of the view frustum are discarded, the others are drawn
+/// with the size and shape of the `style` in pixels and tested like front facing triangles.
+/// Fragments get the vertex data of [`Shader::point_sprite`] at their position on the
+/// point, which textures sprites with [`TextureShader`].
+pub fn draw_point<V, S: VertexShader<V>, Pixel: Color>(
+    target: &mut impl RenderTarget<Pixel = Pixel>,
+    state: DrawState,
+    style: PointStyle,
+    v: V,
+    shader: S,
+) {
+    draw_points(target, state, style, core::slice::from_ref(&v), shader);
+}
+
+/// Draws a point for each of the `vertices`, see [`draw_point`].
+pub fn draw_points<V, S: VertexShader<V>, Pixel: Color>(
+    target: &mut impl RenderTarget<Pixel = Pixel>,
+    state: DrawState,
+    style: PointStyle,
+    vertices: &[V],
+    mut shader: S,
+) {
+    let mut target = target.framebuffer();
+    let (width, height) = (target.width, target.height);
+    for vertex in vertices {
+        let (v, d) = shader.vertex(vertex);
+        let Some([x, y, z, _]) = clip::clip_and_project_point(v, width, height) else {
+            continue;
+        };
+        point::rast_point_inner(&mut target, state, style, [x, y, z], d, &mut shader);
+    }
+}
+
+/// Fills a triangle between `vertices` at `[x, y]` in screen space with a single color.
 pub fn rast_triangle_colored<Pixel: Copy>(
-    pixels: &mut [Pixel],
-    width: usize,
-    height: usize,
-    v1x: f32,
-    v1y: f32,
-    v2x: f32,
-    v2y: f32,
-    v3x: f32,
-    v3y: f32,
+    target: &mut impl RenderTarget<Pixel = Pixel>,
+    vertices: [[f32; 2]; 3],
     c: Pixel,
 ) {
+    let [[v1x, v1y], [v2x, v2y], [v3x, v3y]] = vertices;
+    let Framebuffer {
+        color: pixels,
+        width,
+        height,
+        samples,
+        ..
+    } = target.framebuffer();
+
     // bounding box clip
     let minx = (v1x.min(v2x).min(v3x).max(0.0)) as usize;
     let maxx = libm::ceilf(v1x.max(v2x).max(v3x).min(width as f32)) as usize;
@@ -98,81 +327,80 @@ pub fn rast_triangle_colored<Pixel: Copy>(
         return;
     }
 
-    for y in miny..maxy.min(height) {
-        for x in minx..maxx.min(width) {
-            if barycentric_coordinates(x as f32, y as f32, v1x, v1y, v2x, v2y, v3x, v3y).is_some() {
-                let index = y * width + x;
-                pixels[index] = c;
+    let Some(setup) = TriangleSetup::new((v1x, v1y), (v2x, v2y), (v3x, v3y), (0.5, 0.5), 1.0)
+    else {
+        return;
+    };
+    let (maxx, maxy) = (maxx.min(width), maxy.min(height));
+    let n = samples.count();
+    if n == 1 {
+        setup.for_each_block(minx, miny, maxx, maxy, |x, y, (mask, _)| {
+            let index = y * width + x;
+            let len = BLOCK_SIZE.min(width - x);
+            for (i, pixel) in pixels[index..index + len].iter_mut().enumerate() {
+                if mask & (1 << i) != 0 {
+                    *pixel = c;
+                }
             }
-        }
+        });
+        return;
     }
+
+    let offsets = samples.positions();
+    setup.for_each_multisample_block(minx, miny, maxx, maxy, offsets, |x, y, _, blocks| {
+        let index = y * width + x;
+        let len = BLOCK_SIZE.min(width - x);
+        let pixels = &mut pixels[index * n..(index + len) * n];
+        for (s, (mask, _)) in blocks.iter().enumerate() {
+            for (i, pixel) in pixels.iter_mut().skip(s).step_by(n).enumerate() {
+                if mask & (1 << i) != 0 {
+                    *pixel = c;
+                }
+            }
+        }
+    });
 }
 
+/// Rasterizes a line between `vertices` at `[x, y, z]` in screen space without depth
+/// testing.
 pub fn rast_line<Pixel: Copy>(
-    pixels: &mut [Pixel],
-    width: usize,
-    height: usize,
-    v1x: f32,
-    v1y: f32,
-    v1z: f32,
-    v2x: f32,
-    v2y: f32,
-    v2z: f32,
+    target: &mut impl RenderTarget<Pixel = Pixel>,
+    vertices: [[f32; 3]; 2],
     c: Pixel,
 ) {
-    #[rustfmt::skip]
-    rast_line_inner(
-        pixels,
-        &mut [],
-        width,
-        height,
-        v1x, v1y, v1z,
-        v2x, v2y, v2z,
-        c,
-        false,
-    );
+    let mut target = target.framebuffer().color_only();
+    rast_line_inner(&mut target, DepthState::default(), vertices, c);
 }
 
+/// Rasterizes a line in screen space, testing `z` against the depth attachment with the
+/// depth state of the `state` if the target has one.
 pub fn rast_line_checked<Pixel: Copy>(
-    pixels: &mut [Pixel],
-    zbuffer: &mut [f32],
-    width: usize,
-    height: usize,
-    v1x: f32,
-    v1y: f32,
-    v1z: f32,
-    v2x: f32,
-    v2y: f32,
-    v2z: f32,
+    target: &mut impl RenderTarget<Pixel = Pixel>,
+    state: DrawState,
+    vertices: [[f32; 3]; 2],
     c: Pixel,
 ) {
-    #[rustfmt::skip]
-    rast_line_inner(
-        pixels,
-        zbuffer,
-        width,
-        height,
-        v1x, v1y, v1z,
-        v2x, v2y, v2z,
-        c,
-        true,
-    );
+    rast_line_inner(&mut target.framebuffer(), state.depth, vertices, c);
 }
 
-fn rast_line_inner<Pixel: Copy>(
-    pixels: &mut [Pixel],
-    zbuffer: &mut [f32],
-    width: usize,
-    height: usize,
-    v1x: f32,
-    v1y: f32,
-    v1z: f32,
-    v2x: f32,
-    v2y: f32,
-    v2z: f32,
+fn rast_line_inner<Pixel: Copy, D: DepthFormat>(
+    target: &mut Framebuffer<'_, Pixel, D>,
+    depth: DepthState,
+    [[v1x, v1y, v1z], [v2x, v2y, v2z]]: [[f32; 3]; 2],
     c: Pixel,
-    depth_check: bool,
 ) {
+    let (pixels, mut zbuffer) = (&mut *target.color, target.depth.as_deref_mut());
+    let (width, height, samples) = (target.width, target.height, target.samples);
+    // only the part inside of the target is stepped
+    let bounds = (0.0, 0.0, width as f32, height as f32);
+    let Some((t0, t1)) = clip::clip_segment((v1x, v1y), (v2x, v2y), bounds) else {
+        return;
+    };
+    let (dx, dy, dz) = (v2x - v1x, v2y - v1y, v2z - v1z);
+    let at = |t: f32| (v1x + dx * t, v1y + dy * t, v1z + dz * t);
+    let (v1x, v1y, v1z) = if t0 > 0.0 { at(t0) } else { (v1x, v1y, v1z) };
+    let (v2x, v2y, v2z) = if t1 < 1.0 { at(t1) } else { (v2x, v2y, v2z) };
+
     let dx = v2x - v1x;
     let dy = v2y - v1y;
     if dx.abs() < 1.0 && dy.abs() < 1.0 {
@@ -198,194 +426,786 @@ fn rast_line_inner<Pixel: Copy>(
         let pixel_y = libm::floorf(y) as i32;
 
         if pixel_x >= 0 && pixel_x < width as i32 && pixel_y >= 0 && pixel_y < height as i32 {
-            let index = (pixel_y as usize) * width + (pixel_x as usize);
-
-            if depth_check {
-                if zbuffer[index] <= z {
-                    continue;
+            // lines cover every sample of a pixel
+            let n = samples.count();
+            let index = ((pixel_y as usize) * width + (pixel_x as usize)) * n;
+            for index in index..index + n {
+                if let Some(zbuffer) = zbuffer.as_deref_mut() {
+                    let z = D::from_f32(z);
+                    if !depth.compare.test(z.to_f32(), zbuffer[index].to_f32()) {
+                        continue;
+                    }
+                    if depth.write {
+                        zbuffer[index] = z;
+                    }
                 }
-                zbuffer[index] = z;
+                pixels[index] = c;
             }
-            pixels[index] = c;
         }
     }
 }
 
 pub fn rast_triangle_wireframe<Pixel: Copy>(
-    pixels: &mut [Pixel],
-    width: usize,
-    height: usize,
-    v1x: f32,
-    v1y: f32,
-    v1z: f32,
-    v2x: f32,
-    v2y: f32,
-    v2z: f32,
-    v3x: f32,
-    v3y: f32,
-    v3z: f32,
+    target: &mut impl RenderTarget<Pixel = Pixel>,
+    [v1, v2, v3]: [[f32; 3]; 3],
     c: Pixel,
 ) {
-    #[rustfmt::skip]
-    rast_line(
-        pixels,
-        width,
-        height,
-        v1x, v1y, v1z,
-        v2x, v2y, v2z,
-        c,
-    );
-    #[rustfmt::skip]
-    rast_line(
-        pixels,
-        width,
-        height,
-        v1x, v1y, v1z,
-        v3x, v3y, v3z,
-        c,
-    );
-    #[rustfmt::skip]
-    rast_line(
-        pixels,
-        width,
-        height,
-        v2x, v2y, v2z,
-        v3x, v3y, v3z,
-        c,
-    );
+    rast_line(target, [v1, v2], c);
+    rast_line(target, [v1, v3], c);
+    rast_line(target, [v2, v3], c);
 }
 
 pub fn rast_triangle_wireframe_checked<Pixel: Copy>(
-    pixels: &mut [Pixel],
-    zbuffer: &mut [f32],
-    width: usize,
-    height: usize,
+    target: &mut impl RenderTarget<Pixel = Pixel>,
+    state: DrawState,
+    [v1, v2, v3]: [[f32; 3]; 3],
+    c: Pixel,
+) {
+    rast_line_checked(target, state, [v1, v2], c);
+    rast_line_checked(target, state, [v1, v3], c);
+    rast_line_checked(target, state, [v2, v3], c);
+}
+
+/// Rasterizes an anti-aliased line between `vertices` at `[x, y, z]` in screen space,
+/// blending the coverage of every pixel over the target.
+pub fn rast_line_aa<Pixel: Color>(
+    target: &mut impl RenderTarget<Pixel = Pixel>,
+    vertices: [[f32; 3]; 2],
+    c: Pixel,
+) {
+    let mut target = target.framebuffer().color_only();
+    line::rast_line_aa_inner(&mut target, DepthState::default(), vertices, c);
+}
+
+/// Like [`rast_line_aa`], but tests `z` like [`rast_line_checked`].
+pub fn rast_line_aa_checked<Pixel: Color>(
+    target: &mut impl RenderTarget<Pixel = Pixel>,
+    state: DrawState,
+    vertices: [[f32; 3]; 2],
+    c: Pixel,
+) {
+    line::rast_line_aa_inner(&mut target.framebuffer(), state.depth, vertices, c);
+}
+
+pub fn rast_triangle_wireframe_aa<Pixel: Color>(
+    target: &mut impl RenderTarget<Pixel = Pixel>,
+    [v1, v2, v3]: [[f32; 3]; 3],
+    c: Pixel,
+) {
+    rast_line_aa(target, [v1, v2], c);
+    rast_line_aa(target, [v1, v3], c);
+    rast_line_aa(target, [v2, v3], c);
+}
+
+pub fn rast_triangle_wireframe_aa_checked<Pixel: Color>(
+    target: &mut impl RenderTarget<Pixel = Pixel>,
+    state: DrawState,
+    [v1, v2, v3]: [[f32; 3]; 3],
+    c: Pixel,
+) {
+    rast_line_aa_checked(target, state, [v1, v2], c);
+    rast_line_aa_checked(target, state, [v1, v3], c);
+    rast_line_aa_checked(target, state, [v2, v3], c);
+}
+
+/// Strokes a line in screen space with the width, caps and dashes of the `style`.
+pub fn rast_line_thick<Pixel: Copy>(
+    target: &mut impl RenderTarget<Pixel = Pixel>,
     v1x: f32,
     v1y: f32,
-    v1z: f32,
     v2x: f32,
     v2y: f32,
-    v2z: f32,
-    v3x: f32,
-    v3y: f32,
-    v3z: f32,
+    style: StrokeStyle,
+    c: Pixel,
+) {
+    rast_polyline(target, &[(v1x, v1y), (v2x, v2y)], false, style, c);
+}
+
+/// Strokes the segments between `points` in screen space with the `style`, and the segment
+/// back to the first point if `closed`.
+///
+/// The stroke is filled with triangles like [`rast_triangle_colored`], which multisampled
+/// targets anti-alias.
+pub fn rast_polyline<Pixel: Copy>(
+    target: &mut impl RenderTarget<Pixel = Pixel>,
+    points: &[(f32, f32)],
+    closed: bool,
+    style: StrokeStyle,
+    c: Pixel,
+) {
+    let mut target = target.framebuffer();
+    let size = (target.width, target.height);
+    let mut stroker = Stroker::new(style, |vertices: [(f32, f32); 3]| {
+        rast_triangle_colored(&mut target, vertices.map(|(x, y)| [x, y]), c);
+    });
+    stroker.polyline(points, closed, size);
+}
+
+/// Fills the polygon enclosed by the `contours` in screen space with a single color, each
+/// contour closing back to its first point.
+///
+/// Where contours overlap, the `rule` decides what is inside. Contours within others cut
+/// holes with either rule if they wind the other way. Like [`rast_triangle_colored`], pixels
+/// are filled if their center is inside, or each sample of multisampled targets.
+pub fn rast_polygon<Pixel: Copy>(
+    target: &mut impl RenderTarget<Pixel = Pixel>,
+    contours: &[&[(f32, f32)]],
+    rule: FillRule,
     c: Pixel,
 ) {
-    #[rustfmt::skip]
-    rast_line_checked(
-        pixels,
-        zbuffer,
+    let Framebuffer {
+        color: pixels,
         width,
         height,
-        v1x, v1y, v1z,
-        v2x, v2y, v2z,
-        c,
-    );
-    #[rustfmt::skip]
-    rast_line_checked(
-        pixels,
-        zbuffer,
+        samples,
+        ..
+    } = target.framebuffer();
+    let mut polygon = Polygon::new();
+    for contour in contours {
+        polygon.contour(contour);
+    }
+    let n = samples.count();
+    polygon.fill((width, height), rule, samples, |x0, x1, y, s| {
+        let row = y * width;
+        let span = &mut pixels[(row + x0) * n..(row + x1) * n];
+        for pixel in span.iter_mut().skip(s).step_by(n) {
+            *pixel = c;
+        }
+    });
+}
+
+/// Like [`rast_polygon`], but anti-aliased by blending the covered fraction of every pixel
+/// over the target.
+pub fn rast_polygon_aa<Pixel: Color>(
+    target: &mut impl RenderTarget<Pixel = Pixel>,
+    contours: &[&[(f32, f32)]],
+    rule: FillRule,
+    c: Pixel,
+) {
+    let mut polygon = Polygon::new();
+    for contour in contours {
+        polygon.contour(contour);
+    }
+    fill_aa(target, (&mut polygon, rule), c);
+}
+
+/// Like [`rast_polygon_aa`], but shades the polygon with the `shader`, such as a
+/// [`Gradient`].
+///
+/// The vertex data of the fragments is the center of their pixel in screen space. Their
+/// alpha is multiplied with the coverage before blending like [`BlendState::ALPHA`].
+pub fn rast_polygon_shaded<S: Shader<VertexData = (f32, f32)>, Pixel: Color>(
+    target: &mut impl RenderTarget<Pixel = Pixel>,
+    contours: &[&[(f32, f32)]],
+    rule: FillRule,
+    mut shader: S,
+) {
+    let mut polygon = Polygon::new();
+    for contour in contours {
+        polygon.contour(contour);
+    }
+    let corners = [(0.0, 0.0), (1.0, 0.0), (0.0, 1.0)];
+    fill_shaded(target, (&mut polygon, rule), corners, corners, &mut shader);
+}
+
+/// Fills the subpaths of a `path` in screen space like [`rast_polygon_aa`], open subpaths
+/// are closed.
+pub fn rast_path_fill<Pixel: Color>(
+    target: &mut impl RenderTarget<Pixel = Pixel>,
+    path: &Path,
+    rule: FillRule,
+    c: Pixel,
+) {
+    let mut polygon = Polygon::new();
+    path.flatten(|points, _| polygon.contour(points));
+    fill_aa(target, (&mut polygon, rule), c);
+}
+
+/// Strokes the subpaths of a `path` in screen space with the `style`, anti-aliased like
+/// [`rast_polygon_aa`].
+///
+/// Closed subpaths are joined at their start, and dash patterns restart at every subpath.
+pub fn rast_path_stroke<Pixel: Color>(
+    target: &mut impl RenderTarget<Pixel = Pixel>,
+    path: &Path,
+    style: StrokeStyle,
+    c: Pixel,
+) {
+    let mut target = target.framebuffer();
+    let mut polygon = stroke_polygon(path, style, (target.width, target.height));
+    fill_aa(&mut target, (&mut polygon, FillRule::NonZero), c);
+}
+
+/// Fills a `shape` transformed by `transform` with a single color, anti-aliased like
+/// [`rast_polygon_aa`].
+///
+/// The coverage of rectangles that stay axis-aligned, and of circles, ellipses and rings
+/// that stay axis-aligned ellipses with radii of at least two pixels, is computed directly.
+/// Other shapes, such as rotated rectangles and all rounded rectangles, arcs and pies, are
+/// filled from the flattened path.
+pub fn rast_shape<Pixel: Color>(
+    target: &mut impl RenderTarget<Pixel = Pixel>,
+    shape: Shape,
+    transform: Transform,
+    c: Pixel,
+) {
+    if let Some(analytic) = shape.analytic_fill(transform) {
+        return fill_aa(target, analytic, c);
+    }
+    let mut path = shape.path();
+    path.transform(transform);
+    rast_path_fill(target, &path, FillRule::NonZero, c);
+}
+
+/// Strokes the outline of a `shape` transformed by `transform` with the `style`, see
+/// [`rast_path_stroke`]. The width is in pixels after the transform.
+///
+/// The coverage of solid outlines of rectangles that stay axis-aligned with mitered corners,
+/// and of circles that stay circles, is computed directly.
+pub fn rast_shape_outline<Pixel: Color>(
+    target: &mut impl RenderTarget<Pixel = Pixel>,
+    shape: Shape,
+    transform: Transform,
+    style: StrokeStyle,
+    c: Pixel,
+) {
+    if let Some(analytic) = shape.analytic_outline(transform, &style) {
+        return fill_aa(target, analytic, c);
+    }
+    let mut path = shape.path();
+    path.transform(transform);
+    rast_path_stroke(target, &path, style, c);
+}
+
+/// Like [`rast_shape`], but shades the shape with the `shader`.
+///
+/// The vertex `data` belongs to the top left, top right and bottom left corners of the
+/// [`Shape::bounds`], and is interpolated across the transformed shape for the center of
+/// every pixel. The alpha of the fragments is multiplied with the coverage before blending
+/// like [`BlendState::ALPHA`].
+pub fn rast_shape_shaded<S: Shader, Pixel: Color>(
+    target: &mut impl RenderTarget<Pixel = Pixel>,
+    shape: Shape,
+    transform: Transform,
+    data: [S::VertexData; 3],
+    mut shader: S,
+) {
+    let corners = shape_corners(shape, transform);
+    if let Some(analytic) = shape.analytic_fill(transform) {
+        return fill_shaded(target, analytic, corners, data, &mut shader);
+    }
+    let mut path = shape.path();
+    path.transform(transform);
+    let mut polygon = Polygon::new();
+    path.flatten(|points, _| polygon.contour(points));
+    let coverage = (&mut polygon, FillRule::NonZero);
+    fill_shaded(target, coverage, corners, data, &mut shader);
+}
+
+/// Like [`rast_shape_outline`], but shades the outline with the `shader` like
+/// [`rast_shape_shaded`].
+pub fn rast_shape_outline_shaded<S: Shader, Pixel: Color>(
+    target: &mut impl RenderTarget<Pixel = Pixel>,
+    shape: Shape,
+    transform: Transform,
+    style: StrokeStyle,
+    data: [S::VertexData; 3],
+    mut shader: S,
+) {
+    let corners = shape_corners(shape, transform);
+    if let Some(analytic) = shape.analytic_outline(transform, &style) {
+        return fill_shaded(target, analytic, corners, data, &mut shader);
+    }
+    let mut path = shape.path();
+    path.transform(transform);
+    let mut target = target.framebuffer();
+    let mut polygon = stroke_polygon(&path, style, (target.width, target.height));
+    let coverage = (&mut polygon, FillRule::NonZero);
+    fill_shaded(&mut target, coverage, corners, data, &mut shader);
+}
+
+// Transformed top left, top right and bottom left corners of the bounds of a shape.
+fn shape_corners(shape: Shape, transform: Transform) -> [(f32, f32); 3] {
+    let (x, y, width, height) = shape.bounds();
+    [(x, y), (x + width, y), (x, y + height)].map(|p| transform.apply(p))
+}
+
+// Widens the subpaths of a `path` for a target of `size` pixels into a polygon filled by the
+// non-zero rule.
+fn stroke_polygon(path: &Path, style: StrokeStyle, size: (usize, usize)) -> Polygon {
+    // the triangles of the stroke overlap, winding them the same way fills their union
+    let mut polygon = Polygon::new();
+    let mut stroker = Stroker::new(style, |[v1, v2, v3]: [(f32, f32); 3]| {
+        let area = (v2.0 - v1.0) * (v3.1 - v1.1) - (v3.0 - v1.0) * (v2.1 - v1.1);
+        if area < 0.0 {
+            polygon.contour(&[v1, v3, v2]);
+        } else {
+            polygon.contour(&[v1, v2, v3]);
+        }
+    });
+    path.flatten(|points, closed| stroker.polyline(points, closed, size));
+    polygon
+}
+
+// Covered fractions of the pixels of anti-aliased fills.
+trait Coverage {
+    // Calls `plot` with the column, row and covered fraction of every pixel of a target of
+    // `size` pixels that is at least partly covered.
+    fn cover(self, size: (usize, usize), plot: impl FnMut(usize, usize, f32));
+}
+
+impl Coverage for (&mut Polygon, FillRule) {
+    fn cover(self, size: (usize, usize), plot: impl FnMut(usize, usize, f32)) {
+        let (polygon, rule) = self;
+        polygon.fill_aa(size, rule, plot);
+    }
+}
+
+impl Coverage for Analytic {
+    fn cover(self, size: (usize, usize), plot: impl FnMut(usize, usize, f32)) {
+        self.fill_aa(size, plot);
+    }
+}
+
+// Blends the covered fraction of every pixel over the target.
+fn fill_aa<Pixel: Color>(
+    target: &mut impl RenderTarget<Pixel = Pixel>,
+    coverage: impl Coverage,
+    c: Pixel,
+) {
+    let Framebuffer {
+        color: pixels,
         width,
         height,
-        v1x, v1y, v1z,
-        v3x, v3y, v3z,
-        c,
-    );
-    #[rustfmt::skip]
-    rast_line_checked(
-        pixels,
-        zbuffer,
+        samples,
+        ..
+    } = target.framebuffer();
+    let color: LinearRgb = c.into();
+    let n = samples.count();
+    coverage.cover((width, height), |x, y, coverage| {
+        let index = (y * width + x) * n;
+        for pixel in &mut pixels[index..index + n] {
+            *pixel = if coverage < 1.0 {
+                BlendState::ALPHA
+                    .blend(Rgba::new(color, coverage), (*pixel).into())
+                    .into()
+            } else {
+                c
+            };
+        }
+    });
+}
+
+// Blends the fragments of the `shader` over the covered pixels, with their alpha multiplied
+// by the covered fraction. The vertex data is interpolated linearly in screen space from the
+// `corners`.
+fn fill_shaded<S: Shader, Pixel: Color>(
+    target: &mut impl RenderTarget<Pixel = Pixel>,
+    coverage: impl Coverage,
+    [p1, p2, p3]: [(f32, f32); 3],
+    [d1, d2, d3]: [S::VertexData; 3],
+    shader: &mut S,
+) {
+    let Framebuffer {
+        color: pixels,
         width,
         height,
-        v2x, v2y, v2z,
-        v3x, v3y, v3z,
-        c,
-    );
+        samples,
+        ..
+    } = target.framebuffer();
+
+    // corners on a line give their data to every pixel
+    let area = edge_function(p1.0, p1.1, p2.0, p2.1, p3.0, p3.1);
+    let area = if area != 0.0 && area.is_finite() {
+        area
+    } else {
+        f32::INFINITY
+    };
+    let n = samples.count();
+    coverage.cover((width, height), |x, y, coverage| {
+        let (px, py) = (x as f32 + 0.5, y as f32 + 0.5);
+        let bcy = edge_function(p3.0, p3.1, p1.0, p1.1, px, py) / area;
+        let bcz = edge_function(p1.0, p1.1, p2.0, p2.1, px, py) / area;
+        let bc = (1.0 - bcy - bcz, bcy, bcz);
+        let data = shader.interpolate_fragment(bc, bc, d1, d2, d3);
+        let Fragment::Color(color) = shader.fragment(data) else {
+            return;
+        };
+        let color = Rgba::new(color.color, color.alpha * coverage);
+        let index = (y * width + x) * n;
+        for pixel in &mut pixels[index..index + n] {
+            *pixel = BlendState::ALPHA.blend(color, (*pixel).into()).into();
+        }
+    });
+}
+
+// Vertices are `[x, y, z / w, 1 / w]` in screen space with the reciprocal of their clip
+// space `w`.
+fn rast_triangle_inner<S: Shader, Pixel: Color, D: DepthFormat>(
+    target: &mut Framebuffer<'_, Pixel, D>,
+    vertices: [([f32; 4], S::VertexData); 3],
+    shader: &mut S,
+    state: DrawState,
+    front_facing: bool,
+    tile: Tile,
+) {
+    let [
+        ([v1x, v1y, v1z, v1w], d1),
+        ([v2x, v2y, v2z, v2w], d2),
+        ([v3x, v3y, v3z, v3w], d3),
+    ] = vertices;
+    let (width, height, samples) = (target.width, target.height, target.samples);
+    let pixels = &mut *target.color;
+    let mut zbuffer = target.depth.as_deref_mut();
+    let stencil = target.stencil.as_deref_mut();
+    debug_assert!(tile.maxx <= width && tile.maxy <= height);
+
+    // bounding box clip
+    let minx = (v1x.min(v2x).min(v3x).max(tile.minx as f32)) as usize;
+    let maxx = libm::ceilf(v1x.max(v2x).max(v3x).min(tile.maxx as f32)) as usize;
+    let miny = (v1y.min(v2y).min(v3y).max(tile.miny as f32)) as usize;
+    let maxy = libm::ceilf(v1y.max(v2y).max(v3y).min(tile.maxy as f32)) as usize;
+    if miny >= maxy || minx >= maxx {
+        return;
+    }
+
+    let mut stencil = stencil.filter(|_| state.stencil.is_enabled());
+    let blend = state.blend.is_enabled();
+    let (z1, z2, z3) = (F32x8::splat(v1z), F32x8::splat(v2z), F32x8::splat(v3z));
+    let (w1, w2, w3) = (F32x8::splat(v1w), F32x8::splat(v2w), F32x8::splat(v3w));
+    let mut colors = [Fragment::Discard; BLOCK_SIZE];
+
+    // Shades the fragments in `mask`, returning the ones that were not discarded.
+    let mut shade =
+        |mut mask: u32, [b1, b2, b3]: [F32x8; 3], colors: &mut [Fragment; BLOCK_SIZE]| {
+            let (p1, p2, p3) = (b1 * w1, b2 * w2, b3 * w3);
+            let rw = F32x8::splat(1.0) / (p1 + p2 + p3);
+            let fragments = FragmentBlock {
+                mask,
+                front_facing,
+                perspective: [p1 * rw, p2 * rw, p3 * rw].map(F32x8::to_array),
+                linear: [b1, b2, b3].map(F32x8::to_array),
+            };
+            shader.fragment_block(&fragments, d1, d2, d3, colors);
+            for (i, fragment) in colors.iter().enumerate() {
+                if let Fragment::Discard = fragment {
+                    mask &= !(1 << i);
+                }
+            }
+            mask
+        };
+    let write = |pixel: &mut Pixel, color: Rgba| {
+        *pixel = if blend {
+            state.blend.blend(color, (*pixel).into()).into()
+        } else {
+            color.color.into()
+        };
+    };
+
+    // I first saw this method used here:
+    //
+    // https://github.com/tsoding/olive.c/blob/master/olive.c
+    //
+    // The edge functions are set up once and stepped across the bounding box in blocks of
+    // fragments that are depth tested, interpolated and shaded together. Pixels are sampled
+    // at their centers, or at the positions of the `samples`.
+    let Some(setup) = setup(state.subpixel, [(v1x, v1y), (v2x, v2y), (v3x, v3y)]) else {
+        return;
+    };
+
+    let n = samples.count();
+    if n == 1 {
+        setup.for_each_block(minx, miny, maxx, maxy, |x, y, (mut mask, b)| {
+            let index = (y - tile.miny) * width + x;
+            let len = BLOCK_SIZE.min(width - x);
+            let [b1, b2, b3] = b;
+            let (z, tested) = depth::quantize::<D>((z1 * b1) + (z2 * b2) + (z3 * b3));
+            let depth_pass = match zbuffer.as_deref() {
+                Some(zbuffer) => {
+                    let stored = depth::load(&zbuffer[index..index + len]);
+                    mask & state.depth.test(tested, stored)
+                }
+                None => mask,
+            };
+            mask = match stencil.as_deref_mut() {
+                Some(stencil) => {
+                    let stencil = &mut stencil[index..index + len];
+                    state.stencil.test(stencil, mask, depth_pass, front_facing)
+                }
+                None => depth_pass,
+            };
+            if mask == 0 {
+                return;
+            }
+            let mask = shade(mask, b, &mut colors);
+
+            // discarded fragments skip the pass operation and the depth and color writes, the
+            // stencil test already applied the fail operations
+            if let Some(stencil) = stencil.as_deref_mut() {
+                let stencil = &mut stencil[index..index + len];
+                state.stencil.pass(stencil, mask, front_facing);
+            }
+            if let Some(zbuffer) = zbuffer.as_deref_mut().filter(|_| state.depth.write) {
+                let depth = &mut zbuffer[index..index + len];
+                for (i, depth) in depth.iter_mut().enumerate() {
+                    if mask & (1 << i) != 0 {
+                        *depth = z[i];
+                    }
+                }
+            }
+            for (i, pixel) in pixels[index..index + len].iter_mut().enumerate() {
+                match colors[i] {
+                    Fragment::Color(color) if mask & (1 << i) != 0 => write(pixel, color),
+                    _ => {}
+                }
+            }
+        });
+        return;
+    }
+
+    // Every sample is tested on its own and covered samples share the color of the fragment,
+    // which is shaded once at the pixel center.
+    let offsets = samples.positions();
+    setup.for_each_multisample_block(minx, miny, maxx, maxy, offsets, |x, y, (_, b), blocks| {
+        let index = (y - tile.miny) * width + x;
+        let len = BLOCK_SIZE.min(width - x);
+        let range = index * n..(index + len) * n;
+        let mut masks = [0; MAX_SAMPLES];
+        let mut depths = [[D::from_f32(0.0); BLOCK_SIZE]; MAX_SAMPLES];
+        for (s, &(mask, [b1, b2, b3])) in blocks.iter().enumerate() {
+            let (z, tested) = depth::quantize::<D>((z1 * b1) + (z2 * b2) + (z3 * b3));
+            let depth_pass = match zbuffer.as_deref() {
+                Some(zbuffer) => {
+                    let stored = depth::load(zbuffer[range.clone()].iter().skip(s).step_by(n));
+                    mask & state.depth.test(tested, stored)
+                }
+                None => mask,
+            };
+            masks[s] = match stencil.as_deref_mut() {
+                Some(stencil) => {
+                    let stencil = stencil[range.clone()].iter_mut().skip(s).step_by(n);
+                    state.stencil.test(stencil, mask, depth_pass, front_facing)
+                }
+                None => depth_pass,
+            };
+            depths[s] = z;
+        }
+        let mask = masks.iter().fold(0, |a, m| a | m);
+        if mask == 0 {
+            return;
+        }
+        let mask = shade(mask, b, &mut colors);
+
+        for (s, sample_mask) in masks.iter().take(n).enumerate() {
+            let mask = sample_mask & mask;
+            if let Some(stencil) = stencil.as_deref_mut() {
+                let stencil = stencil[range.clone()].iter_mut().skip(s).step_by(n);
+                state.stencil.pass(stencil, mask, front_facing);
+            }
+            if let Some(zbuffer) = zbuffer.as_deref_mut().filter(|_| state.depth.write) {
+                let depth = zbuffer[range.clone()].iter_mut().skip(s).step_by(n);
+                for (i, depth) in depth.enumerate() {
+                    if mask & (1 << i) != 0 {
+                        *depth = depths[s][i];
+                    }
+                }
+            }
+            let pixels = pixels[range.clone()].iter_mut().skip(s).step_by(n);
+            for (i, pixel) in pixels.enumerate() {
+                match colors[i] {
+                    Fragment::Color(color) if mask & (1 << i) != 0 => write(pixel, color),
+                    _ => {}
+                }
+            }
+        }
+    });
+}
+
+// Sets up the edge functions in the precision of `subpixel`.
+fn setup(subpixel: Subpixel, [v1, v2, v3]: [(f32, f32); 3]) -> Option<edge::Setup> {
+    match subpixel {
+        Subpixel::Float => TriangleSetup::new(v1, v2, v3, (0.5, 0.5), 1.0).map(edge::Setup::Float),
+        Subpixel::Fixed(bits) => {
+            let bits = bits.clamp(1, Subpixel::MAX_BITS);
+            let [v1, v2, v3] = [v1, v2, v3].map(|(x, y)| (snap(x, bits), snap(y, bits)));
+            let half = 1 << (bits - 1);
+            TriangleSetup::new(v1, v2, v3, (half, half), 1 << bits).map(edge::Setup::Fixed)
+        }
+    }
+}
+
+/// Region of the framebuffer a triangle is rasterized into.
+///
+/// The attachments of the framebuffer given to [`rast_triangle_inner`] start at row `miny`.
+#[derive(Debug, Clone, Copy)]
+struct Tile {
+    minx: usize,
+    miny: usize,
+    maxx: usize,
+    maxy: usize,
+}
+
+impl Tile {
+    fn full(width: usize, height: usize) -> Self {
+        Self {
+            minx: 0,
+            miny: 0,
+            maxx: width,
+            maxy: height,
+        }
+    }
+}
+
+/// Precision of vertex positions during rasterization.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Subpixel {
+    /// Vertices are used as is and edge functions are evaluated in `f32`.
+    ///
+    /// Only the terms of each row are set up once, every pixel is evaluated on its own.
+    /// Stepping by repeated addition would accumulate rounding errors that depend on where
+    /// the bounding box starts, and open cracks along edges shared by two triangles.
+    #[default]
+    Float,
+    /// Vertices are snapped to a grid with the given number of fractional bits, clamped to
+    /// `1..=Subpixel::MAX_BITS`, and edge functions are evaluated in integer arithmetic.
+    ///
+    /// Coverage is exact and identical across targets, and the edge functions are stepped
+    /// from pixel to pixel by addition.
+    Fixed(u8),
 }
 
-fn rast_triangle_inner<S: Shader, Pixel: Color>(
-    pixels: &mut [Pixel],
-    zbuffer: &mut [f32],
-    width: usize,
-    height: usize,
-    v1x: f32,
-    v1y: f32,
-    v1z: f32,
-    v2x: f32,
-    v2y: f32,
-    v2z: f32,
-    v3x: f32,
-    v3y: f32,
-    v3z: f32,
-    d1: S::VertexData,
-    d2: S::VertexData,
-    d3: S::VertexData,
-    mut shader: S,
-    depth_check: bool,
-) {
-    // bounding box clip
-    let minx = (v1x.min(v2x).min(v3x).max(0.0)) as usize;
-    let maxx = libm::ceilf(v1x.max(v2x).max(v3x).min(width as f32)) as usize;
-    let miny = (v1y.min(v2y).min(v3y).max(0.0)) as usize;
-    let maxy = libm::ceilf(v1y.max(v2y).max(v3y).min(height as f32)) as usize;
-    if miny == maxy || minx == maxx || miny > height || minx > width {
-        return;
+impl Subpixel {
+    pub const MAX_BITS: u8 = 8;
+}
+
+/// Which triangles are discarded before rasterization.
+///
+/// Degenerate triangles, with zero area after the perspective divide, are culled in every
+/// mode.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum CullMode {
+    /// Keeps both facings.
+    #[default]
+    None,
+    /// Discards triangles facing away from the viewer.
+    Back,
+    /// Discards triangles facing the viewer.
+    Front,
+}
+
+/// Winding order of front facing triangles in normalized device coordinates, with `y`
+/// pointing up.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum FrontFace {
+    /// Counter-clockwise.
+    #[default]
+    Ccw,
+    /// Clockwise.
+    Cw,
+}
+
+/// Comparison of a fragment value `a` against a stored value `b`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compare {
+    Never,
+    Less,
+    Equal,
+    LessEqual,
+    Greater,
+    NotEqual,
+    GreaterEqual,
+    Always,
+}
+
+impl Compare {
+    #[inline]
+    pub fn test<T: PartialOrd>(self, a: T, b: T) -> bool {
+        match self {
+            Self::Never => false,
+            Self::Less => a < b,
+            Self::Equal => a == b,
+            Self::LessEqual => a <= b,
+            Self::Greater => a > b,
+            Self::NotEqual => a != b,
+            Self::GreaterEqual => a >= b,
+            Self::Always => true,
+        }
     }
+}
 
-    let (v1x, v1y, v1z) = shader.vertex(v1x, v1y, v1z);
-    let (v2x, v2y, v2z) = shader.vertex(v2x, v2y, v2z);
-    let (v3x, v3y, v3z) = shader.vertex(v3x, v3y, v3z);
+/// Per-draw rasterizer state.
+///
+/// Triangles are culled by `cull` and `front_face` before clipping, degenerate triangles are
+/// always culled.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct DrawState {
+    pub subpixel: Subpixel,
+    pub cull: CullMode,
+    pub front_face: FrontFace,
+    pub depth: DepthState,
+    pub stencil: StencilState,
+    pub blend: BlendState,
+}
 
-    // I first saw this method used here:
-    //
-    // https://github.com/tsoding/olive.c/blob/master/olive.c
-    for y in miny..maxy.min(height) {
-        for x in minx..maxx.min(width) {
-            let index = y * width + x;
-            if let Some((bcx, bcy, bcz)) =
-                barycentric_coordinates(x as f32, y as f32, v1x, v1y, v2x, v2y, v3x, v3y)
-            {
-                if depth_check {
-                    let z = (v1z * bcx) + (v2z * bcy) + (v3z * bcz);
-                    if zbuffer[index] <= z {
-                        continue;
-                    }
-                    zbuffer[index] = z;
-                }
-                let vd = shader.interpolate(bcx, bcy, bcz, d1, d2, d3);
-                let color = shader.fragment(vd);
-                pixels[index] = color.into();
-            }
+impl DrawState {
+    /// Whether a triangle in clip space is front facing, or `None` if it is culled.
+    ///
+    /// The sign of the determinant of the homogeneous `x`, `y` and `w` coordinates is the
+    /// winding order after the perspective divide, so triangles are culled before clipping.
+    /// Degenerate triangles are always culled.
+    fn facing(&self, v1: [f32; 4], v2: [f32; 4], v3: [f32; 4]) -> Option<bool> {
+        let [x1, y1, _, w1] = v1;
+        let [x2, y2, _, w2] = v2;
+        let [x3, y3, _, w3] = v3;
+        let det = x1 * (y2 * w3 - y3 * w2) - y1 * (x2 * w3 - x3 * w2) + w1 * (x2 * y3 - x3 * y2);
+        if det == 0.0 || det.is_nan() {
+            return None;
+        }
+
+        let ccw = det > 0.0;
+        let front_facing = ccw == (self.front_face == FrontFace::Ccw);
+        match (self.cull, front_facing) {
+            (CullMode::Back, false) | (CullMode::Front, true) => None,
+            _ => Some(front_facing),
         }
     }
 }
 
-pub fn barycentric_coordinates(
-    px: f32,
-    py: f32,
-    v1x: f32,
-    v1y: f32,
-    v2x: f32,
-    v2y: f32,
-    v3x: f32,
-    v3y: f32,
-) -> Option<(f32, f32, f32)> {
+// Keeps every edge function product well within `i64`.
+const FIXED_GUARD_BAND: f32 = (1 << 21) as f32;
+
+#[inline]
+fn snap(v: f32, bits: u8) -> i64 {
+    let v = v.clamp(-FIXED_GUARD_BAND, FIXED_GUARD_BAND);
+    libm::roundf(v * (1 << bits) as f32) as i64
+}
+
+/// Computes the barycentric coordinates of the point `p` if it is covered by the triangle
+/// between `vertices`.
+///
+/// Coverage follows the top-left fill rule: a point exactly on an edge is only covered if
+/// that edge is a top or left edge, so a point on an edge shared by two triangles is covered
+/// by exactly one of them. Either winding order is accepted.
+pub fn barycentric_coordinates(p: [f32; 2], vertices: [[f32; 2]; 3]) -> Option<(f32, f32, f32)> {
     // https://en.wikipedia.org/wiki/Barycentric_coordinate_system#Edge_approach
-    let d = (v1x - v3x) * (v2y - v3y) - (v1y - v3y) * (v2x - v3x);
-    let u = ((px - v3x) * (v2y - v3y) - (py - v3y) * (v2x - v3x)) / d;
-    let v = ((px - v3x) * (v3y - v1y) - (py - v3y) * (v3x - v1x)) / d;
-    let w = 1.0 - u - v;
+    let [v1, v2, v3, p] = [vertices[0], vertices[1], vertices[2], p].map(|[x, y]| (x, y));
+    TriangleSetup::new(v1, v2, v3, p, 1.0)?.barycentric(0, 0)
+}
 
-    if u > 0.0 && u < 1.0 && v > 0.0 && v < 1.0 && w > 0.0 && w < 1.0 {
-        Some((u, v, w))
-    } else {
-        None
-    }
+/// Signed area of the parallelogram spanned by `a -> b` and `a -> p`.
+///
+/// Positive if `p` is to the right of `a -> b` with `y` pointing down.
+#[inline]
+pub fn edge_function(ax: f32, ay: f32, bx: f32, by: f32, px: f32, py: f32) -> f32 {
+    edge::edge_function((ax, ay), (bx, by), (px, py))
 }
 
 pub trait Shader {
     type VertexData: Copy;
 
+    /// Interpolates vertex data with barycentric coordinates.
+    ///
+    /// Fragments are given perspective-correct coordinates, see [`Shader::interpolate_fragment`].
     fn interpolate(
         &self,
         bcx: f32,
@@ -396,15 +1216,148 @@ pub trait Shader {
         d3: Self::VertexData,
     ) -> Self::VertexData;
 
+    /// Interpolates vertex data for a fragment.
+    ///
+    /// `perspective` are perspective-correct barycentric coordinates and `linear` are
+    /// screen space barycentric coordinates. Override this to opt individual attributes
+    /// into [`Interpolation::NoPerspective`] or [`Interpolation::Flat`].
+    #[inline]
+    fn interpolate_fragment(
+        &self,
+        perspective: (f32, f32, f32),
+        linear: (f32, f32, f32),
+        d1: Self::VertexData,
+        d2: Self::VertexData,
+        d3: Self::VertexData,
+    ) -> Self::VertexData {
+        let _ = linear;
+        let (bcx, bcy, bcz) = perspective;
+        self.interpolate(bcx, bcy, bcz, d1, d2, d3)
+    }
+
+    /// Vertex data at `coord` on a point sprite, from `(0, 0)` at the top left to `(1, 1)`
+    /// at the bottom right corner. Fragments of points interpolate it.
+    ///
+    /// Defaults to the data of the point.
     #[inline]
-    fn vertex(&mut self, x: f32, y: f32, z: f32) -> (f32, f32, f32) {
-        (x, y, z)
+    fn point_sprite(&self, data: Self::VertexData, coord: (f32, f32)) -> Self::VertexData {
+        let _ = coord;
+        data
     }
 
+    /// Shades a fragment, the alpha is used by [`DrawState::blend`].
+    ///
+    /// The depth and stencil tests run before the fragment stage, and fragments that fail
+    /// them are not shaded. Returning [`Fragment::Discard`] skips the stencil pass operation
+    /// and the depth and color writes, but the stencil fail and depth fail operations of the
+    /// fragment were already applied.
     #[inline]
-    fn fragment(&mut self, data: Self::VertexData) -> LinearRgb {
+    fn fragment(&mut self, data: Self::VertexData) -> Fragment {
         let _ = data;
-        LinearRgb::rgb(1.0, 1.0, 1.0)
+        LinearRgb::rgb(1.0, 1.0, 1.0).into()
+    }
+
+    /// Shades a block of fragments, writing the color of fragment `i` to `colors[i]`.
+    ///
+    /// Only fragments in [`FragmentBlock::mask`] are written to the target. Defaults to
+    /// [`Shader::interpolate_fragment`] and [`Shader::fragment`] for each of them.
+    #[inline]
+    fn fragment_block(
+        &mut self,
+        block: &FragmentBlock,
+        d1: Self::VertexData,
+        d2: Self::VertexData,
+        d3: Self::VertexData,
+        colors: &mut [Fragment; BLOCK_SIZE],
+    ) {
+        for i in block.iter() {
+            let data = self.interpolate_fragment(block.perspective(i), block.linear(i), d1, d2, d3);
+            colors[i] = self.fragment(data);
+        }
+    }
+}
+
+/// Vertex stage of a [`Shader`] for vertices of type `V`.
+///
+/// Uniforms such as transformation matrices are fields of the shader. Shaders in this crate
+/// take vertices already in clip space, `([x, y, z, w], VertexData)`.
+pub trait VertexShader<V>: Shader {
+    /// Outputs the position in homogeneous clip space and the data interpolated across the
+    /// triangle. Runs before culling, clipping and triangle setup.
+    fn vertex(&mut self, vertex: &V) -> ([f32; 4], Self::VertexData);
+}
+
+/// Number of fragments in a [`FragmentBlock`].
+///
+/// Blocks are processed with SIMD instructions on `x86_64`, AVX2 if the crate is built with
+/// `-C target-feature=+avx2` and SSE2 otherwise.
+pub const BLOCK_SIZE: usize = simd::LANES;
+
+/// Output of the fragment stage.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Fragment {
+    Color(Rgba),
+    /// The fragment is not drawn.
+    Discard,
+}
+
+impl From<Rgba> for Fragment {
+    #[inline]
+    fn from(color: Rgba) -> Self {
+        Self::Color(color)
+    }
+}
+
+impl From<LinearRgb> for Fragment {
+    #[inline]
+    fn from(color: LinearRgb) -> Self {
+        Self::Color(color.into())
+    }
+}
+
+/// `None` discards the fragment.
+impl<T: Into<Rgba>> From<Option<T>> for Fragment {
+    #[inline]
+    fn from(color: Option<T>) -> Self {
+        match color {
+            Some(color) => Self::Color(color.into()),
+            None => Self::Discard,
+        }
+    }
+}
+
+/// Horizontally adjacent fragments of a triangle that are shaded together.
+#[derive(Debug, Clone, Copy)]
+pub struct FragmentBlock {
+    /// Bit `i` is set if fragment `i` is covered and passed the stencil and depth tests.
+    pub mask: u32,
+    /// The triangle is front facing, see [`DrawState::front_face`]. Triangles drawn with
+    /// [`rast_triangle`] are always front facing.
+    pub front_facing: bool,
+    /// Perspective-correct barycentric coordinates, see [`Shader::interpolate_fragment`].
+    pub perspective: [[f32; BLOCK_SIZE]; 3],
+    /// Screen space barycentric coordinates.
+    pub linear: [[f32; BLOCK_SIZE]; 3],
+}
+
+impl FragmentBlock {
+    #[inline]
+    pub fn perspective(&self, i: usize) -> (f32, f32, f32) {
+        let [b1, b2, b3] = &self.perspective;
+        (b1[i], b2[i], b3[i])
+    }
+
+    #[inline]
+    pub fn linear(&self, i: usize) -> (f32, f32, f32) {
+        let [b1, b2, b3] = &self.linear;
+        (b1[i], b2[i], b3[i])
+    }
+
+    /// Indices of the fragments in the mask.
+    #[inline]
+    pub fn iter(&self) -> impl Iterator<Item = usize> + use<> {
+        let mask = self.mask;
+        (0..BLOCK_SIZE).filter(move |i| mask & (1 << i) != 0)
     }
 }
 
@@ -415,19 +1368,61 @@ where
     (d1 * bcx) + (d2 * bcy) + (d3 * bcz)
 }
 
+/// Interpolation qualifier for a single vertex attribute.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Interpolation {
+    /// Perspective-correct interpolation.
+    #[default]
+    Perspective,
+    /// Linear interpolation in screen space.
+    NoPerspective,
+    /// No interpolation, the first vertex of the triangle is used, also in the triangles
+    /// that remain of it after clipping.
+    Flat,
+}
+
+impl Interpolation {
+    /// Interpolates an attribute with the coordinates given to [`Shader::interpolate_fragment`].
+    #[inline]
+    pub fn lerp<T>(
+        self,
+        perspective: (f32, f32, f32),
+        linear: (f32, f32, f32),
+        d1: T,
+        d2: T,
+        d3: T,
+    ) -> T
+    where
+        T: core::ops::Add<T, Output = T> + core::ops::Mul<f32, Output = T>,
+    {
+        match self {
+            Self::Perspective => {
+                barycentric_lerp(perspective.0, perspective.1, perspective.2, d1, d2, d3)
+            }
+            Self::NoPerspective => barycentric_lerp(linear.0, linear.1, linear.2, d1, d2, d3),
+            Self::Flat => d1,
+        }
+    }
+}
+
+/// Shader from a vertex and a fragment closure.
+///
+/// The fragment closure returns anything that converts to a [`Fragment`], such as an opaque
+/// [`LinearRgb`], an [`Rgba`] or an `Option` of either that is `None` for discarded
+/// fragments.
 #[derive(Debug, Clone, Copy)]
-pub struct FnShader<V, F, D>(V, F, PhantomData<D>);
+pub struct FnShader<V, F, D, O>(V, F, PhantomData<(D, O)>);
 
-impl<V, F, D> FnShader<V, F, D> {
+impl<V, F, D, O> FnShader<V, F, D, O> {
     pub fn new(vertex: V, fragment: F) -> Self {
         Self(vertex, fragment, PhantomData)
     }
 }
 
-impl<V, F, D> Shader for FnShader<V, F, D>
+impl<V, F, D, O> Shader for FnShader<V, F, D, O>
 where
-    V: FnMut(f32, f32, f32) -> (f32, f32, f32),
-    F: FnMut(D) -> LinearRgb,
+    F: FnMut(D) -> O,
+    O: Into<Fragment>,
     D: Copy + core::ops::Add<D, Output = D> + core::ops::Mul<f32, Output = D>,
 {
     type VertexData = D;
@@ -446,13 +1441,21 @@ where
     }
 
     #[inline]
-    fn vertex(&mut self, x: f32, y: f32, z: f32) -> (f32, f32, f32) {
-        self.0(x, y, z)
+    fn fragment(&mut self, data: Self::VertexData) -> Fragment {
+        self.1(data).into()
     }
+}
 
+impl<I, V, F, D, O> VertexShader<I> for FnShader<V, F, D, O>
+where
+    V: FnMut(&I) -> ([f32; 4], D),
+    F: FnMut(D) -> O,
+    O: Into<Fragment>,
+    D: Copy + core::ops::Add<D, Output = D> + core::ops::Mul<f32, Output = D>,
+{
     #[inline]
-    fn fragment(&mut self, data: Self::VertexData) -> LinearRgb {
-        self.1(data)
+    fn vertex(&mut self, vertex: &I) -> ([f32; 4], Self::VertexData) {
+        self.0(vertex)
     }
 }
 
@@ -476,8 +1479,30 @@ impl Shader for ColorShader {
     }
 
     #[inline]
-    fn fragment(&mut self, data: Self::VertexData) -> LinearRgb {
-        data
+    fn fragment(&mut self, data: Self::VertexData) -> Fragment {
+        data.into()
+    }
+
+    #[inline]
+    fn fragment_block(
+        &mut self,
+        block: &FragmentBlock,
+        d1: Self::VertexData,
+        d2: Self::VertexData,
+        d3: Self::VertexData,
+        colors: &mut [Fragment; BLOCK_SIZE],
+    ) {
+        let [b1, b2, b3] = &block.perspective;
+        for (i, color) in colors.iter_mut().enumerate() {
+            *color = barycentric_lerp(b1[i], b2[i], b3[i], d1, d2, d3).into();
+        }
+    }
+}
+
+impl VertexShader<([f32; 4], LinearRgb)> for ColorShader {
+    #[inline]
+    fn vertex(&mut self, vertex: &([f32; 4], LinearRgb)) -> ([f32; 4], Self::VertexData) {
+        *vertex
     }
 }
 
@@ -487,6 +1512,18 @@ pub struct TextureShader<'a, T> {
     pub width: usize,
     pub height: usize,
     pub sampler: Sampler,
+    /// Discards fragments whose sampled alpha is below a cutoff, fragments that pass are
+    /// opaque.
+    pub alpha_test: Option<AlphaTest<T>>,
+}
+
+/// Alpha test of a [`TextureShader`].
+#[derive(Debug, Clone, Copy)]
+pub struct AlphaTest<T> {
+    /// Alpha of a texel in `0..=1`, sampled like the color.
+    pub alpha: fn(&T) -> f32,
+    /// Fragments with less alpha are discarded.
+    pub cutoff: f32,
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -515,14 +1552,20 @@ where
         (u, v)
     }
 
-    fn fragment(&mut self, data: Self::VertexData) -> LinearRgb {
+    /// Points are textured with the whole texture.
+    fn point_sprite(&self, _: Self::VertexData, coord: (f32, f32)) -> Self::VertexData {
+        coord
+    }
+
+    fn fragment(&mut self, data: Self::VertexData) -> Fragment {
         let (u, v) = data;
-        match self.sampler {
+        let alpha = |texel: &T| self.alpha_test.map_or(1.0, |test| (test.alpha)(texel));
+        let (color, alpha): (LinearRgb, f32) = match self.sampler {
             Sampler::Nearest => {
-                let x = libm::roundf(u * self.width as f32) as usize;
-                let y = libm::roundf(v * self.height as f32) as usize;
-                let len = self.texture.len().saturating_sub(1);
-                self.texture[(y * self.height + x).clamp(0, len)].into()
+                let x = ((u * self.width as f32).max(0.0) as usize).min(self.width - 1);
+                let y = ((v * self.height as f32).max(0.0) as usize).min(self.height - 1);
+                let texel = self.texture[y * self.width + x];
+                (texel.into(), alpha(&texel))
             }
             Sampler::Bilinear => {
                 // https://en.wikipedia.org/wiki/Bilinear_interpolation
@@ -538,21 +1581,64 @@ where
                 let dx = xf - x0 as f32;
                 let dy = yf - y0 as f32;
 
-                let c00: LinearRgb = self.texture[y0 * self.width + x0].into();
-                let c10: LinearRgb = self.texture[y0 * self.width + x1].into();
-                let c01: LinearRgb = self.texture[y1 * self.width + x0].into();
-                let c11: LinearRgb = self.texture[y1 * self.width + x1].into();
+                let texels = [
+                    self.texture[y0 * self.width + x0],
+                    self.texture[y0 * self.width + x1],
+                    self.texture[y1 * self.width + x0],
+                    self.texture[y1 * self.width + x1],
+                ];
 
+                let [c00, c10, c01, c11] = texels.map(Into::<LinearRgb>::into);
                 let top = c00 * (1.0 - dx) + c10 * dx;
                 let bottom = c01 * (1.0 - dx) + c11 * dx;
-                top * (1.0 - dy) + bottom * dy
+                let [a00, a10, a01, a11] = texels.map(|texel| alpha(&texel));
+                let alpha_top = a00 * (1.0 - dx) + a10 * dx;
+                let alpha_bottom = a01 * (1.0 - dx) + a11 * dx;
+                (
+                    top * (1.0 - dy) + bottom * dy,
+                    alpha_top * (1.0 - dy) + alpha_bottom * dy,
+                )
             }
+        };
+        match self.alpha_test {
+            Some(test) if alpha < test.cutoff => Fragment::Discard,
+            _ => color.into(),
+        }
+    }
+
+    fn fragment_block(
+        &mut self,
+        block: &FragmentBlock,
+        d1: Self::VertexData,
+        d2: Self::VertexData,
+        d3: Self::VertexData,
+        colors: &mut [Fragment; BLOCK_SIZE],
+    ) {
+        let [b1, b2, b3] = &block.perspective;
+        let mut u = [0.0; BLOCK_SIZE];
+        let mut v = [0.0; BLOCK_SIZE];
+        for i in 0..BLOCK_SIZE {
+            u[i] = barycentric_lerp(b1[i], b2[i], b3[i], d1.0, d2.0, d3.0);
+            v[i] = barycentric_lerp(b1[i], b2[i], b3[i], d1.1, d2.1, d3.1);
+        }
+        for i in block.iter() {
+            colors[i] = self.fragment((u[i], v[i]));
         }
     }
 }
 
+impl<T> VertexShader<([f32; 4], (f32, f32))> for TextureShader<'_, T>
+where
+    T: Copy + Color,
+{
+    #[inline]
+    fn vertex(&mut self, vertex: &([f32; 4], (f32, f32))) -> ([f32; 4], Self::VertexData) {
+        *vertex
+    }
+}
+
 pub mod empty {
-    use crate::Shader;
+    use crate::{Shader, VertexShader};
 
     pub struct EmptyShader;
     impl Shader for EmptyShader {
@@ -569,6 +1655,11 @@ pub mod empty {
             EmptyVertexData
         }
     }
+    impl VertexShader<[f32; 4]> for EmptyShader {
+        fn vertex(&mut self, vertex: &[f32; 4]) -> ([f32; 4], Self::VertexData) {
+            (*vertex, EmptyVertexData)
+        }
+    }
     #[derive(Clone, Copy)]
     pub struct EmptyVertexData;
     impl core::ops::Add for EmptyVertexData {
@@ -584,3 +1675,697 @@ pub mod empty {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+
+    use super::*;
+
+    const SIZE: usize = 48;
+
+    type Triangle = [(f32, f32); 3];
+
+    // Triangles sharing a vertex near the middle and covering the whole target, with vertices
+    // and edges through pixel centers.
+    fn fan(steps: usize) -> Vec<Triangle> {
+        let center = (23.5, 24.5);
+        let corner = |i: usize| {
+            let angle = i as f32 / steps as f32 * core::f32::consts::TAU;
+            let radius = if i.is_multiple_of(3) { 200.0 } else { 150.25 };
+            (
+                libm::roundf(center.0 + libm::cosf(angle) * radius) + 0.5,
+                libm::roundf(center.1 + libm::sinf(angle) * radius),
+            )
+        };
+        (0..steps)
+            .map(|i| [center, corner(i), corner(i + 1)])
+            .collect()
+    }
+
+    // Cells split into two triangles, covering the whole target. Corners are jittered and
+    // every other row and column of them is on pixel centers.
+    fn grid() -> Vec<Triangle> {
+        let mut random = testing::random(3);
+        let cells = 9;
+        let corners: Vec<Vec<(f32, f32)>> = (0..=cells)
+            .map(|j| {
+                (0..=cells)
+                    .map(|i| {
+                        let at = |k: usize| k as f32 * 8.0 - 12.0 + 0.5;
+                        match (i % 2, j % 2) {
+                            (0, 0) => (at(i), at(j)),
+                            (0, _) => (at(i), at(j) + random() * 3.0),
+                            (_, 0) => (at(i) + random() * 3.0, at(j)),
+                            _ => (at(i) + random() * 3.0, at(j) + random() * 3.0),
+                        }
+                    })
+                    .collect()
+            })
+            .collect();
+        let mut triangles = Vec::new();
+        for j in 0..cells {
+            for i in 0..cells {
+                let [a, b, c, d] = [
+                    corners[j][i],
+                    corners[j][i + 1],
+                    corners[j + 1][i + 1],
+                    corners[j + 1][i],
+                ];
+                // alternate the diagonal to get edges in every direction
+                if (i + j).is_multiple_of(2) {
+                    triangles.extend([[a, b, c], [a, c, d]]);
+                } else {
+                    triangles.extend([[a, b, d], [b, c, d]]);
+                }
+            }
+        }
+        triangles
+    }
+
+    // Number of triangles covering each sample.
+    fn hits(triangles: &[Triangle], subpixel: Subpixel, samples: SampleCount) -> Vec<u32> {
+        let state = DrawState {
+            subpixel,
+            ..Default::default()
+        };
+        testing::hits((SIZE, SIZE), samples, state, |target, state| {
+            let one = LinearRgb::rgb(1.0, 1.0, 1.0);
+            for triangle in triangles {
+                let vertices = triangle.map(|(x, y)| ([x, y, 0.0], one));
+                rast_triangle_checked(target, state, vertices, ColorShader);
+            }
+        })
+    }
+
+    // Discards every other column, with the screen `x` as vertex data.
+    struct Columns;
+
+    impl Shader for Columns {
+        type VertexData = f32;
+
+        fn interpolate(&self, bcx: f32, bcy: f32, bcz: f32, d1: f32, d2: f32, d3: f32) -> f32 {
+            barycentric_lerp(bcx, bcy, bcz, d1, d2, d3)
+        }
+
+        fn fragment(&mut self, x: f32) -> Fragment {
+            let white = LinearRgb::rgb(1.0, 1.0, 1.0);
+            ((x as usize).is_multiple_of(2)).then_some(white).into()
+        }
+    }
+
+    #[test]
+    fn discarded_fragments_leave_every_attachment_untouched() {
+        let (black, white) = (LinearRgb::rgb(0.0, 0.0, 0.0), LinearRgb::rgb(1.0, 1.0, 1.0));
+        let state = DrawState {
+            stencil: StencilState::both(
+                StencilFace {
+                    pass: StencilOp::Replace,
+                    ..Default::default()
+                },
+                5,
+            ),
+            ..Default::default()
+        };
+        for samples in [SampleCount::X1, SampleCount::X4] {
+            let mut target = OwnedFramebuffer::new_multisample(SIZE, SIZE, samples, black)
+                .with_depth(1.0)
+                .with_stencil(0);
+            let far = 2.0 * SIZE as f32;
+            rast_triangle_checked(
+                &mut target,
+                state,
+                [
+                    ([0.0, 0.0, 0.5], 0.0),
+                    ([far, 0.0, 0.5], far),
+                    ([0.0, far, 0.5], 0.0),
+                ],
+                Columns,
+            );
+
+            let count = samples.count();
+            let depth = target.depth().unwrap();
+            let stencil = target.stencil().unwrap();
+            for (i, &color) in target.color().iter().enumerate() {
+                let kept = (i / count % SIZE).is_multiple_of(2);
+                let expected = if kept { (white, 5) } else { (black, 0) };
+                assert_eq!((color, stencil[i]), expected, "{samples:?} {i}");
+                let expected = if kept { 0.5 } else { 1.0 };
+                assert!((depth[i] - expected).abs() < 1e-6, "{samples:?} {i}");
+            }
+        }
+    }
+
+    #[test]
+    fn discarded_fragments_still_apply_the_stencil_fail_operations() {
+        let (black, white) = (LinearRgb::rgb(0.0, 0.0, 0.0), LinearRgb::rgb(1.0, 1.0, 1.0));
+        let state = DrawState {
+            stencil: StencilState::both(
+                StencilFace {
+                    depth_fail: StencilOp::IncrementClamp,
+                    pass: StencilOp::Replace,
+                    ..Default::default()
+                },
+                5,
+            ),
+            ..Default::default()
+        };
+        // the top half is closer than the triangle and fails the depth test
+        let mut target = OwnedFramebuffer::new(SIZE, SIZE, black)
+            .with_depth(1.0)
+            .with_stencil(0);
+        let mut framebuffer = target.framebuffer();
+        for (i, depth) in framebuffer.depth().unwrap().iter_mut().enumerate() {
+            if i / SIZE < SIZE / 2 {
+                *depth = 0.25;
+            }
+        }
+        let far = 2.0 * SIZE as f32;
+        rast_triangle_checked(
+            &mut target,
+            state,
+            [
+                ([0.0, 0.0, 0.5], 0.0),
+                ([far, 0.0, 0.5], far),
+                ([0.0, far, 0.5], 0.0),
+            ],
+            Columns,
+        );
+
+        let depth = target.depth().unwrap();
+        let stencil = target.stencil().unwrap();
+        for (i, &color) in target.color().iter().enumerate() {
+            let (x, y) = (i % SIZE, i / SIZE);
+            // fragments that fail a test are not shaded, so discarding can't undo their
+            // operations
+            let expected = match (y < SIZE / 2, x.is_multiple_of(2)) {
+                (true, _) => (black, 0.25, 1),
+                (false, true) => (white, 0.5, 5),
+                (false, false) => (black, 1.0, 0),
+            };
+            assert_eq!((color, depth[i], stencil[i]), expected, "{x}, {y}");
+        }
+    }
+
+    #[test]
+    fn textures_discard_fragments_below_the_alpha_cutoff() {
+        let black = LinearRgb::rgb(0.0, 0.0, 0.0);
+        // the alpha of the texels is their red channel
+        let texture = [
+            LinearRgb::rgb(1.0, 0.0, 0.0),
+            LinearRgb::rgb(0.25, 0.0, 0.0),
+        ];
+        let shader = TextureShader {
+            texture: &texture,
+            width: 2,
+            height: 1,
+            sampler: Sampler::Bilinear,
+            alpha_test: Some(AlphaTest {
+                alpha: |texel: &LinearRgb| texel.r,
+                cutoff: 0.5,
+            }),
+        };
+        let mut target = OwnedFramebuffer::new(12, 2, black)
+            .with_depth(1.0)
+            .with_stencil(0);
+        let state = DrawState {
+            stencil: StencilState::both(
+                StencilFace {
+                    pass: StencilOp::Replace,
+                    ..Default::default()
+                },
+                5,
+            ),
+            ..Default::default()
+        };
+        rast_triangle_checked(
+            &mut target,
+            state,
+            [
+                ([0.0, 0.0, 0.5], (0.0, 0.0)),
+                ([24.0, 0.0, 0.5], (2.0, 0.0)),
+                ([0.0, 24.0, 0.5], (0.0, 0.0)),
+            ],
+            shader,
+        );
+
+        // `u` is a third at the center of column 4, where alpha drops below the cutoff
+        let depth = target.depth().unwrap();
+        let stencil = target.stencil().unwrap();
+        for (i, &color) in target.color().iter().enumerate() {
+            let kept = i % 12 < 4;
+            let written = (color.r > 0.0, depth[i] < 1.0, stencil[i] == 5);
+            assert_eq!(written, (kept, kept, kept), "{i}");
+        }
+    }
+
+    #[test]
+    fn nearest_sampling_picks_the_texel_under_every_fragment() {
+        // a texture that is wider than tall, drawn over a sprite of two by three pixels per
+        // texel
+        let texture: [LinearRgb; 6] = core::array::from_fn(|i| LinearRgb::rgb(i as f32, 0.0, 1.0));
+        let shader = TextureShader {
+            texture: &texture,
+            width: 3,
+            height: 2,
+            sampler: Sampler::Nearest,
+            alpha_test: None,
+        };
+        let mut target = OwnedFramebuffer::new(6, 6, LinearRgb::rgb(0.0, 0.0, 0.0));
+        rast_point(
+            &mut target,
+            PointStyle::new(6.0),
+            [3.0, 3.0],
+            (0.0, 0.0),
+            shader,
+        );
+
+        for (i, &color) in target.color().iter().enumerate() {
+            let (x, y) = (i % 6 / 2, i / 6 / 3);
+            assert_eq!(color, texture[y * 3 + x], "{i}");
+        }
+    }
+
+    // Interpolates red perspective-correct, green linearly in screen space and blue flat.
+    struct Qualifiers;
+
+    impl Shader for Qualifiers {
+        type VertexData = LinearRgb;
+
+        fn interpolate(
+            &self,
+            bcx: f32,
+            bcy: f32,
+            bcz: f32,
+            d1: LinearRgb,
+            d2: LinearRgb,
+            d3: LinearRgb,
+        ) -> LinearRgb {
+            barycentric_lerp(bcx, bcy, bcz, d1, d2, d3)
+        }
+
+        fn interpolate_fragment(
+            &self,
+            perspective: (f32, f32, f32),
+            linear: (f32, f32, f32),
+            d1: LinearRgb,
+            d2: LinearRgb,
+            d3: LinearRgb,
+        ) -> LinearRgb {
+            let lerp = |qualifier: Interpolation, f: fn(LinearRgb) -> f32| {
+                qualifier.lerp(perspective, linear, f(d1), f(d2), f(d3))
+            };
+            LinearRgb::rgb(
+                lerp(Interpolation::Perspective, |c| c.r),
+                lerp(Interpolation::NoPerspective, |c| c.g),
+                lerp(Interpolation::Flat, |c| c.b),
+            )
+        }
+
+        fn fragment(&mut self, data: LinearRgb) -> Fragment {
+            data.into()
+        }
+    }
+
+    impl VertexShader<([f32; 4], LinearRgb)> for Qualifiers {
+        fn vertex(&mut self, vertex: &([f32; 4], LinearRgb)) -> ([f32; 4], LinearRgb) {
+            *vertex
+        }
+    }
+
+    #[test]
+    fn attributes_are_interpolated_by_their_qualifier() {
+        let black = LinearRgb::rgb(0.0, 0.0, 0.0);
+        let mut target = OwnedFramebuffer::new(SIZE, SIZE, black);
+        // the second vertex is four times as far away, red and green are 1 there
+        let (p1, p2, p3) = ([-1.0, -1.0], [1.0, -1.0], [-1.0, 1.0]);
+        let clip = |[x, y]: [f32; 2], w: f32| [x * w, y * w, 0.5 * w, w];
+        let one = LinearRgb::rgb(1.0, 1.0, 3.0);
+        let (v1, v2, v3) = (
+            (clip(p1, 1.0), black),
+            (clip(p2, 4.0), one),
+            (clip(p3, 1.0), black),
+        );
+        draw_triangle(&mut target, DrawState::default(), v1, v2, v3, Qualifiers);
+
+        let screen =
+            |[x, y]: [f32; 2]| ((x * 0.5 + 0.5) * SIZE as f32, (0.5 - y * 0.5) * SIZE as f32);
+        let [(x1, y1), (x2, y2), (x3, y3)] = [p1, p2, p3].map(screen);
+        let mut farther = 0;
+        for (i, c) in target.color().iter().enumerate() {
+            let (px, py) = ((i % SIZE) as f32 + 0.5, (i / SIZE) as f32 + 0.5);
+            let Some((l1, l2, l3)) =
+                barycentric_coordinates([px, py], [[x1, y1], [x2, y2], [x3, y3]])
+            else {
+                assert_eq!(*c, black);
+                continue;
+            };
+            // perspective-correct coordinates weigh vertices by `1 / w`
+            let perspective = (l2 / 4.0) / (l1 + l2 / 4.0 + l3);
+            assert!((c.r - perspective).abs() < 1e-4, "{px}, {py}: {c:?}");
+            assert!((c.g - l2).abs() < 1e-4, "{px}, {py}: {c:?}");
+            assert_eq!(c.b, 0.0, "{px}, {py}");
+            if c.g - c.r > 0.1 {
+                farther += 1;
+            }
+        }
+        // the far half is compressed on screen
+        assert!(farther > 100, "{farther}");
+    }
+
+    #[test]
+    fn flat_attributes_keep_the_first_vertex_after_clipping() {
+        let black = LinearRgb::rgb(0.0, 0.0, 0.0);
+        let first = LinearRgb::rgb(0.0, 0.0, 7.0);
+        let other = LinearRgb::rgb(1.0, 1.0, 1.0);
+        // the first vertex is in front of the near plane
+        let v1 = ([0.0, 0.5, -1.0, 1.0], first);
+        let (v2, v3) = (
+            ([-1.0, -1.0, 0.5, 1.0], other),
+            ([1.0, -1.0, 0.5, 1.0], other),
+        );
+        for (v1, v2, v3) in [(v1, v2, v3), (v1, v3, v2)] {
+            let mut target = OwnedFramebuffer::new(SIZE, SIZE, black);
+            draw_triangle(&mut target, DrawState::default(), v1, v2, v3, Qualifiers);
+            let drawn: Vec<_> = target.color().iter().filter(|&&c| c != black).collect();
+            assert!(drawn.len() > 100, "{}", drawn.len());
+            assert!(drawn.iter().all(|c| c.b == 7.0), "{drawn:?}");
+            // unlike the interpolated attributes
+            assert!(drawn.iter().all(|c| c.r > 0.5 && c.g > 0.5), "{drawn:?}");
+        }
+
+        let mut target = OwnedFramebuffer::new(SIZE, SIZE, black);
+        let v2 = ([0.0, -0.5, 1.0, 1.0], other);
+        draw_line(&mut target, DrawState::default(), v1, v2, Qualifiers);
+        let drawn: Vec<_> = target.color().iter().filter(|&&c| c != black).collect();
+        assert!(drawn.len() > 10, "{}", drawn.len());
+        assert!(drawn.iter().all(|c| c.b == 7.0), "{drawn:?}");
+    }
+
+    // Counts the fragments of the blocks it shades, and colors every lane of them.
+    struct Blocks<'a>(&'a core::cell::Cell<u32>);
+
+    impl Shader for Blocks<'_> {
+        type VertexData = f32;
+
+        fn interpolate(&self, bcx: f32, bcy: f32, bcz: f32, d1: f32, d2: f32, d3: f32) -> f32 {
+            barycentric_lerp(bcx, bcy, bcz, d1, d2, d3)
+        }
+
+        fn fragment_block(
+            &mut self,
+            block: &FragmentBlock,
+            _: f32,
+            _: f32,
+            _: f32,
+            colors: &mut [Fragment; BLOCK_SIZE],
+        ) {
+            self.0.set(self.0.get() + block.mask.count_ones());
+            *colors = [LinearRgb::rgb(1.0, 1.0, 1.0).into(); BLOCK_SIZE];
+        }
+    }
+
+    #[test]
+    fn partial_blocks_at_the_right_edge_only_write_their_fragments() {
+        // the last block of a row has 5 of its 8 fragments inside of the target
+        let (width, height) = (13, 5);
+        let black = LinearRgb::rgb(0.0, 0.0, 0.0);
+        for samples in [SampleCount::X1, SampleCount::X4] {
+            for right in [11.0, 13.0, 40.0] {
+                let shaded = core::cell::Cell::new(0);
+                let mut target = OwnedFramebuffer::new_multisample(width, height, samples, black);
+                let (h, state) = (height as f32, DrawState::default());
+                rast_triangle_checked(
+                    &mut target,
+                    state,
+                    [
+                        ([0.0, 0.0, 0.5], 0.0),
+                        ([right, 0.0, 0.5], 0.0),
+                        ([right, h, 0.5], 0.0),
+                    ],
+                    Blocks(&shaded),
+                );
+                rast_triangle_checked(
+                    &mut target,
+                    state,
+                    [
+                        ([0.0, 0.0, 0.5], 0.0),
+                        ([right, h, 0.5], 0.0),
+                        ([0.0, h, 0.5], 0.0),
+                    ],
+                    Blocks(&shaded),
+                );
+
+                // multisampled pixels on the shared diagonal are shaded by both triangles
+                let columns = (right as usize).min(width);
+                let shared = if samples == SampleCount::X1 {
+                    0
+                } else {
+                    2 * height
+                };
+                let shaded = shaded.get() as usize;
+                assert!(shaded >= columns * height, "{samples:?} {right}");
+                assert!(shaded <= columns * height + shared, "{samples:?} {right}");
+                for (i, c) in target.color().iter().enumerate() {
+                    let x = i / samples.count() % width;
+                    assert_eq!(c.r, (x < columns) as u8 as f32, "{samples:?} {right} {i}");
+                }
+            }
+        }
+    }
+
+    // Counts the front and back facing fragments it shades.
+    struct Facing<'a>(&'a core::cell::Cell<[u32; 2]>);
+
+    impl Shader for Facing<'_> {
+        type VertexData = f32;
+
+        fn interpolate(&self, bcx: f32, bcy: f32, bcz: f32, d1: f32, d2: f32, d3: f32) -> f32 {
+            barycentric_lerp(bcx, bcy, bcz, d1, d2, d3)
+        }
+
+        fn fragment_block(
+            &mut self,
+            block: &FragmentBlock,
+            _: f32,
+            _: f32,
+            _: f32,
+            colors: &mut [Fragment; BLOCK_SIZE],
+        ) {
+            let mut counts = self.0.get();
+            counts[!block.front_facing as usize] += block.mask.count_ones();
+            self.0.set(counts);
+            *colors = [LinearRgb::rgb(1.0, 1.0, 1.0).into(); BLOCK_SIZE];
+        }
+    }
+
+    impl VertexShader<[f32; 4]> for Facing<'_> {
+        fn vertex(&mut self, vertex: &[f32; 4]) -> ([f32; 4], f32) {
+            (*vertex, 0.0)
+        }
+    }
+
+    #[test]
+    fn culling_follows_the_mode_and_winding_and_reports_the_facing() {
+        let black = LinearRgb::rgb(0.0, 0.0, 0.0);
+        let (ccw, cw) = (
+            [[-1.0, -1.0], [1.0, -1.0], [-1.0, 1.0]],
+            [[-1.0, -1.0], [-1.0, 1.0], [1.0, -1.0]],
+        );
+        // front facing fragments increment the stencil value, back facing ones decrement it
+        let stencil = StencilState {
+            front: StencilFace {
+                pass: StencilOp::IncrementClamp,
+                ..Default::default()
+            },
+            back: StencilFace {
+                pass: StencilOp::DecrementClamp,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        for cull in [CullMode::None, CullMode::Back, CullMode::Front] {
+            for front_face in [FrontFace::Ccw, FrontFace::Cw] {
+                for (is_ccw, triangle) in [(true, ccw), (false, cw)] {
+                    let state = DrawState {
+                        cull,
+                        front_face,
+                        stencil,
+                        ..Default::default()
+                    };
+                    let front = is_ccw == (front_face == FrontFace::Ccw);
+                    let culled = matches!(
+                        (cull, front),
+                        (CullMode::Back, false) | (CullMode::Front, true)
+                    );
+                    let case = alloc::format!("{cull:?} {front_face:?} ccw: {is_ccw}");
+
+                    // in clip space, and in screen space with `y` pointing down
+                    let counts = core::cell::Cell::new([0; 2]);
+                    let mut clip = OwnedFramebuffer::new(8, 8, black).with_stencil(1);
+                    let [p1, p2, p3] = triangle.map(|[x, y]| [x, y, 0.5, 1.0]);
+                    draw_triangle(&mut clip, state, p1, p2, p3, Facing(&counts));
+                    let mut screen = OwnedFramebuffer::new(8, 8, black).with_stencil(1);
+                    let vertices =
+                        triangle.map(|[x, y]| ([(x + 1.0) * 4.0, (1.0 - y) * 4.0, 0.5], 0.0));
+                    rast_triangle_checked(&mut screen, state, vertices, Facing(&counts));
+
+                    let white = LinearRgb::rgb(1.0, 1.0, 1.0);
+                    let covered = clip.color().iter().filter(|&&c| c == white).count() as u32;
+                    assert_eq!(covered == 0, culled, "{case}");
+                    let [fronts, backs] = counts.get();
+                    let shaded = 2 * covered;
+                    assert_eq!(
+                        (fronts, backs),
+                        if front { (shaded, 0) } else { (0, shaded) },
+                        "{case}"
+                    );
+                    assert_eq!(clip.stencil(), screen.stencil(), "{case}");
+                    let written = match (culled, front) {
+                        (true, _) => 1,
+                        (false, true) => 2,
+                        (false, false) => 0,
+                    };
+                    for (&value, &c) in clip.stencil().unwrap().iter().zip(clip.color()) {
+                        assert_eq!(value, if c == white { written } else { 1 }, "{case}");
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn degenerate_triangles_are_always_culled() {
+        let black = LinearRgb::rgb(0.0, 0.0, 0.0);
+        let counts = core::cell::Cell::new([0; 2]);
+        let mut target = OwnedFramebuffer::new(8, 8, black);
+        let state = DrawState::default();
+        // collinear, and with two vertices at the same position
+        rast_triangle_checked(
+            &mut target,
+            state,
+            [
+                ([0.0, 0.0, 0.5], 0.0),
+                ([4.0, 4.0, 0.5], 0.0),
+                ([8.0, 8.0, 0.5], 0.0),
+            ],
+            Facing(&counts),
+        );
+        let (p1, p2) = ([-1.0, -1.0, 0.5, 1.0], [1.0, 1.0, 0.5, 1.0]);
+        draw_triangle(&mut target, state, p1, p2, p2, Facing(&counts));
+        assert_eq!(counts.get(), [0, 0]);
+        assert!(target.color().iter().all(|c| *c == black));
+    }
+
+    #[test]
+    fn the_vertex_stage_matches_pre_transformed_vertices() {
+        let black = LinearRgb::rgb(0.0, 0.0, 0.0);
+        // a fan in object space, with varying depth so the triangles are drawn in perspective
+        #[rustfmt::skip]
+        let vertices = [
+            ([0.0, 0.0, 0.5], LinearRgb::rgb(1.0, 1.0, 1.0)),
+            ([-1.0, -1.0, 0.0], LinearRgb::rgb(1.0, 0.0, 0.0)),
+            ([1.0, -1.0, 1.0], LinearRgb::rgb(0.0, 1.0, 0.0)),
+            ([1.0, 1.0, 2.0], LinearRgb::rgb(0.0, 0.0, 1.0)),
+            ([-1.0, 1.0, 0.5], LinearRgb::rgb(1.0, 1.0, 0.0)),
+        ];
+        let indices = [0, 1, 2, 0, 2, 3, 0, 3, 4, 0, 4, 1];
+        let transform = |&([x, y, z], color): &([f32; 3], LinearRgb)| {
+            ([x * 1.5, y * 1.5 + 0.25, z, 1.0 + z], color)
+        };
+
+        let calls = core::cell::Cell::new(0);
+        let shader = FnShader::new(
+            |v: &([f32; 3], LinearRgb)| {
+                calls.set(calls.get() + 1);
+                transform(v)
+            },
+            |color: LinearRgb| color,
+        );
+        let state = DrawState::default();
+        let mut indexed = OwnedFramebuffer::new(SIZE, SIZE, black).with_depth(1.0);
+        draw_indexed(&mut indexed, state, &vertices, &indices, shader);
+        assert_eq!(calls.get(), vertices.len());
+
+        calls.set(0);
+        let mut listed = OwnedFramebuffer::new(SIZE, SIZE, black).with_depth(1.0);
+        let mut expected = OwnedFramebuffer::new(SIZE, SIZE, black).with_depth(1.0);
+        for triangle in indices.chunks_exact(3) {
+            let [v1, v2, v3] = [0, 1, 2].map(|i| vertices[triangle[i] as usize]);
+            draw_triangle(&mut listed, state, v1, v2, v3, shader);
+            let [v1, v2, v3] = [v1, v2, v3].map(|v| transform(&v));
+            draw_triangle(&mut expected, state, v1, v2, v3, ColorShader);
+        }
+        assert_eq!(calls.get(), indices.len());
+
+        assert!(expected.color().iter().any(|&c| c != black));
+        assert_eq!(indexed.color(), expected.color());
+        assert_eq!(indexed.depth(), expected.depth());
+        assert_eq!(listed.color(), expected.color());
+        assert_eq!(listed.depth(), expected.depth());
+    }
+
+    #[test]
+    fn snapped_edges_through_pixel_centers_follow_the_top_left_rule() {
+        // vertices snap onto pixel centers, so every edge runs through some of them
+        let jitter = 0.001;
+        let triangle = [
+            (10.5 + jitter, 5.5 - jitter),
+            (20.5 - jitter, 5.5 + jitter),
+            (10.5 - jitter, 15.5 + jitter),
+        ];
+        let [a, b, c] = triangle;
+        for triangle in [[a, b, c], [a, c, b]] {
+            for bits in [4, 8] {
+                let hits = hits(&[triangle], Subpixel::Fixed(bits), SampleCount::X1);
+                for (i, &hits) in hits.iter().enumerate() {
+                    let (x, y) = (i % SIZE, i / SIZE);
+                    // the left and top edges are included, the diagonal is a right edge
+                    let inside = x >= 10 && y >= 5 && (x - 10) + (y - 5) < 10;
+                    assert_eq!(hits, inside as u32, "{bits} bits at {x}, {y}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn huge_coordinates_are_clamped_to_the_guard_band() {
+        assert_eq!(snap(1e30, 8), 1 << 29);
+        assert_eq!(snap(f32::NEG_INFINITY, 8), -(1 << 29));
+        assert_eq!(snap(-3e6, 1), -(1 << 22));
+
+        let (min, max) = (-1e20, 1e20);
+        let quad = [
+            [(min, min), (max, min), (max, max)],
+            [(min, min), (max, max), (min, max)],
+        ];
+        for bits in [1, 4, 8] {
+            let hits = hits(&quad, Subpixel::Fixed(bits), SampleCount::X4);
+            assert!(hits.iter().all(|&hits| hits == 1), "{bits} bits");
+        }
+    }
+
+    #[test]
+    fn shared_edges_are_covered_exactly_once() {
+        let subpixels = [Subpixel::Float, Subpixel::Fixed(4), Subpixel::Fixed(8)];
+        let samples = [SampleCount::X1, SampleCount::X4];
+        for (name, triangles) in [("fan", fan(24)), ("grid", grid())] {
+            let reversed: Vec<Triangle> = triangles.iter().map(|&[a, b, c]| [a, c, b]).collect();
+            for (winding, triangles) in [("cw", &triangles), ("ccw", &reversed)] {
+                for subpixel in subpixels {
+                    for samples in samples {
+                        let hits = hits(triangles, subpixel, samples);
+                        let count = samples.count();
+                        for (i, &hits) in hits.iter().enumerate() {
+                            let (x, y) = (i / count % SIZE, i / count / SIZE);
+                            assert_eq!(
+                                hits, 1,
+                                "{name} {winding} {subpixel:?} {samples:?} at {x}, {y}"
+                            );
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,428 @@
+use tint::{Color, LinearRgb};
+
+use crate::clip::clip_segment;
+use crate::{
+    BLOCK_SIZE, BlendState, DepthFormat, DepthState, DrawState, Fragment, FragmentBlock,
+    Framebuffer, Rgba, Shader,
+};
+
+/// How [`draw_lines`] connects vertices.
+///
+/// [`draw_lines`]: crate::draw_lines
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum LineTopology {
+    /// Every two vertices form a line, a trailing vertex is ignored.
+    #[default]
+    List,
+    /// Every vertex after the first continues the line from the previous one.
+    Strip,
+}
+
+// Xiaolin Wu's line algorithm.
+//
+// The line is stepped along its major axis one pixel at a time and covers the two pixels
+// closest to it on the minor axis, weighted by their distance. The pixels at both ends are
+// also weighted by how much of them the line spans. Coverage is blended over the target like
+// alpha, every sample of a pixel gets the same coverage.
+pub(crate) fn rast_line_aa_inner<Pixel: Color, D: DepthFormat>(
+    target: &mut Framebuffer<'_, Pixel, D>,
+    depth: DepthState,
+    [[v1x, v1y, v1z], [v2x, v2y, v2z]]: [[f32; 3]; 2],
+    c: Pixel,
+) {
+    let (pixels, mut zbuffer) = (&mut *target.color, target.depth.as_deref_mut());
+    let (width, height, samples) = (target.width, target.height, target.samples);
+    // pixel centers on integers
+    let (mut ax, mut ay, mut bx, mut by) = (v1x - 0.5, v1y - 0.5, v2x - 0.5, v2y - 0.5);
+    let (mut az, mut bz) = (v1z, v2z);
+    let steep = (by - ay).abs() > (bx - ax).abs();
+    if steep {
+        core::mem::swap(&mut ax, &mut ay);
+        core::mem::swap(&mut bx, &mut by);
+    }
+    if ax > bx {
+        core::mem::swap(&mut ax, &mut bx);
+        core::mem::swap(&mut ay, &mut by);
+        core::mem::swap(&mut az, &mut bz);
+    }
+    let dx = bx - ax;
+    if dx == 0.0 || !dx.is_finite() {
+        return;
+    }
+    let gradient = (by - ay) / dx;
+    let step_z = (bz - az) / dx;
+
+    let color: LinearRgb = c.into();
+    let n = samples.count();
+    let (major_len, minor_len) = if steep {
+        (height, width)
+    } else {
+        (width, height)
+    };
+    let mut plot = |major: usize, minor: f32, coverage: f32, z: f32| {
+        if coverage <= 0.0 || minor < 0.0 || minor >= minor_len as f32 {
+            return;
+        }
+        let minor = minor as usize;
+        let (x, y) = if steep {
+            (minor, major)
+        } else {
+            (major, minor)
+        };
+        let index = (y * width + x) * n;
+        let src = Rgba::new(color, coverage);
+        for index in index..index + n {
+            if let Some(zbuffer) = zbuffer.as_deref_mut() {
+                let z = D::from_f32(z);
+                if !depth.compare.test(z.to_f32(), zbuffer[index].to_f32()) {
+                    continue;
+                }
+                if depth.write {
+                    zbuffer[index] = z;
+                }
+            }
+            pixels[index] = BlendState::ALPHA.blend(src, pixels[index].into()).into();
+        }
+    };
+
+    // only the pixels inside the target are stepped
+    let first = libm::roundf(ax).max(0.0);
+    let last = libm::roundf(bx).min(major_len as f32 - 1.0);
+    if first > last {
+        return;
+    }
+    for major in first as usize..=last as usize {
+        let x = major as f32;
+        let span = (x + 0.5).min(bx) - (x - 0.5).max(ax);
+        let y = ay + gradient * (x - ax);
+        let z = az + step_z * (x - ax).clamp(0.0, dx);
+        let minor = libm::floorf(y);
+        let fract = y - minor;
+        plot(major, minor, span * (1.0 - fract), z);
+        plot(major, minor + 1.0, span * fract, z);
+    }
+}
+
+// Rasterizes a line between vertices `[x, y, z / w, 1 / w]` in screen space.
+//
+// Pixels are drawn where the line crosses the center of their column, or row for steep lines,
+// from the start to before the end. The pixels are shaded in blocks of consecutive pixels
+// with the barycentric coordinates `(1 - t, t, 0)`, the third vertex repeats the first. Lines
+// are always front facing, and tested before shading like triangles.
+pub(crate) fn rast_line_shaded<S: Shader, Pixel: Color, D: DepthFormat>(
+    target: &mut Framebuffer<'_, Pixel, D>,
+    [([v1x, v1y, v1z, v1w], d1), ([v2x, v2y, v2z, v2w], d2)]: [([f32; 4], S::VertexData); 2],
+    shader: &mut S,
+    state: DrawState,
+) {
+    let (width, height, samples) = (target.width, target.height, target.samples);
+    let pixels = &mut *target.color;
+    let mut zbuffer = target.depth.as_deref_mut();
+    let stencil = target.stencil.as_deref_mut();
+    let bounds = (0.0, 0.0, width as f32, height as f32);
+    let Some((t0, t1)) = clip_segment((v1x, v1y), (v2x, v2y), bounds) else {
+        return;
+    };
+
+    let steep = (v2y - v1y).abs() > (v2x - v1x).abs();
+    let (a, b, minor_a, minor_b, major_len, minor_len) = if steep {
+        (v1y, v2y, v1x, v2x, height, width)
+    } else {
+        (v1x, v2x, v1y, v2y, width, height)
+    };
+    let d = b - a;
+    if d == 0.0 || !d.is_finite() {
+        return;
+    }
+
+    // centers `c + 0.5` between the clipped end points, `lo..hi` in the direction of `d`
+    let (ca, cb) = (a + d * t0 - 0.5, a + d * t1 - 0.5);
+    let clamp = |c: f32| c.clamp(0.0, major_len as f32) as usize;
+    let (lo, hi) = if d > 0.0 {
+        (clamp(libm::ceilf(ca)), clamp(libm::ceilf(cb)))
+    } else {
+        (clamp(libm::floorf(cb) + 1.0), clamp(libm::floorf(ca) + 1.0))
+    };
+    let columns = lo..hi.max(lo);
+    let forward = d > 0.0;
+
+    let mut stencil = stencil.filter(|_| state.stencil.is_enabled());
+    let blend = state.blend.is_enabled();
+    let n = samples.count();
+    let mut colors = [Fragment::Discard; BLOCK_SIZE];
+    let mut shade = |lanes: &[(usize, f32)]| {
+        let mut fragments = FragmentBlock {
+            mask: 0,
+            front_facing: true,
+            perspective: [[0.0; BLOCK_SIZE]; 3],
+            linear: [[0.0; BLOCK_SIZE]; 3],
+        };
+        let mut passed = [0u32; BLOCK_SIZE];
+        let mut depths = [D::from_f32(0.0); BLOCK_SIZE];
+        for (i, &(index, t)) in lanes.iter().enumerate() {
+            let z = D::from_f32(v1z + (v2z - v1z) * t);
+            for s in 0..n {
+                let j = index * n + s;
+                let depth_pass = match zbuffer.as_deref() {
+                    Some(zbuffer) => state.depth.compare.test(z.to_f32(), zbuffer[j].to_f32()),
+                    None => true,
+                };
+                let pass = match stencil.as_deref_mut() {
+                    Some(stencil) => {
+                        let stencil = core::iter::once(&mut stencil[j]);
+                        state.stencil.test(stencil, 1, depth_pass as u32, true) != 0
+                    }
+                    None => depth_pass,
+                };
+                passed[i] |= (pass as u32) << s;
+            }
+            if passed[i] != 0 {
+                fragments.mask |= 1 << i;
+            }
+
+            // `1 / w` interpolates linearly in screen space
+            let p = t * v2w / (v1w + (v2w - v1w) * t);
+            fragments.perspective[0][i] = 1.0 - p;
+            fragments.perspective[1][i] = p;
+            fragments.linear[0][i] = 1.0 - t;
+            fragments.linear[1][i] = t;
+            depths[i] = z;
+        }
+        if fragments.mask == 0 {
+            return;
+        }
+        shader.fragment_block(&fragments, d1, d2, d1, &mut colors);
+
+        for (i, &(index, _)) in lanes.iter().enumerate() {
+            let Fragment::Color(color) = colors[i] else {
+                continue;
+            };
+            for s in (0..n).filter(|s| passed[i] & (1 << s) != 0) {
+                let j = index * n + s;
+                if let Some(stencil) = stencil.as_deref_mut() {
+                    state
+                        .stencil
+                        .pass(core::iter::once(&mut stencil[j]), 1, true);
+                }
+                if let Some(zbuffer) = zbuffer.as_deref_mut().filter(|_| state.depth.write) {
+                    zbuffer[j] = depths[i];
+                }
+                pixels[j] = if blend {
+                    state.blend.blend(color, pixels[j].into()).into()
+                } else {
+                    color.color.into()
+                };
+            }
+        }
+    };
+
+    // the pixel of the start point is always drawn and the one of the end point never, so
+    // connected lines share exactly one pixel at every vertex
+    let pixel = |x: f32, y: f32| {
+        let inside = (0.0..width as f32).contains(&x) && (0.0..height as f32).contains(&y);
+        inside.then(|| y as usize * width + x as usize)
+    };
+    let start = pixel(v1x, v1y);
+    let end = pixel(v2x, v2y);
+
+    let mut lanes = [(0, 0.0); BLOCK_SIZE];
+    let mut len = 0;
+    let mut push = |index: usize, t: f32| {
+        lanes[len] = (index, t);
+        len += 1;
+        if len == BLOCK_SIZE {
+            shade(&lanes);
+            len = 0;
+        }
+    };
+    let mut drawn_start = false;
+    let mut step = |c: usize| {
+        let t = (c as f32 + 0.5 - a) / d;
+        let minor = minor_a + (minor_b - minor_a) * t;
+        if !(0.0..minor_len as f32).contains(&minor) {
+            return;
+        }
+        let minor = minor as usize;
+        let (x, y) = if steep { (minor, c) } else { (c, minor) };
+        let index = y * width + x;
+        if Some(index) != end {
+            drawn_start |= Some(index) == start;
+            push(index, t);
+        }
+    };
+    if forward {
+        columns.for_each(&mut step);
+    } else {
+        columns.rev().for_each(&mut step);
+    }
+    if let Some(start) = start.filter(|_| !drawn_start && start != end) {
+        push(start, 0.0);
+    }
+    if len > 0 {
+        shade(&lanes[..len]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+
+    use crate::{
+        ColorShader, DrawState, LineTopology, OwnedFramebuffer, SampleCount, draw_lines,
+        rast_line_aa, testing,
+    };
+    use tint::LinearRgb;
+
+    // Coverage of every pixel of a white line drawn over black.
+    fn coverage(size: usize, a: (f32, f32), b: (f32, f32)) -> Vec<f32> {
+        let black = LinearRgb::rgb(0.0, 0.0, 0.0);
+        let mut target = OwnedFramebuffer::new(size, size, black);
+        let white = LinearRgb::rgb(1.0, 1.0, 1.0);
+        rast_line_aa(&mut target, [[a.0, a.1, 0.0], [b.0, b.1, 0.0]], white);
+        target.color().iter().map(|c| c.r).collect()
+    }
+
+    #[test]
+    fn end_pixels_are_covered_by_the_part_of_them_the_line_spans() {
+        let image = coverage(8, (2.25, 5.5), (6.75, 5.5));
+        let row = &image[5 * 8..6 * 8];
+        assert_eq!(row, [0.0, 0.0, 0.75, 1.0, 1.0, 1.0, 0.75, 0.0]);
+        assert_eq!(image.iter().sum::<f32>(), 4.5);
+
+        // a line within a single pixel
+        let image = coverage(8, (3.25, 3.5), (3.75, 3.5));
+        assert_eq!(image[3 * 8 + 3], 0.5);
+        assert_eq!(image.iter().sum::<f32>(), 0.5);
+    }
+
+    #[test]
+    fn coverage_is_split_between_the_two_closest_pixels() {
+        // a quarter of the way from the center of row 2 to the center of row 3, across the
+        // whole target
+        let image = coverage(8, (0.0, 2.75), (8.0, 2.75));
+        for x in 0..8 {
+            let column: Vec<f32> = (0..8).map(|y| image[y * 8 + x]).collect();
+            assert_eq!(column, [0.0, 0.0, 0.75, 0.25, 0.0, 0.0, 0.0, 0.0], "{x}");
+        }
+    }
+
+    #[test]
+    fn steep_lines_mirror_shallow_ones() {
+        let (a, b) = ((1.25, 2.5), (13.75, 7.125));
+        let shallow = coverage(16, a, b);
+        let steep = coverage(16, (a.1, a.0), (b.1, b.0));
+        let reversed = coverage(16, b, a);
+        for y in 0..16 {
+            for x in 0..16 {
+                assert_eq!(shallow[y * 16 + x], steep[x * 16 + y], "{x}, {y}");
+                assert_eq!(shallow[y * 16 + x], reversed[y * 16 + x], "{x}, {y}");
+            }
+        }
+        // every column of the line is covered by its span
+        for x in 2..13 {
+            let sum: f32 = (0..16).map(|y| shallow[y * 16 + x]).sum();
+            assert!((sum - 1.0).abs() < 1e-6, "{x}");
+        }
+    }
+
+    #[test]
+    fn lines_are_clipped_to_the_target() {
+        let (a, b) = ((-20.375, 3.25), (40.625, 12.875));
+        let clipped = coverage(16, a, b);
+        let offset = 24.0;
+        let full = coverage(
+            64,
+            (a.0 + offset, a.1 + offset),
+            (b.0 + offset, b.1 + offset),
+        );
+        for y in 0..16 {
+            for x in 0..16 {
+                let expected = full[(y + 24) * 64 + x + 24];
+                assert!((clipped[y * 16 + x] - expected).abs() < 1e-5, "{x}, {y}");
+            }
+        }
+
+        // only the pixels inside of the target are stepped
+        let far = coverage(16, (-1e7, 0.5), (1e7, 15.5));
+        assert!(far.iter().all(|c| (0.0..=1.0).contains(c)));
+        assert!(
+            coverage(16, (-10.0, -10.0), (-1.0, 40.0))
+                .iter()
+                .all(|&c| c == 0.0)
+        );
+    }
+
+    const SIZE: usize = 16;
+
+    // Number of lines covering every pixel, with vertices in pixels.
+    fn hits(topology: LineTopology, points: &[(f32, f32, f32)]) -> Vec<u32> {
+        let one = LinearRgb::rgb(1.0, 1.0, 1.0);
+        let size = SIZE as f32;
+        let vertices: Vec<_> = points
+            .iter()
+            .map(|&(x, y, z)| ([x / size * 2.0 - 1.0, 1.0 - y / size * 2.0, z, 1.0], one))
+            .collect();
+        let state = DrawState::default();
+        testing::hits((SIZE, SIZE), SampleCount::X1, state, |target, state| {
+            draw_lines(target, state, topology, &vertices, ColorShader);
+        })
+    }
+
+    #[test]
+    fn lists_draw_every_pair_of_vertices() {
+        let points = [
+            (2.5, 2.5, 0.5),
+            (10.5, 2.5, 0.5),
+            (2.5, 8.5, 0.5),
+            (10.5, 8.5, 0.5),
+            (2.5, 12.5, 0.5),
+        ];
+        let hits = hits(LineTopology::List, &points);
+        // from the start up to before the end, the trailing vertex is ignored
+        for (y, row) in hits.chunks(SIZE).enumerate() {
+            let expected = if y == 2 || y == 8 { 8 } else { 0 };
+            assert_eq!(row.iter().sum::<u32>(), expected, "{y}");
+            if expected > 0 {
+                assert!(row[2..10].iter().all(|&hits| hits == 1), "{y}");
+            }
+        }
+    }
+
+    #[test]
+    fn strips_share_their_vertices() {
+        // a closed loop of steep and shallow lines in both directions
+        let points = [
+            (2.5, 2.5, 0.5),
+            (12.5, 4.5, 0.5),
+            (10.5, 13.5, 0.5),
+            (3.5, 9.5, 0.5),
+            (2.5, 2.5, 0.5),
+        ];
+        let covered = hits(LineTopology::Strip, &points);
+        assert!(covered.iter().all(|&hits| hits <= 1));
+        // every vertex is drawn exactly once, by the line that starts at it
+        for &(x, y, _) in &points {
+            assert_eq!(covered[y as usize * SIZE + x as usize], 1, "{x}, {y}");
+        }
+        // the major axis of each line is covered once, 10 + 9 + 7 + 7 pixels
+        assert_eq!(covered.iter().sum::<u32>(), 33);
+
+        // a single vertex has no line
+        assert!(
+            hits(LineTopology::Strip, &points[..1])
+                .iter()
+                .all(|&hits| hits == 0)
+        );
+    }
+
+    #[test]
+    fn lines_crossing_the_near_plane_are_clipped() {
+        // from in front of the near plane at the left edge to the right edge
+        let points = [(0.0, 8.5, -1.0), (16.0, 8.5, 1.0)];
+        let hits = hits(LineTopology::List, &points);
+        let row = &hits[8 * SIZE..9 * SIZE];
+        assert!(row[..8].iter().all(|&hits| hits == 0), "{row:?}");
+        assert!(row[8..].iter().all(|&hits| hits == 1), "{row:?}");
+        assert_eq!(hits.iter().sum::<u32>(), 8);
+    }
+}
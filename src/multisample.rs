@@ -0,0 +1,176 @@
+use tint::{Color, LinearRgb};
+
+/// Number of samples per pixel of a render target.
+///
+/// Multisampled targets store color, depth and stencil per sample, interleaved per pixel.
+/// Triangles test depth and stencil at every sample and shade once per pixel, then
+/// [`Framebuffer::resolve`] averages the samples.
+///
+/// [`Framebuffer::resolve`]: crate::Framebuffer::resolve
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SampleCount {
+    #[default]
+    X1,
+    X2,
+    X4,
+    X8,
+}
+
+impl SampleCount {
+    #[inline]
+    pub const fn count(self) -> usize {
+        match self {
+            Self::X1 => 1,
+            Self::X2 => 2,
+            Self::X4 => 4,
+            Self::X8 => 8,
+        }
+    }
+
+    /// The standard sample positions of Direct3D and Vulkan, in pixels from the pixel center
+    /// with `y` pointing down.
+    #[inline]
+    pub const fn positions(self) -> &'static [(f32, f32)] {
+        match self {
+            Self::X1 => &X1,
+            Self::X2 => &X2,
+            Self::X4 => &X4,
+            Self::X8 => &X8,
+        }
+    }
+}
+
+const fn sixteenths<const N: usize>(positions: [(i8, i8); N]) -> [(f32, f32); N] {
+    let mut out = [(0.0, 0.0); N];
+    let mut i = 0;
+    while i < N {
+        out[i] = (positions[i].0 as f32 / 16.0, positions[i].1 as f32 / 16.0);
+        i += 1;
+    }
+    out
+}
+
+const X1: [(f32, f32); 1] = [(0.0, 0.0)];
+const X2: [(f32, f32); 2] = sixteenths([(4, 4), (-4, -4)]);
+const X4: [(f32, f32); 4] = sixteenths([(-2, -6), (6, -2), (-6, 2), (2, 6)]);
+#[rustfmt::skip]
+const X8: [(f32, f32); 8] = sixteenths([
+    (1, -3), (-1, 3), (5, 1), (-3, -5),
+    (-5, 5), (-7, -1), (3, 7), (7, -7),
+]);
+
+// Averages every `samples` consecutive colors in linear space.
+pub(crate) fn resolve<P: Color, Q: Color>(color: &[P], samples: usize, target: &mut [Q]) {
+    let scale = 1.0 / samples as f32;
+    for (pixel, samples) in target.iter_mut().zip(color.chunks_exact(samples)) {
+        let sum = samples[1..]
+            .iter()
+            .fold(samples[0].into(), |sum: LinearRgb, c| sum + (*c).into());
+        *pixel = (sum * scale).into();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tint::Srgb;
+
+    use super::*;
+    use crate::{ColorShader, DrawState, OwnedFramebuffer, rast_triangle_checked};
+
+    const COUNTS: [SampleCount; 4] = [
+        SampleCount::X1,
+        SampleCount::X2,
+        SampleCount::X4,
+        SampleCount::X8,
+    ];
+
+    #[test]
+    fn positions_are_the_standard_patterns() {
+        for samples in COUNTS {
+            let positions = samples.positions();
+            assert_eq!(positions.len(), samples.count());
+            for (i, &(x, y)) in positions.iter().enumerate() {
+                assert!((-0.5..0.5).contains(&x) && (-0.5..0.5).contains(&y));
+                assert_eq!(
+                    (x * 16.0, y * 16.0),
+                    (libm::roundf(x * 16.0), libm::roundf(y * 16.0))
+                );
+                assert!(!positions[..i].contains(&(x, y)), "{samples:?}");
+            }
+            // centered on the pixel
+            let sum = positions
+                .iter()
+                .fold((0.0, 0.0), |s, p| (s.0 + p.0, s.1 + p.1));
+            assert_eq!(sum, (0.0, 0.0), "{samples:?}");
+        }
+        assert_eq!(SampleCount::X2.positions(), [(0.25, 0.25), (-0.25, -0.25)]);
+        assert_eq!(
+            SampleCount::X4.positions(),
+            [
+                (-0.125, -0.375),
+                (0.375, -0.125),
+                (-0.375, 0.125),
+                (0.125, 0.375)
+            ]
+        );
+    }
+
+    #[test]
+    fn samples_are_covered_at_their_position() {
+        let (black, white) = (LinearRgb::rgb(0.0, 0.0, 0.0), LinearRgb::rgb(1.0, 1.0, 1.0));
+        for samples in COUNTS {
+            // everything right of the center of column 2 and below the center of row 1
+            let mut target = OwnedFramebuffer::new_multisample(4, 4, samples, black);
+            rast_triangle_checked(
+                &mut target,
+                DrawState::default(),
+                [
+                    ([2.5, 1.5, 0.0], white),
+                    ([100.0, 1.5, 0.0], white),
+                    ([2.5, 100.0, 0.0], white),
+                ],
+                ColorShader,
+            );
+
+            let n = samples.count();
+            for (i, &color) in target.color().iter().enumerate() {
+                let (pixel, (ox, oy)) = (i / n, samples.positions()[i % n]);
+                let (x, y) = ((pixel % 4) as f32 + 0.5 + ox, (pixel / 4) as f32 + 0.5 + oy);
+                let covered = x >= 2.5 && y >= 1.5;
+                assert_eq!(color.r > 0.5, covered, "{samples:?} at {x}, {y}");
+            }
+        }
+    }
+
+    #[test]
+    fn resolving_averages_in_linear_space() {
+        let (black, white) = (LinearRgb::rgb(0.0, 0.0, 0.0), LinearRgb::rgb(1.0, 1.0, 1.0));
+        let mut target = OwnedFramebuffer::new_multisample(4, 1, SampleCount::X4, black);
+        rast_triangle_checked(
+            &mut target,
+            DrawState::default(),
+            [
+                ([1.5, -10.0, 0.0], white),
+                ([100.0, -10.0, 0.0], white),
+                ([1.5, 100.0, 0.0], white),
+            ],
+            ColorShader,
+        );
+
+        let mut linear = OwnedFramebuffer::new(4, 1, white);
+        target.resolve(&mut linear);
+        let resolved: [f32; 4] = core::array::from_fn(|i| linear.color()[i].r);
+        // half of the samples of pixel 1 are right of its center
+        assert_eq!(resolved, [0.0, 0.5, 1.0, 1.0]);
+
+        // encoded after averaging, rather than averaging encoded colors
+        let mut srgb = OwnedFramebuffer::new(4, 1, Srgb::default());
+        target.resolve(&mut srgb);
+        assert_eq!(srgb.color()[1], Srgb::from(LinearRgb::rgb(0.5, 0.5, 0.5)));
+
+        let mut colors = [Srgb::default(); 2];
+        let samples = [Srgb::rgb(255, 255, 255), Srgb::rgb(0, 0, 0)];
+        resolve(&samples, 2, &mut colors[..1]);
+        assert_eq!(colors[0], srgb.color()[1]);
+    }
+}
@@ -0,0 +1,537 @@
+use core::f32::consts::{FRAC_PI_2, TAU};
+
+use alloc::vec::Vec;
+
+type Point = (f32, f32);
+
+/// Affine transform of 2D points, mapping `(x, y)` to `(a x + c y + e, b x + d y + f)`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Transform {
+    pub a: f32,
+    pub b: f32,
+    pub c: f32,
+    pub d: f32,
+    pub e: f32,
+    pub f: f32,
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+impl Transform {
+    pub const IDENTITY: Self = Self::new(1.0, 0.0, 0.0, 1.0, 0.0, 0.0);
+
+    pub const fn new(a: f32, b: f32, c: f32, d: f32, e: f32, f: f32) -> Self {
+        Self { a, b, c, d, e, f }
+    }
+
+    pub const fn translate(x: f32, y: f32) -> Self {
+        Self::new(1.0, 0.0, 0.0, 1.0, x, y)
+    }
+
+    pub const fn scale(x: f32, y: f32) -> Self {
+        Self::new(x, 0.0, 0.0, y, 0.0, 0.0)
+    }
+
+    /// Rotates by `angle` radians, clockwise on screen since `y` points down.
+    pub fn rotate(angle: f32) -> Self {
+        let (sin, cos) = libm::sincosf(angle);
+        Self::new(cos, sin, -sin, cos, 0.0, 0.0)
+    }
+
+    /// Applies `self`, then `other`.
+    pub fn then(self, other: Self) -> Self {
+        Self::new(
+            other.a * self.a + other.c * self.b,
+            other.b * self.a + other.d * self.b,
+            other.a * self.c + other.c * self.d,
+            other.b * self.c + other.d * self.d,
+            other.a * self.e + other.c * self.f + other.e,
+            other.b * self.e + other.d * self.f + other.f,
+        )
+    }
+
+    #[inline]
+    pub fn apply(self, (x, y): (f32, f32)) -> (f32, f32) {
+        (
+            self.a * x + self.c * y + self.e,
+            self.b * x + self.d * y + self.f,
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Segment {
+    Move(Point),
+    Line(Point),
+    Quad(Point, Point),
+    Cubic(Point, Point, Point),
+    Close,
+}
+
+// Most lines a curve is flattened into.
+const MAX_CURVE_LINES: usize = 1024;
+
+/// 2D vector path of lines and Bézier curves in screen space.
+///
+/// A path consists of subpaths, each started by [`Path::move_to`]. Segments continue from the
+/// end of the previous one, or from the origin at the start of the path. After
+/// [`Path::close`], they continue from the start of the closed subpath.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Path {
+    segments: Vec<Segment>,
+    start: Point,
+    current: Point,
+    tolerance: f32,
+}
+
+impl Default for Path {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Path {
+    pub const fn new() -> Self {
+        Self {
+            segments: Vec::new(),
+            start: (0.0, 0.0),
+            current: (0.0, 0.0),
+            tolerance: 0.1,
+        }
+    }
+
+    /// Sets the largest distance in pixels of the lines that curves are flattened into from
+    /// the curves, 0.1 by default.
+    pub fn with_tolerance(self, tolerance: f32) -> Self {
+        Self { tolerance, ..self }
+    }
+
+    /// Starts a new subpath at `x`, `y`.
+    pub fn move_to(&mut self, x: f32, y: f32) -> &mut Self {
+        self.start = (x, y);
+        self.current = (x, y);
+        self.segments.push(Segment::Move((x, y)));
+        self
+    }
+
+    pub fn line_to(&mut self, x: f32, y: f32) -> &mut Self {
+        self.current = (x, y);
+        self.segments.push(Segment::Line((x, y)));
+        self
+    }
+
+    /// Adds a quadratic Bézier curve with the control point `cx`, `cy`.
+    pub fn quad_to(&mut self, cx: f32, cy: f32, x: f32, y: f32) -> &mut Self {
+        self.current = (x, y);
+        self.segments.push(Segment::Quad((cx, cy), (x, y)));
+        self
+    }
+
+    /// Adds a cubic Bézier curve with the control points `c1x`, `c1y` and `c2x`, `c2y`.
+    pub fn cubic_to(
+        &mut self,
+        c1x: f32,
+        c1y: f32,
+        c2x: f32,
+        c2y: f32,
+        x: f32,
+        y: f32,
+    ) -> &mut Self {
+        self.current = (x, y);
+        self.segments
+            .push(Segment::Cubic((c1x, c1y), (c2x, c2y), (x, y)));
+        self
+    }
+
+    /// Adds an elliptical arc like the SVG arc command, with the radii `(rx, ry)` rotated by
+    /// `rotation` radians.
+    ///
+    /// Of the four arcs that fit, `large_arc` picks one of the two that span more than half
+    /// the ellipse and `sweep` one of the two that turn clockwise on screen. Radii that are
+    /// too small are scaled up until an arc fits, zero radii give a line.
+    pub fn arc_to(
+        &mut self,
+        (rx, ry): (f32, f32),
+        rotation: f32,
+        large_arc: bool,
+        sweep: bool,
+        x: f32,
+        y: f32,
+    ) -> &mut Self {
+        // https://www.w3.org/TR/SVG11/implnote.html#ArcConversionEndpointToCenter
+        let (x1, y1) = self.current;
+        if (x1, y1) == (x, y) {
+            return self;
+        }
+        let (mut rx, mut ry) = (rx.abs(), ry.abs());
+        if rx == 0.0 || ry == 0.0 {
+            return self.line_to(x, y);
+        }
+        let (sin, cos) = libm::sincosf(rotation);
+        let (dx, dy) = ((x1 - x) * 0.5, (y1 - y) * 0.5);
+        let (x1p, y1p) = (cos * dx + sin * dy, -sin * dx + cos * dy);
+        let lambda = (x1p * x1p) / (rx * rx) + (y1p * y1p) / (ry * ry);
+        if lambda > 1.0 {
+            let scale = libm::sqrtf(lambda);
+            rx *= scale;
+            ry *= scale;
+        }
+
+        let (rx2, ry2) = (rx * rx, ry * ry);
+        let den = rx2 * y1p * y1p + ry2 * x1p * x1p;
+        let num = rx2 * ry2 - den;
+        let coef = libm::sqrtf((num / den).max(0.0));
+        let coef = if large_arc == sweep { -coef } else { coef };
+        let (cxp, cyp) = (coef * rx * y1p / ry, -coef * ry * x1p / rx);
+        let cx = cos * cxp - sin * cyp + (x1 + x) * 0.5;
+        let cy = sin * cxp + cos * cyp + (y1 + y) * 0.5;
+
+        let angle = |ux: f32, uy: f32| libm::atan2f(uy, ux);
+        let start = angle((x1p - cxp) / rx, (y1p - cyp) / ry);
+        let end = angle((-x1p - cxp) / rx, (-y1p - cyp) / ry);
+        let mut delta = end - start;
+        if sweep && delta < 0.0 {
+            delta += TAU;
+        } else if !sweep && delta > 0.0 {
+            delta -= TAU;
+        }
+
+        self.ellipse_arc((cx, cy), (rx, ry), (sin, cos), start, delta, (x, y))
+    }
+
+    /// Adds an arc of the ellipse around `cx`, `cy` with the radii `rx`, `ry`, from the angle
+    /// `start` by `sweep` radians, clockwise on screen if positive.
+    ///
+    /// A line leads from the current point to the start of the arc, unless it starts the path.
+    pub fn arc(&mut self, cx: f32, cy: f32, rx: f32, ry: f32, start: f32, sweep: f32) -> &mut Self {
+        let sweep = sweep.clamp(-TAU, TAU);
+        let point = |angle: f32| {
+            let (sin, cos) = libm::sincosf(angle);
+            (cx + rx * cos, cy + ry * sin)
+        };
+        // full turns end exactly where they started
+        let from = point(start);
+        let to = if sweep.abs() == TAU {
+            from
+        } else {
+            point(start + sweep)
+        };
+        if self.segments.is_empty() {
+            self.move_to(from.0, from.1);
+        } else if self.current != from {
+            self.line_to(from.0, from.1);
+        }
+        self.ellipse_arc((cx, cy), (rx, ry), (0.0, 1.0), start, sweep, to)
+    }
+
+    // Adds cubics along the ellipse around `center` with the `radii`, rotated by an angle with
+    // the given sine and cosine, from the angle `start` by `sweep` radians to `end`.
+    fn ellipse_arc(
+        &mut self,
+        (cx, cy): Point,
+        (rx, ry): Point,
+        (sin, cos): (f32, f32),
+        start: f32,
+        sweep: f32,
+        end: Point,
+    ) -> &mut Self {
+        // cubics approximate arcs of up to a quarter turn closely
+        let count = libm::ceilf(sweep.abs() / FRAC_PI_2).max(1.0) as usize;
+        let step = sweep / count as f32;
+        let k = 4.0 / 3.0 * libm::tanf(step * 0.25);
+        let map = |(ux, uy): Point| {
+            (
+                cx + cos * rx * ux - sin * ry * uy,
+                cy + sin * rx * ux + cos * ry * uy,
+            )
+        };
+        let mut a0 = start;
+        let (mut sin0, mut cos0) = libm::sincosf(a0);
+        for i in 1..=count {
+            let a1 = a0 + step;
+            let (sin1, cos1) = libm::sincosf(a1);
+            let c1 = map((cos0 - k * sin0, sin0 + k * cos0));
+            let c2 = map((cos1 + k * sin1, sin1 - k * cos1));
+            let p = if i == count { end } else { map((cos1, sin1)) };
+            self.cubic_to(c1.0, c1.1, c2.0, c2.1, p.0, p.1);
+            (a0, sin0, cos0) = (a1, sin1, cos1);
+        }
+        self
+    }
+
+    /// Closes the current subpath with a line back to its start.
+    pub fn close(&mut self) -> &mut Self {
+        self.current = self.start;
+        self.segments.push(Segment::Close);
+        self
+    }
+
+    /// Transforms every point of the path, which transforms the curves exactly.
+    pub fn transform(&mut self, transform: Transform) -> &mut Self {
+        let t = |p: &mut Point| *p = transform.apply(*p);
+        for segment in &mut self.segments {
+            match segment {
+                Segment::Move(p) | Segment::Line(p) => t(p),
+                Segment::Quad(c, p) => {
+                    t(c);
+                    t(p);
+                }
+                Segment::Cubic(c1, c2, p) => {
+                    t(c1);
+                    t(c2);
+                    t(p);
+                }
+                Segment::Close => {}
+            }
+        }
+        t(&mut self.start);
+        t(&mut self.current);
+        self
+    }
+
+    /// Calls `contour` with the points of every subpath, curves flattened into lines within
+    /// the tolerance, and whether it was closed.
+    pub fn flatten(&self, mut contour: impl FnMut(&[(f32, f32)], bool)) {
+        let mut points = Vec::new();
+        let mut start = (0.0, 0.0);
+        for &segment in &self.segments {
+            let from = match points.last() {
+                Some(&p) => p,
+                None => {
+                    points.push(start);
+                    start
+                }
+            };
+            match segment {
+                Segment::Move(p) => {
+                    if points.len() > 1 {
+                        contour(&points, false);
+                    }
+                    points.clear();
+                    points.push(p);
+                    start = p;
+                }
+                Segment::Line(p) => points.push(p),
+                Segment::Quad(c, p) => {
+                    // the distance of the chords of `n` lines is `|p0 - 2 c + p| / (4 n²)`
+                    let dd = (from.0 - 2.0 * c.0 + p.0, from.1 - 2.0 * c.1 + p.1);
+                    let count = self.curve_lines(libm::hypotf(dd.0, dd.1) / 4.0);
+                    for i in 1..count {
+                        let t = i as f32 / count as f32;
+                        let (u, tt) = (1.0 - t, t * t);
+                        points.push((
+                            u * u * from.0 + 2.0 * u * t * c.0 + tt * p.0,
+                            u * u * from.1 + 2.0 * u * t * c.1 + tt * p.1,
+                        ));
+                    }
+                    points.push(p);
+                }
+                Segment::Cubic(c1, c2, p) => {
+                    // bounded by the larger second difference, see Wang's formula
+                    let dd1 = (from.0 - 2.0 * c1.0 + c2.0, from.1 - 2.0 * c1.1 + c2.1);
+                    let dd2 = (c1.0 - 2.0 * c2.0 + p.0, c1.1 - 2.0 * c2.1 + p.1);
+                    let dd = libm::hypotf(dd1.0, dd1.1).max(libm::hypotf(dd2.0, dd2.1));
+                    let count = self.curve_lines(dd * 0.75);
+                    for i in 1..count {
+                        let t = i as f32 / count as f32;
+                        let u = 1.0 - t;
+                        let (a, b, c, d) = (u * u * u, 3.0 * u * u * t, 3.0 * u * t * t, t * t * t);
+                        points.push((
+                            a * from.0 + b * c1.0 + c * c2.0 + d * p.0,
+                            a * from.1 + b * c1.1 + c * c2.1 + d * p.1,
+                        ));
+                    }
+                    points.push(p);
+                }
+                Segment::Close => {
+                    if points.len() > 1 {
+                        contour(&points, true);
+                    }
+                    points.clear();
+                }
+            }
+        }
+        if points.len() > 1 {
+            contour(&points, false);
+        }
+    }
+
+    // Lines that keep a curve with the given `deviation` of a single chord within the
+    // tolerance, the deviation falls with the square of the lines.
+    fn curve_lines(&self, deviation: f32) -> usize {
+        let count = libm::ceilf(libm::sqrtf(deviation / self.tolerance));
+        (count as usize).clamp(1, MAX_CURVE_LINES)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec;
+
+    use super::*;
+
+    // Flattens the single contour of `path`.
+    fn flattened(path: &Path) -> Vec<Point> {
+        let mut points = Vec::new();
+        path.flatten(|contour, _| {
+            assert!(points.is_empty());
+            points.extend_from_slice(contour);
+        });
+        points
+    }
+
+    fn distance_to_line(p: Point, a: Point, b: Point) -> f32 {
+        let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+        let t = ((p.0 - a.0) * dx + (p.1 - a.1) * dy) / (dx * dx + dy * dy);
+        let t = t.clamp(0.0, 1.0);
+        libm::hypotf(a.0 + dx * t - p.0, a.1 + dy * t - p.1)
+    }
+
+    // Largest distance of the points of `curve` from the polyline.
+    fn deviation(points: &[Point], curve: impl Fn(f32) -> Point) -> f32 {
+        (0..=1000)
+            .map(|i| {
+                let p = curve(i as f32 / 1000.0);
+                points
+                    .windows(2)
+                    .map(|l| distance_to_line(p, l[0], l[1]))
+                    .fold(f32::INFINITY, f32::min)
+            })
+            .fold(0.0, f32::max)
+    }
+
+    #[test]
+    fn quads_are_flattened_within_the_tolerance() {
+        // `|p0 - 2 c + p|` is 12.8, which needs 6 lines for a tolerance of 0.1
+        for tolerance in [0.1, 0.25, 1.0] {
+            let mut path = Path::new().with_tolerance(tolerance);
+            path.move_to(0.0, 0.0).quad_to(50.0, 6.4, 100.0, 0.0);
+            let points = flattened(&path);
+            assert_eq!(points.first(), Some(&(0.0, 0.0)));
+            assert_eq!(points.last(), Some(&(100.0, 0.0)));
+            let deviation = deviation(&points, |t| {
+                let u = 1.0 - t;
+                (2.0 * u * t * 50.0 + t * t * 100.0, 2.0 * u * t * 6.4)
+            });
+            assert!(deviation <= tolerance, "{deviation} > {tolerance}");
+        }
+    }
+
+    #[test]
+    fn cubics_are_flattened_within_the_tolerance() {
+        let (p0, c1, c2, p) = ((0.0, 0.0), (30.0, 40.0), (70.0, -40.0), (100.0, 0.0));
+        for tolerance in [0.05, 0.1, 0.5] {
+            let mut path = Path::new().with_tolerance(tolerance);
+            path.move_to(p0.0, p0.1)
+                .cubic_to(c1.0, c1.1, c2.0, c2.1, p.0, p.1);
+            let points = flattened(&path);
+            assert_eq!(points.last(), Some(&p));
+            let deviation = deviation(&points, |t| {
+                let u = 1.0 - t;
+                let (a, b, c, d) = (u * u * u, 3.0 * u * u * t, 3.0 * u * t * t, t * t * t);
+                (
+                    a * p0.0 + b * c1.0 + c * c2.0 + d * p.0,
+                    a * p0.1 + b * c1.1 + c * c2.1 + d * p.1,
+                )
+            });
+            assert!(deviation <= tolerance, "{deviation} > {tolerance}");
+        }
+    }
+
+    #[test]
+    fn arcs_are_flattened_within_the_tolerance() {
+        let tolerance = 0.1;
+        let mut path = Path::new().with_tolerance(tolerance);
+        path.move_to(0.0, 0.0)
+            .arc_to((50.0, 50.0), 0.0, false, true, 100.0, 0.0);
+        let points = flattened(&path);
+        assert_eq!(points.first(), Some(&(0.0, 0.0)));
+        assert_eq!(points.last(), Some(&(100.0, 0.0)));
+
+        // a half circle around `50, 0` that turns clockwise on screen, over the top
+        let radius = |p: Point| libm::hypotf(p.0 - 50.0, p.1);
+        for line in points.windows(2) {
+            let mid = ((line[0].0 + line[1].0) * 0.5, (line[0].1 + line[1].1) * 0.5);
+            assert!(line[1].1 <= 1e-3, "{line:?}");
+            assert!((radius(line[1]) - 50.0).abs() <= 0.02, "{line:?}");
+            assert!(50.0 - radius(mid) <= tolerance + 0.02, "{line:?}");
+        }
+        let deviation = deviation(&points, |t| {
+            let (sin, cos) = libm::sincosf(core::f32::consts::PI * (1.0 + t));
+            (50.0 + 50.0 * cos, 50.0 * sin)
+        });
+        assert!(deviation <= tolerance + 0.02, "{deviation}");
+    }
+
+    #[test]
+    fn full_circles_end_at_their_start() {
+        for start in [0.0, 1.0, -2.5] {
+            for sweep in [TAU, -TAU, 10.0] {
+                let mut path = Path::new();
+                path.arc(3.0, 4.0, 5.0, 2.0, start, sweep);
+                let points = flattened(&path);
+                assert_eq!(points.first(), points.last());
+            }
+        }
+    }
+
+    #[test]
+    fn too_small_arc_radii_are_scaled_up() {
+        let mut path = Path::new();
+        path.move_to(0.0, 0.0)
+            .arc_to((1.0, 1.0), 0.0, false, false, 100.0, 0.0);
+        for p in flattened(&path) {
+            assert!(p.1 >= -1e-3, "{p:?}");
+            assert!(
+                (libm::hypotf(p.0 - 50.0, p.1) - 50.0).abs() <= 0.02,
+                "{p:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn transforms_compose_in_order() {
+        let translate = Transform::translate(10.0, -5.0);
+        let scale = Transform::scale(2.0, 3.0);
+        let rotate = Transform::rotate(0.3);
+        let p = (1.5, -2.0);
+
+        let q = translate.then(scale).apply(p);
+        assert_eq!(q, scale.apply(translate.apply(p)));
+        assert_eq!(q, (23.0, -21.0));
+        assert_eq!(scale.then(translate).apply(p), (13.0, -11.0));
+
+        let composed = rotate.then(scale).then(translate).apply(p);
+        let applied = translate.apply(scale.apply(rotate.apply(p)));
+        assert!(libm::hypotf(composed.0 - applied.0, composed.1 - applied.1) < 1e-5);
+
+        let sum = Transform::rotate(0.3).then(Transform::rotate(0.5));
+        let rotated = Transform::rotate(0.8);
+        for (a, b) in [
+            (sum.a, rotated.a),
+            (sum.b, rotated.b),
+            (sum.c, rotated.c),
+            (sum.d, rotated.d),
+        ] {
+            assert!((a - b).abs() < 1e-6);
+        }
+        assert_eq!(Transform::IDENTITY.then(rotate), rotate);
+    }
+
+    #[test]
+    fn transformed_paths_transform_their_points() {
+        let mut path = Path::new();
+        path.move_to(0.0, 0.0)
+            .line_to(1.0, 0.0)
+            .line_to(0.0, 1.0)
+            .close();
+        path.transform(Transform::scale(2.0, 2.0).then(Transform::translate(5.0, 5.0)));
+        let mut contours = Vec::new();
+        path.flatten(|points, closed| contours.push((points.to_vec(), closed)));
+        assert_eq!(contours, [(vec![(5.0, 5.0), (7.0, 5.0), (5.0, 7.0)], true)]);
+    }
+}
@@ -0,0 +1,262 @@
+use core::f32::consts::TAU;
+
+use tint::Color;
+
+use crate::stroke::arc_segments;
+use crate::{DepthFormat, DrawState, Framebuffer, Shader, Tile, rast_triangle_inner};
+
+/// Shape of a point.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum PointShape {
+    #[default]
+    Square,
+    Round,
+}
+
+/// How points are drawn.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PointStyle {
+    /// Width of squares and diameter of circles in pixels, independent of depth.
+    pub size: f32,
+    pub shape: PointShape,
+}
+
+impl Default for PointStyle {
+    fn default() -> Self {
+        Self::new(1.0)
+    }
+}
+
+impl PointStyle {
+    pub const fn new(size: f32) -> Self {
+        Self {
+            size,
+            shape: PointShape::Square,
+        }
+    }
+}
+
+// Position in screen space and vertex data of a corner of a point.
+type Corner<D> = ((f32, f32), D);
+
+// Rasterizes a point centered at `[x, y, z]` in screen space as triangles, circles as a
+// polygon close enough to them.
+//
+// The corners get the data of `Shader::point_sprite` at their position on the sprite, which
+// interpolates to the exact sprite coordinates of every fragment. The point is front facing.
+pub(crate) fn rast_point_inner<S: Shader, Pixel: Color, D: DepthFormat>(
+    target: &mut Framebuffer<'_, Pixel, D>,
+    state: DrawState,
+    style: PointStyle,
+    [x, y, z]: [f32; 3],
+    data: S::VertexData,
+    shader: &mut S,
+) {
+    let radius = style.size * 0.5;
+    if !(radius > 0.0 && radius.is_finite()) {
+        return;
+    }
+
+    // offset from the center in radii
+    let corner = |shader: &S, (dx, dy): (f32, f32)| {
+        let coord = (0.5 + dx * 0.5, 0.5 + dy * 0.5);
+        let position = (x + dx * radius, y + dy * radius);
+        (position, shader.point_sprite(data, coord))
+    };
+    let mut triangle = |shader: &mut S, corners: [Corner<S::VertexData>; 3]| {
+        let vertices = corners.map(|((x, y), d)| ([x, y, z, 1.0], d));
+        let tile = Tile::full(target.width, target.height);
+        rast_triangle_inner(target, vertices, shader, state, true, tile);
+    };
+
+    match style.shape {
+        PointShape::Square => {
+            let [tl, tr, br, bl] =
+                [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)].map(|c| corner(shader, c));
+            triangle(shader, [tl, tr, br]);
+            triangle(shader, [tl, br, bl]);
+        }
+        PointShape::Round => {
+            let center = corner(shader, (0.0, 0.0));
+            let count = arc_segments(radius, TAU).max(4);
+            let first = corner(shader, (1.0, 0.0));
+            let mut prev = first;
+            for i in 1..=count {
+                let next = if i == count {
+                    first
+                } else {
+                    let (sin, cos) = libm::sincosf(TAU * i as f32 / count as f32);
+                    corner(shader, (cos, sin))
+                };
+                triangle(shader, [center, prev, next]);
+                prev = next;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+
+    use tint::LinearRgb;
+
+    use super::*;
+    use crate::{ColorShader, Fragment, OwnedFramebuffer, draw_point, rast_point};
+
+    const SIZE: usize = 16;
+
+    // Pixels covered by a white point in screen space.
+    fn covered(style: PointStyle, x: f32, y: f32) -> Vec<bool> {
+        let black = LinearRgb::rgb(0.0, 0.0, 0.0);
+        let mut target = OwnedFramebuffer::new(SIZE, SIZE, black);
+        let white = LinearRgb::rgb(1.0, 1.0, 1.0);
+        rast_point(&mut target, style, [x, y], white, ColorShader);
+        target.color().iter().map(|c| c.r > 0.5).collect()
+    }
+
+    fn count(covered: &[bool]) -> usize {
+        covered.iter().filter(|&&c| c).count()
+    }
+
+    #[test]
+    fn squares_cover_the_pixel_centers_inside_of_them() {
+        for (size, x, y, expected) in [
+            // centered on a pixel
+            (1.0, 7.5, 7.5, 1),
+            (1.5, 7.5, 7.5, 1),
+            (2.0, 7.5, 7.5, 4),
+            (3.0, 7.5, 7.5, 9),
+            // centered on a corner between pixels
+            (1.0, 8.0, 8.0, 1),
+            (2.0, 8.0, 8.0, 4),
+            (3.0, 8.0, 8.0, 9),
+            (4.0, 8.0, 8.0, 16),
+            // too small to reach a center
+            (0.5, 8.0, 8.0, 0),
+        ] {
+            let covered = covered(PointStyle::new(size), x, y);
+            assert_eq!(count(&covered), expected, "{size} at {x}, {y}");
+        }
+
+        for size in [0.0, -1.0, f32::NAN, f32::INFINITY] {
+            assert_eq!(
+                count(&covered(PointStyle::new(size), 8.0, 8.0)),
+                0,
+                "{size}"
+            );
+        }
+    }
+
+    #[test]
+    fn circles_cover_the_pixel_centers_inside_of_them() {
+        let style = PointStyle {
+            size: 9.0,
+            shape: PointShape::Round,
+        };
+        let covered = covered(style, 8.0, 8.0);
+        for (i, &covered) in covered.iter().enumerate() {
+            let (x, y) = ((i % SIZE) as f32 + 0.5, (i / SIZE) as f32 + 0.5);
+            let distance = libm::hypotf(x - 8.0, y - 8.0);
+            if distance < 4.3 {
+                assert!(covered, "{x}, {y}");
+            } else if distance > 4.5 {
+                assert!(!covered, "{x}, {y}");
+            }
+        }
+    }
+
+    #[test]
+    fn points_are_clipped_to_the_target() {
+        // only the quarter inside of the target is drawn
+        let covered = covered(PointStyle::new(4.0), 0.0, 0.0);
+        assert_eq!(count(&covered), 4);
+        assert!(covered[0] && covered[1] && covered[SIZE] && covered[SIZE + 1]);
+        let covered = self::covered(PointStyle::new(4.0), 16.0, 8.0);
+        assert_eq!(count(&covered), 8);
+
+        // points with their center outside of the view frustum are discarded entirely
+        let black = LinearRgb::rgb(0.0, 0.0, 0.0);
+        let white = LinearRgb::rgb(1.0, 1.0, 1.0);
+        let mut target = OwnedFramebuffer::new(SIZE, SIZE, black);
+        let style = PointStyle::new(8.0);
+        let state = DrawState::default();
+        draw_point(
+            &mut target,
+            state,
+            style,
+            ([1.01, 0.0, 0.5, 1.0], white),
+            ColorShader,
+        );
+        draw_point(
+            &mut target,
+            state,
+            style,
+            ([0.0, 0.0, -0.1, 1.0], white),
+            ColorShader,
+        );
+        assert!(target.color().iter().all(|&c| c == black));
+        draw_point(
+            &mut target,
+            state,
+            style,
+            ([0.99, 0.0, 0.5, 1.0], white),
+            ColorShader,
+        );
+        assert_eq!(target.color().iter().filter(|c| c.r > 0.5).count(), 4 * 8);
+    }
+
+    // Writes sprite coordinates to the red and green channels.
+    struct SpriteShader;
+
+    impl Shader for SpriteShader {
+        type VertexData = (f32, f32);
+
+        fn interpolate(
+            &self,
+            bcx: f32,
+            bcy: f32,
+            bcz: f32,
+            d1: (f32, f32),
+            d2: (f32, f32),
+            d3: (f32, f32),
+        ) -> (f32, f32) {
+            (
+                crate::barycentric_lerp(bcx, bcy, bcz, d1.0, d2.0, d3.0),
+                crate::barycentric_lerp(bcx, bcy, bcz, d1.1, d2.1, d3.1),
+            )
+        }
+
+        fn point_sprite(&self, _: (f32, f32), coord: (f32, f32)) -> (f32, f32) {
+            coord
+        }
+
+        fn fragment(&mut self, (u, v): (f32, f32)) -> Fragment {
+            LinearRgb::rgb(u, v, 1.0).into()
+        }
+    }
+
+    #[test]
+    fn sprites_run_from_the_top_left_to_the_bottom_right_corner() {
+        for shape in [PointShape::Square, PointShape::Round] {
+            let black = LinearRgb::rgb(0.0, 0.0, 0.0);
+            let mut target = OwnedFramebuffer::new(SIZE, SIZE, black);
+            let style = PointStyle { size: 8.0, shape };
+            rast_point(&mut target, style, [8.0, 8.0], (0.0, 0.0), SpriteShader);
+            for (i, c) in target.color().iter().enumerate() {
+                if c.b == 0.0 {
+                    continue;
+                }
+                let (x, y) = ((i % SIZE) as f32 + 0.5, (i / SIZE) as f32 + 0.5);
+                let (u, v) = ((x - 4.0) / 8.0, (y - 4.0) / 8.0);
+                assert!(
+                    (c.r - u).abs() < 1e-5 && (c.g - v).abs() < 1e-5,
+                    "{shape:?} {x}, {y}"
+                );
+            }
+            // the corner pixel is only covered by squares
+            let corner = target.color()[4 * SIZE + 4];
+            assert_eq!(corner.b > 0.5, shape == PointShape::Square, "{shape:?}");
+        }
+    }
+}
@@ -0,0 +1,402 @@
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::SampleCount;
+
+/// Decides which areas enclosed by the contours of a polygon are inside.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum FillRule {
+    /// Inside where the contours wind around a point at all, holes wind the other way.
+    #[default]
+    NonZero,
+    /// Inside where a ray from a point crosses an odd number of edges.
+    EvenOdd,
+}
+
+impl FillRule {
+    #[inline]
+    fn inside(self, winding: i32) -> bool {
+        match self {
+            Self::NonZero => winding != 0,
+            Self::EvenOdd => winding % 2 != 0,
+        }
+    }
+}
+
+type Point = (f32, f32);
+
+// Sub-scanlines per row of anti-aliased fills, the horizontal coverage is exact.
+const SUBSCANLINES: usize = 16;
+
+// Edge with `y0 < y1`, `winding` is 1 if it pointed down and -1 if it pointed up.
+#[derive(Clone, Copy)]
+struct Edge {
+    x0: f32,
+    y0: f32,
+    x1: f32,
+    y1: f32,
+    winding: i32,
+}
+
+impl Edge {
+    #[inline]
+    fn x_at(&self, y: f32) -> f32 {
+        self.x0 + (self.x1 - self.x0) * ((y - self.y0) / (self.y1 - self.y0))
+    }
+}
+
+/// Edges of closed contours, filled with scanlines.
+pub(crate) struct Polygon {
+    edges: Vec<Edge>,
+}
+
+impl Polygon {
+    pub(crate) fn new() -> Self {
+        Self { edges: Vec::new() }
+    }
+
+    /// Adds a contour that closes back to its first point. Contours with points that aren't
+    /// finite are skipped.
+    pub(crate) fn contour(&mut self, points: &[Point]) {
+        if points.iter().any(|p| !p.0.is_finite() || !p.1.is_finite()) {
+            return;
+        }
+        let Some(&last) = points.last() else {
+            return;
+        };
+        let mut prev = last;
+        for &p in points {
+            self.edge(prev, p);
+            prev = p;
+        }
+    }
+
+    fn edge(&mut self, a: Point, b: Point) {
+        // horizontal edges cross no scanlines
+        let edge = if a.1 < b.1 {
+            Edge {
+                x0: a.0,
+                y0: a.1,
+                x1: b.0,
+                y1: b.1,
+                winding: 1,
+            }
+        } else if a.1 > b.1 {
+            Edge {
+                x0: b.0,
+                y0: b.1,
+                x1: a.0,
+                y1: a.1,
+                winding: -1,
+            }
+        } else {
+            return;
+        };
+        self.edges.push(edge);
+    }
+
+    /// Calls `span` with the inside spans `x0..x1` of every sample of the rows of a target
+    /// of `size` pixels, along with the row and the sample index.
+    ///
+    /// A sample is inside if its position in the pixel, see [`SampleCount::positions`], is
+    /// inside by the `rule`.
+    pub(crate) fn fill(
+        &mut self,
+        size: (usize, usize),
+        rule: FillRule,
+        samples: SampleCount,
+        mut span: impl FnMut(usize, usize, usize, usize),
+    ) {
+        let (width, height) = size;
+        let mut walker = Walker::new(&mut self.edges);
+        let Some((top, bottom)) = walker.rows(height) else {
+            return;
+        };
+        for y in top..bottom {
+            walker.row(y);
+            for (s, &(px, py)) in samples.positions().iter().enumerate() {
+                walker.scanline(y as f32 + 0.5 + py, rule, |xa, xb| {
+                    let clamp = |x: f32| libm::ceilf(x - 0.5 - px).clamp(0.0, width as f32);
+                    let (x0, x1) = (clamp(xa) as usize, clamp(xb) as usize);
+                    if x0 < x1 {
+                        span(x0, x1, y, s);
+                    }
+                });
+            }
+        }
+    }
+
+    /// Calls `plot` with the column, row and fraction of every pixel of a target of `size`
+    /// pixels that is covered by the inside of the polygon by the `rule`.
+    pub(crate) fn fill_aa(
+        &mut self,
+        size: (usize, usize),
+        rule: FillRule,
+        mut plot: impl FnMut(usize, usize, f32),
+    ) {
+        let (width, height) = size;
+        let mut walker = Walker::new(&mut self.edges);
+        let Some((top, bottom)) = walker.rows(height) else {
+            return;
+        };
+        let (minx, maxx) = walker.columns(width);
+
+        // partly covered ends of spans, and the changes of the count of fully covered
+        // sub-scanlines from one column to the next, from column `minx` on
+        let mut partial = vec![0.0f32; maxx - minx + 1];
+        let mut delta = vec![0i32; maxx - minx + 1];
+        let weight = 1.0 / SUBSCANLINES as f32;
+        for y in top..bottom {
+            walker.row(y);
+            let (mut left, mut right) = (maxx, minx);
+            for sub in 0..SUBSCANLINES {
+                let sy = y as f32 + (sub as f32 + 0.5) * weight;
+                walker.scanline(sy, rule, |xa, xb| {
+                    let (xa, xb) = (xa.max(minx as f32), xb.min(maxx as f32));
+                    if xa >= xb {
+                        return;
+                    }
+                    let (ia, ib) = (xa as usize, xb as usize);
+                    left = left.min(ia);
+                    right = right.max(ib + 1);
+                    let (ja, jb) = (ia - minx, ib - minx);
+                    if ia == ib {
+                        partial[ja] += (xb - xa) * weight;
+                        return;
+                    }
+                    partial[ja] += (ia as f32 + 1.0 - xa) * weight;
+                    partial[jb] += (xb - ib as f32) * weight;
+                    delta[ja + 1] += 1;
+                    delta[jb] -= 1;
+                });
+            }
+
+            let mut full = 0;
+            for x in left..right {
+                let j = x - minx;
+                full += delta[j];
+                let coverage = (full as f32 * weight + partial[j]).min(1.0);
+                if x < width && coverage > 0.0 {
+                    plot(x, y, coverage);
+                }
+                partial[j] = 0.0;
+                delta[j] = 0;
+            }
+        }
+    }
+}
+
+// Walks scanlines from top to bottom, keeping the edges that cross them.
+struct Walker<'a> {
+    edges: &'a [Edge],
+    next: usize,
+    active: Vec<Edge>,
+    crossings: Vec<(f32, i32)>,
+}
+
+impl<'a> Walker<'a> {
+    fn new(edges: &'a mut [Edge]) -> Self {
+        edges.sort_unstable_by(|a, b| a.y0.total_cmp(&b.y0));
+        Self {
+            edges,
+            next: 0,
+            active: Vec::new(),
+            crossings: Vec::new(),
+        }
+    }
+
+    // Rows of a target `height` pixels high that the edges can cross.
+    fn rows(&self, height: usize) -> Option<(usize, usize)> {
+        let top = self.edges.first()?.y0.max(0.0);
+        let bottom = self.edges.iter().fold(0.0f32, |y, e| y.max(e.y1));
+        let bottom = libm::ceilf(bottom).min(height as f32);
+        (top < bottom).then_some((top as usize, bottom as usize))
+    }
+
+    // Columns of a target `width` pixels wide that the edges can cross, `minx..=maxx`.
+    fn columns(&self, width: usize) -> (usize, usize) {
+        let (left, right) = self.edges.iter().fold((width as f32, 0.0f32), |(l, r), e| {
+            (l.min(e.x0).min(e.x1), r.max(e.x0).max(e.x1))
+        });
+        let maxx = libm::ceilf(right).min(width as f32) as usize;
+        ((left.max(0.0) as usize).min(maxx), maxx)
+    }
+
+    // Keeps the edges that cross row `y`, rows must be walked from top to bottom.
+    fn row(&mut self, y: usize) {
+        let (top, bottom) = (y as f32, y as f32 + 1.0);
+        while let Some(edge) = self.edges.get(self.next).filter(|e| e.y0 < bottom) {
+            self.active.push(*edge);
+            self.next += 1;
+        }
+        self.active.retain(|e| e.y1 > top);
+    }
+
+    // Calls `span` with the inside spans of the scanline at `y` in the current row.
+    fn scanline(&mut self, y: f32, rule: FillRule, mut span: impl FnMut(f32, f32)) {
+        self.crossings.clear();
+        self.crossings.extend(
+            self.active
+                .iter()
+                .filter(|e| e.y0 <= y && y < e.y1)
+                .map(|e| (e.x_at(y), e.winding)),
+        );
+        self.crossings.sort_unstable_by(|a, b| a.0.total_cmp(&b.0));
+
+        let mut winding = 0;
+        for pair in self.crossings.windows(2) {
+            winding += pair[0].1;
+            if rule.inside(winding) && pair[0].0 < pair[1].0 {
+                span(pair[0].0, pair[1].0);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SIZE: usize = 32;
+
+    fn polygon(contours: &[&[Point]]) -> Polygon {
+        let mut polygon = Polygon::new();
+        for contour in contours {
+            polygon.contour(contour);
+        }
+        polygon
+    }
+
+    // Square from `a` to `b`, clockwise on screen if `a` is its top left corner.
+    fn square(a: f32, b: f32) -> [Point; 4] {
+        [(a, a), (b, a), (b, b), (a, b)]
+    }
+
+    // Samples inside of the polygon, the target is `SIZE` pixels wide and high.
+    fn filled(polygon: &mut Polygon, rule: FillRule, samples: SampleCount) -> Vec<u32> {
+        let n = samples.count();
+        let mut filled = vec![0; SIZE * SIZE * n];
+        polygon.fill((SIZE, SIZE), rule, samples, |x0, x1, y, s| {
+            for x in x0..x1 {
+                filled[(y * SIZE + x) * n + s] += 1;
+            }
+        });
+        filled
+    }
+
+    fn fill_aa(polygon: &mut Polygon, rule: FillRule) -> Vec<f32> {
+        let mut coverage = vec![0.0; SIZE * SIZE];
+        polygon.fill_aa((SIZE, SIZE), rule, |x, y, c| {
+            assert_eq!(coverage[y * SIZE + x], 0.0, "{x}, {y} plotted twice");
+            coverage[y * SIZE + x] = c;
+        });
+        coverage
+    }
+
+    #[test]
+    fn nested_contours_follow_the_fill_rule() {
+        let (outer, inner) = (square(4.0, 20.0), square(8.0, 16.0));
+        let mut reversed = inner;
+        reversed.reverse();
+        for (inner, rule, expected) in [
+            (inner, FillRule::NonZero, 256),
+            (inner, FillRule::EvenOdd, 192),
+            (reversed, FillRule::NonZero, 192),
+            (reversed, FillRule::EvenOdd, 192),
+        ] {
+            let mut polygon = polygon(&[&outer, &inner]);
+            let filled = filled(&mut polygon, rule, SampleCount::X1);
+            assert!(filled.iter().all(|&f| f <= 1), "{rule:?}");
+            assert_eq!(filled.iter().sum::<u32>(), expected, "{rule:?}");
+            // the hole, if any, is at the center
+            assert_eq!(filled[12 * SIZE + 12] == 1, expected == 256, "{rule:?}");
+            assert_eq!(filled[5 * SIZE + 5], 1, "{rule:?}");
+        }
+    }
+
+    #[test]
+    fn self_intersecting_contours_follow_the_fill_rule() {
+        // a pentagram, the pentagon at its center winds twice
+        let star: [Point; 5] = core::array::from_fn(|i| {
+            let angle = core::f32::consts::TAU * (i * 2) as f32 / 5.0;
+            (
+                16.0 + 14.0 * libm::sinf(angle),
+                16.0 - 14.0 * libm::cosf(angle),
+            )
+        });
+        let nonzero = filled(&mut polygon(&[&star]), FillRule::NonZero, SampleCount::X1);
+        let evenodd = filled(&mut polygon(&[&star]), FillRule::EvenOdd, SampleCount::X1);
+        assert_eq!(nonzero[16 * SIZE + 16], 1);
+        assert_eq!(evenodd[16 * SIZE + 16], 0);
+        // the points are filled with either rule
+        assert_eq!(nonzero[5 * SIZE + 16], 1);
+        assert_eq!(evenodd[5 * SIZE + 16], 1);
+        assert!(evenodd.iter().zip(&nonzero).all(|(e, n)| e <= n));
+    }
+
+    #[test]
+    fn samples_are_inside_by_their_position() {
+        // edges through the middle of the pixels
+        let mut polygon = polygon(&[&square(4.5, 8.5)]);
+        let samples = SampleCount::X4;
+        let filled = filled(&mut polygon, FillRule::NonZero, samples);
+        for y in 0..SIZE {
+            for x in 0..SIZE {
+                for (s, &(px, py)) in samples.positions().iter().enumerate() {
+                    let (sx, sy) = (x as f32 + 0.5 + px, y as f32 + 0.5 + py);
+                    let inside = (4.5..8.5).contains(&sx) && (4.5..8.5).contains(&sy);
+                    assert_eq!(filled[(y * SIZE + x) * 4 + s] == 1, inside, "{x}, {y}, {s}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn anti_aliased_edges_cover_their_exact_area() {
+        // a rectangle with edges a quarter into the pixels
+        let rect = [(2.25, 3.0), (5.75, 3.0), (5.75, 5.0), (2.25, 5.0)];
+        let coverage = fill_aa(&mut polygon(&[&rect]), FillRule::NonZero);
+        for (x, expected) in [(1, 0.0), (2, 0.75), (3, 1.0), (4, 1.0), (5, 0.75), (6, 0.0)] {
+            for y in 0..SIZE {
+                let expected = if (3..5).contains(&y) { expected } else { 0.0 };
+                assert!((coverage[y * SIZE + x] - expected).abs() < 1e-5, "{x}, {y}");
+            }
+        }
+
+        // a diagonal edge halves the pixels it crosses
+        let triangle = [(4.0, 4.0), (20.0, 4.0), (4.0, 20.0)];
+        let coverage = fill_aa(&mut polygon(&[&triangle]), FillRule::NonZero);
+        for y in 4..20 {
+            let x = 23 - y;
+            assert!((coverage[y * SIZE + x] - 0.5).abs() < 1e-5, "{x}, {y}");
+            assert_eq!(coverage[y * SIZE + x - 1], (x > 4) as u8 as f32, "{x}, {y}");
+            assert_eq!(coverage[y * SIZE + x + 1], 0.0, "{x}, {y}");
+        }
+        let area: f32 = coverage.iter().sum();
+        assert!((area - 128.0).abs() < 1e-3, "{area}");
+
+        // holes are cut with the same precision
+        let (outer, inner) = (square(4.0, 20.0), square(8.5, 15.5));
+        let coverage = fill_aa(&mut polygon(&[&outer, &inner]), FillRule::EvenOdd);
+        assert!((coverage[8 * SIZE + 8] - 0.75).abs() < 1e-5);
+        assert!((coverage[8 * SIZE + 12] - 0.5).abs() < 1e-5);
+        assert_eq!(coverage[12 * SIZE + 12], 0.0);
+        let area: f32 = coverage.iter().sum();
+        assert!((area - 207.0).abs() < 1e-3, "{area}");
+    }
+
+    #[test]
+    fn polygons_outside_of_the_target_are_clipped() {
+        let mut polygon = polygon(&[&square(-100.0, 4.0), &[(0.0, 0.0), (f32::NAN, 1.0)]]);
+        let filled = filled(&mut polygon, FillRule::NonZero, SampleCount::X1);
+        assert_eq!(filled.iter().sum::<u32>(), 16);
+        let coverage = fill_aa(&mut polygon, FillRule::NonZero);
+        assert_eq!(coverage.iter().sum::<f32>(), 16.0);
+
+        let mut polygon = self::polygon(&[&square(40.0, 50.0)]);
+        assert_eq!(
+            fill_aa(&mut polygon, FillRule::NonZero).iter().sum::<f32>(),
+            0.0
+        );
+    }
+}
@@ -0,0 +1,793 @@
+use core::f32::consts::{PI, TAU};
+
+use crate::{LineJoin, Path, StrokeStyle, Transform};
+
+// Smallest radius in pixels of ellipses with analytic coverage, smaller ones curve too much
+// across a pixel and are flattened.
+const MIN_ANALYTIC_RADIUS: f32 = 2.0;
+
+/// 2D shape in screen space.
+///
+/// Angles are in radians from the positive `x` axis and turn clockwise on screen since `y`
+/// points down. Negative sizes and radii are taken as positive.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Shape {
+    /// Rectangle with its top left corner at `x`, `y`.
+    Rect {
+        x: f32,
+        y: f32,
+        width: f32,
+        height: f32,
+    },
+    /// Rectangle with corners rounded by up to half its shorter side.
+    RoundedRect {
+        x: f32,
+        y: f32,
+        width: f32,
+        height: f32,
+        radius: f32,
+    },
+    /// Circle around `x`, `y`.
+    Circle { x: f32, y: f32, radius: f32 },
+    /// Axis-aligned ellipse around `x`, `y` with the radii `rx` and `ry`.
+    Ellipse { x: f32, y: f32, rx: f32, ry: f32 },
+    /// Area between two circles around `x`, `y`.
+    Ring {
+        x: f32,
+        y: f32,
+        inner: f32,
+        outer: f32,
+    },
+    /// Arc of a circle from the angle `start` by `sweep`. Fills up to the chord between its
+    /// ends, outlines are open.
+    Arc {
+        x: f32,
+        y: f32,
+        radius: f32,
+        start: f32,
+        sweep: f32,
+    },
+    /// Sector of a circle from the angle `start` by `sweep`.
+    Pie {
+        x: f32,
+        y: f32,
+        radius: f32,
+        start: f32,
+        sweep: f32,
+    },
+}
+
+impl Shape {
+    /// Top left corner, width and height of the axis-aligned box around the shape.
+    ///
+    /// Arcs and pies are bounded by their whole circle.
+    pub fn bounds(&self) -> (f32, f32, f32, f32) {
+        match *self {
+            Self::Rect {
+                x,
+                y,
+                width,
+                height,
+            }
+            | Self::RoundedRect {
+                x,
+                y,
+                width,
+                height,
+                ..
+            } => (
+                x.min(x + width),
+                y.min(y + height),
+                width.abs(),
+                height.abs(),
+            ),
+            Self::Circle { x, y, radius }
+            | Self::Arc { x, y, radius, .. }
+            | Self::Pie { x, y, radius, .. } => {
+                let r = radius.abs();
+                (x - r, y - r, r * 2.0, r * 2.0)
+            }
+            Self::Ellipse { x, y, rx, ry } => {
+                let (rx, ry) = (rx.abs(), ry.abs());
+                (x - rx, y - ry, rx * 2.0, ry * 2.0)
+            }
+            Self::Ring { x, y, inner, outer } => {
+                let r = inner.abs().max(outer.abs());
+                (x - r, y - r, r * 2.0, r * 2.0)
+            }
+        }
+    }
+
+    /// Outline of the shape, the inner circle of rings winds the other way.
+    pub fn path(&self) -> Path {
+        let mut path = Path::new();
+        match *self {
+            Self::Rect { .. } => {
+                let (x, y, w, h) = self.bounds();
+                path.move_to(x, y)
+                    .line_to(x + w, y)
+                    .line_to(x + w, y + h)
+                    .line_to(x, y + h)
+                    .close();
+            }
+            Self::RoundedRect { radius, .. } => {
+                let (x, y, w, h) = self.bounds();
+                let r = radius.abs().min(w.min(h) * 0.5);
+                path.arc(x + w - r, y + r, r, r, -PI * 0.5, PI * 0.5)
+                    .arc(x + w - r, y + h - r, r, r, 0.0, PI * 0.5)
+                    .arc(x + r, y + h - r, r, r, PI * 0.5, PI * 0.5)
+                    .arc(x + r, y + r, r, r, PI, PI * 0.5)
+                    .close();
+            }
+            Self::Circle { x, y, radius } => {
+                let r = radius.abs();
+                path.arc(x, y, r, r, 0.0, TAU).close();
+            }
+            Self::Ellipse { x, y, rx, ry } => {
+                path.arc(x, y, rx.abs(), ry.abs(), 0.0, TAU).close();
+            }
+            Self::Ring { x, y, inner, outer } => {
+                let (a, b) = (inner.abs(), outer.abs());
+                let (inner, outer) = (a.min(b), a.max(b));
+                path.arc(x, y, outer, outer, 0.0, TAU).close();
+                path.move_to(x + inner, y)
+                    .arc(x, y, inner, inner, 0.0, -TAU)
+                    .close();
+            }
+            Self::Arc {
+                x,
+                y,
+                radius,
+                start,
+                sweep,
+            } => {
+                let r = radius.abs();
+                path.arc(x, y, r, r, start, sweep);
+            }
+            Self::Pie {
+                x,
+                y,
+                radius,
+                start,
+                sweep,
+            } => {
+                let r = radius.abs();
+                path.move_to(x, y).arc(x, y, r, r, start, sweep).close();
+            }
+        }
+        path
+    }
+}
+
+impl Shape {
+    /// The fill of the shape transformed by `transform`, if the coverage of its pixels can be
+    /// computed directly instead of from the flattened path.
+    pub(crate) fn analytic_fill(&self, transform: Transform) -> Option<Analytic> {
+        let analytic = match *self {
+            Self::Rect { .. } => Analytic {
+                outer: rect(self.bounds(), transform)?,
+                inner: None,
+            },
+            Self::Circle { x, y, radius } => Analytic {
+                outer: ellipse((x, y), (radius, radius), transform)?,
+                inner: None,
+            },
+            Self::Ellipse { x, y, rx, ry } => Analytic {
+                outer: ellipse((x, y), (rx, ry), transform)?,
+                inner: None,
+            },
+            Self::Ring { x, y, inner, outer } => {
+                let (a, b) = (inner.abs(), outer.abs());
+                let (inner, outer) = (a.min(b), a.max(b));
+                Analytic {
+                    outer: ellipse((x, y), (outer, outer), transform)?,
+                    inner: if inner > 0.0 {
+                        Some(ellipse((x, y), (inner, inner), transform)?)
+                    } else {
+                        None
+                    },
+                }
+            }
+            _ => return None,
+        };
+        Some(analytic)
+    }
+
+    /// The outline of the shape transformed by `transform` and stroked with the `style`, if
+    /// the coverage of its pixels can be computed directly.
+    pub(crate) fn analytic_outline(
+        &self,
+        transform: Transform,
+        style: &StrokeStyle,
+    ) -> Option<Analytic> {
+        let hw = style.width * 0.5;
+        if !(style.dashes.is_empty() && hw > 0.0 && hw.is_finite()) {
+            return None;
+        }
+        let (outer, inner) = match *self {
+            // miters of right angles are `√2` half widths long
+            Self::Rect { .. }
+                if style.join == LineJoin::Miter && libm::sqrtf(0.5) * style.miter_limit >= 1.0 =>
+            {
+                let Area::Rect { x0, y0, x1, y1 } = rect(self.bounds(), transform)? else {
+                    return None;
+                };
+                if x0 == x1 || y0 == y1 {
+                    return None;
+                }
+                let (ix0, iy0, ix1, iy1) = (x0 + hw, y0 + hw, x1 - hw, y1 - hw);
+                let outer = Area::Rect {
+                    x0: x0 - hw,
+                    y0: y0 - hw,
+                    x1: x1 + hw,
+                    y1: y1 + hw,
+                };
+                let inner = (ix0 < ix1 && iy0 < iy1).then_some(Area::Rect {
+                    x0: ix0,
+                    y0: iy0,
+                    x1: ix1,
+                    y1: iy1,
+                });
+                (outer, inner)
+            }
+            Self::Circle { x, y, radius } => {
+                let Area::Ellipse { x, y, rx, ry } = ellipse((x, y), (radius, radius), transform)?
+                else {
+                    return None;
+                };
+                if rx != ry {
+                    return None;
+                }
+                let inner = rx - hw;
+                if inner > 0.0 && inner < MIN_ANALYTIC_RADIUS {
+                    return None;
+                }
+                let circle = |r: f32| Area::Ellipse { x, y, rx: r, ry: r };
+                (circle(rx + hw), (inner > 0.0).then(|| circle(inner)))
+            }
+            _ => return None,
+        };
+        Some(Analytic { outer, inner })
+    }
+}
+
+/// Area of a shape in screen space whose coverage of a pixel is computed directly.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Area {
+    /// Axis-aligned rectangle from `x0`, `y0` to `x1`, `y1`.
+    Rect { x0: f32, y0: f32, x1: f32, y1: f32 },
+    /// Axis-aligned ellipse around `x`, `y`.
+    Ellipse { x: f32, y: f32, rx: f32, ry: f32 },
+}
+
+impl Area {
+    // Columns and rows of pixels the area can cover in a target of `size` pixels.
+    fn pixels(&self, (width, height): (usize, usize)) -> (usize, usize, usize, usize) {
+        let (x0, y0, x1, y1) = match *self {
+            Self::Rect { x0, y0, x1, y1 } => (x0, y0, x1, y1),
+            Self::Ellipse { x, y, rx, ry } => (x - rx, y - ry, x + rx, y + ry),
+        };
+        (
+            x0.max(0.0) as usize,
+            y0.max(0.0) as usize,
+            libm::ceilf(x1.min(width as f32)).max(0.0) as usize,
+            libm::ceilf(y1.min(height as f32)).max(0.0) as usize,
+        )
+    }
+
+    // Fraction of the pixel at column `x` and row `y` inside the area.
+    #[inline]
+    fn coverage(&self, x: usize, y: usize) -> f32 {
+        let (x, y) = (x as f32, y as f32);
+        match *self {
+            Self::Rect { x0, y0, x1, y1 } => {
+                let cover = |p: f32, lo: f32, hi: f32| (hi.min(p + 1.0) - lo.max(p)).max(0.0);
+                cover(x, x0, x1) * cover(y, y0, y1)
+            }
+            Self::Ellipse {
+                x: cx,
+                y: cy,
+                rx,
+                ry,
+            } => {
+                // the distance to the ellipse is close to `(1 - |q|) / |∇|q||` near it, and the
+                // ellipse is close to straight across a pixel since radii are at least
+                // `MIN_ANALYTIC_RADIUS`
+                let (qx, qy) = ((x + 0.5 - cx) / rx, (y + 0.5 - cy) / ry);
+                let q = libm::hypotf(qx, qy);
+                if q == 0.0 {
+                    return 1.0;
+                }
+                let (gx, gy) = (qx / (rx * q), qy / (ry * q));
+                let g = libm::hypotf(gx, gy);
+                half_plane((1.0 - q) / g, (gx / g, gy / g))
+            }
+        }
+    }
+}
+
+// Fraction of a pixel inside a straight edge with the unit `normal` at the signed `distance`
+// from its center, positive if the center is inside.
+fn half_plane(distance: f32, (nx, ny): (f32, f32)) -> f32 {
+    // the pixel projected onto the normal is a trapezoid of area 1 that is `a + b` wide at
+    // its base and `a - b` wide at its top
+    let (a, b) = (nx.abs().max(ny.abs()), nx.abs().min(ny.abs()));
+    let u = distance + (a + b) * 0.5;
+    if u <= 0.0 {
+        0.0
+    } else if u >= a + b {
+        1.0
+    } else if u < b {
+        u * u / (2.0 * a * b)
+    } else if u <= a {
+        (u - b * 0.5) / a
+    } else {
+        let v = a + b - u;
+        1.0 - v * v / (2.0 * a * b)
+    }
+}
+
+/// Fill or outline of a shape whose coverage of every pixel is computed directly, the part
+/// of the `outer` area outside of the `inner` one.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Analytic {
+    outer: Area,
+    inner: Option<Area>,
+}
+
+impl Analytic {
+    /// Calls `plot` with the column, row and covered fraction of every pixel of a target of
+    /// `size` pixels that the shape covers, like `Polygon::fill_aa`.
+    pub(crate) fn fill_aa(&self, size: (usize, usize), mut plot: impl FnMut(usize, usize, f32)) {
+        let (minx, miny, maxx, maxy) = self.outer.pixels(size);
+        for y in miny..maxy {
+            for x in minx..maxx {
+                let inner = self.inner.map_or(0.0, |inner| inner.coverage(x, y));
+                let coverage = (self.outer.coverage(x, y) - inner).min(1.0);
+                if coverage > 0.0 {
+                    plot(x, y, coverage);
+                }
+            }
+        }
+    }
+}
+
+// An axis-aligned rectangle `x`, `y`, `width`, `height` after the transform, if it stays one.
+fn rect((x, y, width, height): (f32, f32, f32, f32), transform: Transform) -> Option<Area> {
+    if transform.b != 0.0 || transform.c != 0.0 {
+        return None;
+    }
+    let (x0, y0) = transform.apply((x, y));
+    let (x1, y1) = transform.apply((x + width, y + height));
+    let area = Area::Rect {
+        x0: x0.min(x1),
+        y0: y0.min(y1),
+        x1: x0.max(x1),
+        y1: y0.max(y1),
+    };
+    [x0, y0, x1, y1]
+        .iter()
+        .all(|v| v.is_finite())
+        .then_some(area)
+}
+
+// The ellipse around `center` with the `radii` after the transform, if it stays axis-aligned
+// and has radii of at least `MIN_ANALYTIC_RADIUS`.
+fn ellipse(center: (f32, f32), (rx, ry): (f32, f32), transform: Transform) -> Option<Area> {
+    let Transform { a, b, c, d, .. } = transform;
+    let (rx, ry) = (rx.abs(), ry.abs());
+    let (rx, ry) = if b == 0.0 && c == 0.0 {
+        (rx * a.abs(), ry * d.abs())
+    } else {
+        // rotations and uniform scales keep circles circles
+        let (x, y) = (a * a + b * b, c * c + d * d);
+        let similar = (a * c + b * d).abs() <= x * 1e-6 && (x - y).abs() <= x * 1e-6;
+        if rx != ry || !similar {
+            return None;
+        }
+        (rx * libm::sqrtf(x), ry * libm::sqrtf(x))
+    };
+    let (x, y) = transform.apply(center);
+    let finite = [x, y, rx, ry].iter().all(|v| v.is_finite());
+    (finite && rx.min(ry) >= MIN_ANALYTIC_RADIUS).then_some(Area::Ellipse { x, y, rx, ry })
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec;
+    use alloc::vec::Vec;
+
+    use tint::LinearRgb;
+
+    use super::*;
+    use crate::{
+        ColorShader, FillRule, LineCap, OwnedFramebuffer, polygon::Polygon, rast_shape,
+        rast_shape_outline, rast_shape_shaded, stroke_polygon,
+    };
+
+    const SIZE: (usize, usize) = (48, 40);
+
+    fn analytic(analytic: Analytic) -> Vec<f32> {
+        let mut coverage = vec![0.0; SIZE.0 * SIZE.1];
+        analytic.fill_aa(SIZE, |x, y, c| coverage[y * SIZE.0 + x] += c);
+        coverage
+    }
+
+    fn flattened(mut polygon: Polygon) -> Vec<f32> {
+        let mut coverage = vec![0.0; SIZE.0 * SIZE.1];
+        polygon.fill_aa(SIZE, FillRule::NonZero, |x, y, c| {
+            coverage[y * SIZE.0 + x] += c
+        });
+        coverage
+    }
+
+    fn fill(shape: Shape, transform: Transform) -> Vec<f32> {
+        let mut path = shape.path();
+        path.transform(transform);
+        let mut polygon = Polygon::new();
+        path.flatten(|points, _| polygon.contour(points));
+        flattened(polygon)
+    }
+
+    // Fraction of the samples of a 32 by 32 grid in every pixel that are `inside`.
+    fn supersampled(inside: impl Fn(f32, f32) -> bool) -> Vec<f32> {
+        let mut coverage = vec![0.0; SIZE.0 * SIZE.1];
+        for (i, coverage) in coverage.iter_mut().enumerate() {
+            let (x, y) = ((i % SIZE.0) as f32, (i / SIZE.0) as f32);
+            let samples = (0..32 * 32)
+                .filter(|s| {
+                    let (sx, sy) = ((s % 32) as f32 + 0.5, (s / 32) as f32 + 0.5);
+                    inside(x + sx / 32.0, y + sy / 32.0)
+                })
+                .count();
+            *coverage = samples as f32 / (32.0 * 32.0);
+        }
+        coverage
+    }
+
+    // Largest difference from fills of flattened paths, which lie within the tolerance of 0.1
+    // pixels of curves and sample 16 sub-scanlines per row.
+    const FLATTENED: f32 = 0.1 + 1.0 / 16.0;
+
+    fn inside_ellipse((cx, cy): (f32, f32), (rx, ry): (f32, f32)) -> impl Fn(f32, f32) -> bool {
+        move |x, y| {
+            let (qx, qy) = ((x - cx) / rx, (y - cy) / ry);
+            qx * qx + qy * qy <= 1.0
+        }
+    }
+
+    fn max_difference(a: &[f32], b: &[f32]) -> f32 {
+        a.iter()
+            .zip(b)
+            .map(|(a, b)| (a - b).abs())
+            .fold(0.0, f32::max)
+    }
+
+    #[test]
+    fn shapes_are_drawn_with_their_coverage() {
+        let (black, white) = (LinearRgb::rgb(0.0, 0.0, 0.0), LinearRgb::rgb(1.0, 1.0, 1.0));
+        let rect = Shape::Rect {
+            x: 1.25,
+            y: 2.5,
+            width: 3.5,
+            height: 1.0,
+        };
+        let mut target = OwnedFramebuffer::new(8, 8, black);
+        rast_shape(&mut target, rect, Transform::IDENTITY, white);
+        let mut shaded = OwnedFramebuffer::new(8, 8, black);
+        let data = [white; 3];
+        rast_shape_shaded(&mut shaded, rect, Transform::IDENTITY, data, ColorShader);
+
+        for target in [target.color(), shaded.color()] {
+            assert_eq!(target[2 * 8 + 1], LinearRgb::rgb(0.375, 0.375, 0.375));
+            assert_eq!(target[2 * 8 + 2], LinearRgb::rgb(0.5, 0.5, 0.5));
+            assert_eq!(target[3 * 8 + 2], LinearRgb::rgb(0.5, 0.5, 0.5));
+            assert_eq!(target[8 + 2], black);
+        }
+
+        let mut target = OwnedFramebuffer::new(8, 8, black);
+        let outline = StrokeStyle::new(1.0);
+        rast_shape_outline(&mut target, rect, Transform::IDENTITY, outline, white);
+        // the outline is as high as the rect, which leaves no inside
+        let sum = target.color().iter().map(|c| c.r).sum::<f32>();
+        assert_eq!(sum, 4.5 * 2.0);
+    }
+
+    #[test]
+    fn half_planes_cover_their_exact_area() {
+        for d in [-1.0, -0.5, -0.25, 0.0, 0.1, 0.5, 2.0] {
+            let expected = (d + 0.5f32).clamp(0.0, 1.0);
+            assert_eq!(half_plane(d, (1.0, 0.0)), expected);
+            assert_eq!(half_plane(d, (0.0, -1.0)), expected);
+        }
+
+        // a diagonal edge through a corner covers half, and a quarter of the diagonal in
+        // from it covers an eighth
+        let n = (libm::sqrtf(0.5), libm::sqrtf(0.5));
+        let diagonal = libm::sqrtf(2.0);
+        assert_eq!(half_plane(0.0, n), 0.5);
+        assert!((half_plane(-diagonal * 0.25, n) - 0.125).abs() < 1e-6);
+        assert_eq!(half_plane(-diagonal * 0.5, n), 0.0);
+        assert_eq!(half_plane(diagonal * 0.5, n), 1.0);
+
+        // the inside and outside of any edge add up to the whole pixel
+        for angle in 0..16 {
+            let n = libm::sincosf(angle as f32 * 0.1);
+            for d in 0..20 {
+                let d = d as f32 * 0.05;
+                assert!((half_plane(d, n) + half_plane(-d, n) - 1.0).abs() < 1e-6);
+            }
+        }
+    }
+
+    #[test]
+    fn rects_cover_their_exact_area() {
+        let shape = Shape::Rect {
+            x: 1.25,
+            y: 2.5,
+            width: 3.5,
+            height: 1.0,
+        };
+        let coverage = analytic(shape.analytic_fill(Transform::IDENTITY).unwrap());
+        let at = |x: usize, y: usize| coverage[y * SIZE.0 + x];
+        assert_eq!(at(1, 2), 0.75 * 0.5);
+        assert_eq!(at(2, 3), 0.5);
+        assert_eq!(at(4, 2), 0.75 * 0.5);
+        assert_eq!(at(5, 2), 0.0);
+        assert_eq!(coverage.iter().sum::<f32>(), 3.5);
+
+        // flipped and scaled
+        let transform = Transform::scale(-2.0, 3.0).then(Transform::translate(20.0, 0.0));
+        let coverage = analytic(shape.analytic_fill(transform).unwrap());
+        assert_eq!(coverage.iter().sum::<f32>(), 3.5 * 6.0);
+        let difference = max_difference(&coverage, &fill(shape, transform));
+        assert!(difference <= 1.0 / 16.0, "{difference}");
+
+        // partly outside of the target
+        let shape = Shape::Rect {
+            x: -10.0,
+            y: 30.5,
+            width: 20.0,
+            height: 20.0,
+        };
+        let coverage = analytic(shape.analytic_fill(Transform::IDENTITY).unwrap());
+        assert_eq!(coverage.iter().sum::<f32>(), 10.0 * 9.5);
+    }
+
+    #[test]
+    fn ellipses_match_their_area() {
+        let shapes = [
+            (Shape::Circle {
+                x: 20.3,
+                y: 18.6,
+                radius: 12.0,
+            }),
+            (Shape::Ellipse {
+                x: 24.0,
+                y: 20.5,
+                rx: 20.0,
+                ry: 6.5,
+            }),
+            (Shape::Circle {
+                x: 10.5,
+                y: 10.5,
+                radius: 3.0,
+            }),
+        ];
+        for shape in shapes {
+            let (x, y, width, height) = shape.bounds();
+            let (cx, cy, rx, ry) = (x + width * 0.5, y + height * 0.5, width * 0.5, height * 0.5);
+            let coverage = analytic(shape.analytic_fill(Transform::IDENTITY).unwrap());
+
+            let area = core::f32::consts::PI * rx * ry;
+            let sum = coverage.iter().sum::<f32>();
+            assert!((sum - area).abs() < area * 0.01, "{shape:?} {sum} {area}");
+
+            let expected = supersampled(inside_ellipse((cx, cy), (rx, ry)));
+            let difference = max_difference(&coverage, &expected);
+            assert!(difference < 0.05, "{shape:?} {difference}");
+            let difference = max_difference(&coverage, &fill(shape, Transform::IDENTITY));
+            assert!(difference < FLATTENED, "{shape:?} {difference}");
+        }
+    }
+
+    #[test]
+    fn transformed_circles_stay_analytic() {
+        let circle = Shape::Circle {
+            x: 4.0,
+            y: 3.0,
+            radius: 5.0,
+        };
+        let transform = Transform::rotate(0.7)
+            .then(Transform::scale(2.0, 2.0))
+            .then(Transform::translate(20.0, 10.0));
+        let analytic = circle.analytic_fill(transform).unwrap();
+        let (x, y) = transform.apply((4.0, 3.0));
+        assert_eq!(
+            analytic.outer,
+            Area::Ellipse {
+                x,
+                y,
+                rx: 10.0,
+                ry: 10.0
+            }
+        );
+        let coverage = self::analytic(analytic);
+        let expected = supersampled(inside_ellipse((x, y), (10.0, 10.0)));
+        let difference = max_difference(&coverage, &expected);
+        assert!(difference < 0.05, "{difference}");
+        let difference = max_difference(&coverage, &fill(circle, transform));
+        assert!(difference < FLATTENED, "{difference}");
+
+        let ellipse = circle.analytic_fill(Transform::scale(2.0, 3.0)).unwrap();
+        assert!(matches!(
+            ellipse.outer,
+            Area::Ellipse {
+                rx: 10.0,
+                ry: 15.0,
+                ..
+            }
+        ));
+
+        // skewed and rotated ellipses, rotated rects and tiny circles are flattened
+        let skew = Transform::new(1.0, 0.5, 0.0, 1.0, 0.0, 0.0);
+        assert_eq!(circle.analytic_fill(skew), None);
+        let ellipse = Shape::Ellipse {
+            x: 4.0,
+            y: 3.0,
+            rx: 5.0,
+            ry: 4.0,
+        };
+        assert_eq!(ellipse.analytic_fill(Transform::rotate(0.7)), None);
+        let rect = Shape::Rect {
+            x: 0.0,
+            y: 0.0,
+            width: 4.0,
+            height: 4.0,
+        };
+        assert_eq!(rect.analytic_fill(Transform::rotate(0.7)), None);
+        let tiny = Shape::Circle {
+            x: 4.0,
+            y: 3.0,
+            radius: 0.5,
+        };
+        assert_eq!(tiny.analytic_fill(Transform::IDENTITY), None);
+        assert_eq!(tiny.analytic_fill(Transform::scale(2.0, 2.0)), None);
+        assert!(tiny.analytic_fill(Transform::scale(4.0, 4.0)).is_some());
+        let rounded = Shape::RoundedRect {
+            x: 0.0,
+            y: 0.0,
+            width: 4.0,
+            height: 4.0,
+            radius: 1.0,
+        };
+        assert_eq!(rounded.analytic_fill(Transform::IDENTITY), None);
+    }
+
+    #[test]
+    fn rings_cut_out_their_inner_circle() {
+        let ring = Shape::Ring {
+            x: 20.0,
+            y: 19.5,
+            inner: 15.0,
+            outer: 8.0,
+        };
+        let coverage = analytic(ring.analytic_fill(Transform::IDENTITY).unwrap());
+        let area = core::f32::consts::PI * (15.0 * 15.0 - 8.0 * 8.0);
+        let sum = coverage.iter().sum::<f32>();
+        assert!((sum - area).abs() < area * 0.005, "{sum} {area}");
+        assert_eq!(coverage[19 * SIZE.0 + 20], 0.0);
+        assert_eq!(coverage[19 * SIZE.0 + 31], 1.0);
+        let outer = inside_ellipse((20.0, 19.5), (15.0, 15.0));
+        let inner = inside_ellipse((20.0, 19.5), (8.0, 8.0));
+        let expected = supersampled(|x, y| outer(x, y) && !inner(x, y));
+        let difference = max_difference(&coverage, &expected);
+        assert!(difference < 0.05, "{difference}");
+        let difference = max_difference(&coverage, &fill(ring, Transform::IDENTITY));
+        assert!(difference < FLATTENED, "{difference}");
+    }
+
+    #[test]
+    fn outlines_match_their_strokes() {
+        let shapes = [
+            Shape::Rect {
+                x: 5.5,
+                y: 4.25,
+                width: 30.0,
+                height: 20.0,
+            },
+            Shape::Rect {
+                x: 5.0,
+                y: 4.0,
+                width: 3.0,
+                height: 20.0,
+            },
+            Shape::Circle {
+                x: 22.0,
+                y: 18.0,
+                radius: 12.5,
+            },
+            Shape::Circle {
+                x: 22.0,
+                y: 18.0,
+                radius: 2.5,
+            },
+        ];
+        for shape in shapes {
+            for width in [1.0, 2.5, 7.0] {
+                let style = StrokeStyle::new(width);
+                let Some(outline) = shape.analytic_outline(Transform::IDENTITY, &style) else {
+                    // small inner circles are flattened
+                    assert!(matches!(shape, Shape::Circle { radius: 2.5, .. }) && width == 2.5);
+                    continue;
+                };
+                let coverage = analytic(outline);
+                let expected = flattened(stroke_polygon(&shape.path(), style, SIZE));
+                let difference = max_difference(&coverage, &expected);
+                assert!(difference < FLATTENED, "{shape:?} {width} {difference}");
+                if let Shape::Circle { x, y, radius } = shape {
+                    let outer =
+                        inside_ellipse((x, y), (radius + width * 0.5, radius + width * 0.5));
+                    let r = (radius - width * 0.5).max(0.0);
+                    let inner = inside_ellipse((x, y), (r, r));
+                    let expected = supersampled(|x, y| outer(x, y) && !inner(x, y));
+                    let difference = max_difference(&coverage, &expected);
+                    assert!(difference < 0.05, "{shape:?} {width} {difference}");
+                }
+            }
+        }
+
+        let rect = shapes[0];
+        let outline = rect.analytic_outline(Transform::IDENTITY, &StrokeStyle::new(2.0));
+        let coverage = analytic(outline.unwrap());
+        assert_eq!(coverage.iter().sum::<f32>(), 32.0 * 22.0 - 28.0 * 18.0);
+    }
+
+    #[test]
+    fn outlines_with_other_corners_are_flattened() {
+        let rect = Shape::Rect {
+            x: 5.0,
+            y: 4.0,
+            width: 30.0,
+            height: 20.0,
+        };
+        let circle = Shape::Circle {
+            x: 22.0,
+            y: 18.0,
+            radius: 12.5,
+        };
+        let style = StrokeStyle::new(2.0);
+        let dashed = StrokeStyle {
+            dashes: &[4.0, 2.0],
+            ..style
+        };
+        let bevel = StrokeStyle {
+            join: LineJoin::Bevel,
+            ..style
+        };
+        let short = StrokeStyle {
+            miter_limit: 1.4,
+            ..style
+        };
+        let capped = StrokeStyle {
+            cap: LineCap::Round,
+            miter_limit: 1.5,
+            ..style
+        };
+        let identity = Transform::IDENTITY;
+        assert!(rect.analytic_outline(identity, &capped).is_some());
+        assert!(circle.analytic_outline(identity, &bevel).is_some());
+        assert_eq!(rect.analytic_outline(identity, &dashed), None);
+        assert_eq!(circle.analytic_outline(identity, &dashed), None);
+        assert_eq!(rect.analytic_outline(identity, &bevel), None);
+        assert_eq!(rect.analytic_outline(identity, &short), None);
+        assert_eq!(
+            rect.analytic_outline(identity, &StrokeStyle::new(0.0)),
+            None
+        );
+        assert_eq!(
+            circle.analytic_outline(Transform::scale(1.0, 2.0), &style),
+            None
+        );
+    }
+}
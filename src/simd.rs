@@ -0,0 +1,413 @@
+//! Eight lanes of `f32` for fragment processing.
+//!
+//! Uses AVX2 when the target enables it, SSE2 on any other `x86_64` target and plain arrays
+//! everywhere else. Every operation is a single IEEE operation per lane, so results are
+//! identical to the scalar code they replace.
+//!
+//! The instruction set is chosen at compile time, AVX2 is not detected at runtime. Default
+//! builds use SSE2, builds with `-C target-feature=+avx2` or a `-C target-cpu` that has it
+//! use AVX2 and only run on CPUs that support it.
+
+use core::ops::{Add, Div, Mul, Neg, Sub};
+
+pub const LANES: usize = 8;
+
+#[cfg(all(target_arch = "x86_64", target_feature = "avx2"))]
+mod imp {
+    use core::arch::x86_64::*;
+
+    pub type Inner = __m256;
+
+    #[inline(always)]
+    pub fn splat(v: f32) -> Inner {
+        // SAFETY: AVX2 is enabled at compile time.
+        unsafe { _mm256_set1_ps(v) }
+    }
+
+    #[inline(always)]
+    pub fn from_array(a: [f32; 8]) -> Inner {
+        // SAFETY: `a` is 8 `f32`s long and `loadu` has no alignment requirement.
+        unsafe { _mm256_loadu_ps(a.as_ptr()) }
+    }
+
+    #[inline(always)]
+    pub fn to_array(v: Inner) -> [f32; 8] {
+        let mut a = [0.0; 8];
+        // SAFETY: `a` is 8 `f32`s long and `storeu` has no alignment requirement.
+        unsafe { _mm256_storeu_ps(a.as_mut_ptr(), v) };
+        a
+    }
+
+    #[inline(always)]
+    pub fn add(a: Inner, b: Inner) -> Inner {
+        // SAFETY: AVX2 is enabled at compile time.
+        unsafe { _mm256_add_ps(a, b) }
+    }
+
+    #[inline(always)]
+    pub fn sub(a: Inner, b: Inner) -> Inner {
+        // SAFETY: AVX2 is enabled at compile time.
+        unsafe { _mm256_sub_ps(a, b) }
+    }
+
+    #[inline(always)]
+    pub fn mul(a: Inner, b: Inner) -> Inner {
+        // SAFETY: AVX2 is enabled at compile time.
+        unsafe { _mm256_mul_ps(a, b) }
+    }
+
+    #[inline(always)]
+    pub fn div(a: Inner, b: Inner) -> Inner {
+        // SAFETY: AVX2 is enabled at compile time.
+        unsafe { _mm256_div_ps(a, b) }
+    }
+
+    #[inline(always)]
+    pub fn neg(a: Inner) -> Inner {
+        // SAFETY: AVX2 is enabled at compile time.
+        unsafe { _mm256_xor_ps(a, _mm256_set1_ps(-0.0)) }
+    }
+
+    #[inline(always)]
+    pub fn gt(a: Inner, b: Inner) -> u32 {
+        // SAFETY: AVX2 is enabled at compile time.
+        unsafe { _mm256_movemask_ps(_mm256_cmp_ps::<_CMP_GT_OQ>(a, b)) as u32 }
+    }
+
+    #[inline(always)]
+    pub fn eq(a: Inner, b: Inner) -> u32 {
+        // SAFETY: AVX2 is enabled at compile time.
+        unsafe { _mm256_movemask_ps(_mm256_cmp_ps::<_CMP_EQ_OQ>(a, b)) as u32 }
+    }
+
+    #[inline(always)]
+    pub fn le(a: Inner, b: Inner) -> u32 {
+        // SAFETY: AVX2 is enabled at compile time.
+        unsafe { _mm256_movemask_ps(_mm256_cmp_ps::<_CMP_LE_OQ>(a, b)) as u32 }
+    }
+}
+
+#[cfg(all(target_arch = "x86_64", not(target_feature = "avx2")))]
+mod imp {
+    use core::arch::x86_64::*;
+
+    pub type Inner = [__m128; 2];
+
+    #[inline(always)]
+    fn map(a: Inner, b: Inner, f: impl Fn(__m128, __m128) -> __m128) -> Inner {
+        [f(a[0], b[0]), f(a[1], b[1])]
+    }
+
+    #[inline(always)]
+    fn mask(a: Inner, b: Inner, f: impl Fn(__m128, __m128) -> __m128) -> u32 {
+        // SAFETY: SSE2 is part of the x86_64 baseline.
+        let (lo, hi) = unsafe {
+            (
+                _mm_movemask_ps(f(a[0], b[0])) as u32,
+                _mm_movemask_ps(f(a[1], b[1])) as u32,
+            )
+        };
+        lo | (hi << 4)
+    }
+
+    #[inline(always)]
+    pub fn splat(v: f32) -> Inner {
+        // SAFETY: SSE2 is part of the x86_64 baseline.
+        [unsafe { _mm_set1_ps(v) }; 2]
+    }
+
+    #[inline(always)]
+    pub fn from_array(a: [f32; 8]) -> Inner {
+        // SAFETY: `a` is 8 `f32`s long and `loadu` has no alignment requirement.
+        unsafe { [_mm_loadu_ps(a.as_ptr()), _mm_loadu_ps(a.as_ptr().add(4))] }
+    }
+
+    #[inline(always)]
+    pub fn to_array(v: Inner) -> [f32; 8] {
+        let mut a = [0.0; 8];
+        // SAFETY: `a` is 8 `f32`s long and `storeu` has no alignment requirement.
+        unsafe {
+            _mm_storeu_ps(a.as_mut_ptr(), v[0]);
+            _mm_storeu_ps(a.as_mut_ptr().add(4), v[1]);
+        }
+        a
+    }
+
+    #[inline(always)]
+    pub fn add(a: Inner, b: Inner) -> Inner {
+        // SAFETY: SSE2 is part of the x86_64 baseline.
+        map(a, b, |a, b| unsafe { _mm_add_ps(a, b) })
+    }
+
+    #[inline(always)]
+    pub fn sub(a: Inner, b: Inner) -> Inner {
+        // SAFETY: SSE2 is part of the x86_64 baseline.
+        map(a, b, |a, b| unsafe { _mm_sub_ps(a, b) })
+    }
+
+    #[inline(always)]
+    pub fn mul(a: Inner, b: Inner) -> Inner {
+        // SAFETY: SSE2 is part of the x86_64 baseline.
+        map(a, b, |a, b| unsafe { _mm_mul_ps(a, b) })
+    }
+
+    #[inline(always)]
+    pub fn div(a: Inner, b: Inner) -> Inner {
+        // SAFETY: SSE2 is part of the x86_64 baseline.
+        map(a, b, |a, b| unsafe { _mm_div_ps(a, b) })
+    }
+
+    #[inline(always)]
+    pub fn neg(a: Inner) -> Inner {
+        // SAFETY: SSE2 is part of the x86_64 baseline.
+        map(a, splat(-0.0), |a, b| unsafe { _mm_xor_ps(a, b) })
+    }
+
+    #[inline(always)]
+    pub fn gt(a: Inner, b: Inner) -> u32 {
+        // SAFETY: SSE2 is part of the x86_64 baseline.
+        mask(a, b, |a, b| unsafe { _mm_cmpgt_ps(a, b) })
+    }
+
+    #[inline(always)]
+    pub fn eq(a: Inner, b: Inner) -> u32 {
+        // SAFETY: SSE2 is part of the x86_64 baseline.
+        mask(a, b, |a, b| unsafe { _mm_cmpeq_ps(a, b) })
+    }
+
+    #[inline(always)]
+    pub fn le(a: Inner, b: Inner) -> u32 {
+        // SAFETY: SSE2 is part of the x86_64 baseline.
+        mask(a, b, |a, b| unsafe { _mm_cmple_ps(a, b) })
+    }
+}
+
+// Also built for tests, which compare the other implementations against it.
+#[cfg(any(test, not(target_arch = "x86_64")))]
+mod scalar {
+    pub type Inner = [f32; 8];
+
+    #[inline(always)]
+    fn map(a: Inner, b: Inner, f: impl Fn(f32, f32) -> f32) -> Inner {
+        core::array::from_fn(|i| f(a[i], b[i]))
+    }
+
+    #[inline(always)]
+    fn mask(a: Inner, b: Inner, f: impl Fn(f32, f32) -> bool) -> u32 {
+        (0..8).fold(0, |m, i| m | ((f(a[i], b[i]) as u32) << i))
+    }
+
+    #[inline(always)]
+    pub fn splat(v: f32) -> Inner {
+        [v; 8]
+    }
+
+    #[inline(always)]
+    pub fn from_array(a: [f32; 8]) -> Inner {
+        a
+    }
+
+    #[inline(always)]
+    pub fn to_array(v: Inner) -> [f32; 8] {
+        v
+    }
+
+    #[inline(always)]
+    pub fn add(a: Inner, b: Inner) -> Inner {
+        map(a, b, |a, b| a + b)
+    }
+
+    #[inline(always)]
+    pub fn sub(a: Inner, b: Inner) -> Inner {
+        map(a, b, |a, b| a - b)
+    }
+
+    #[inline(always)]
+    pub fn mul(a: Inner, b: Inner) -> Inner {
+        map(a, b, |a, b| a * b)
+    }
+
+    #[inline(always)]
+    pub fn div(a: Inner, b: Inner) -> Inner {
+        map(a, b, |a, b| a / b)
+    }
+
+    #[inline(always)]
+    pub fn neg(a: Inner) -> Inner {
+        a.map(|a| -a)
+    }
+
+    #[inline(always)]
+    pub fn gt(a: Inner, b: Inner) -> u32 {
+        mask(a, b, |a, b| a > b)
+    }
+
+    #[inline(always)]
+    pub fn eq(a: Inner, b: Inner) -> u32 {
+        mask(a, b, |a, b| a == b)
+    }
+
+    #[inline(always)]
+    pub fn le(a: Inner, b: Inner) -> u32 {
+        mask(a, b, |a, b| a <= b)
+    }
+}
+
+#[cfg(not(target_arch = "x86_64"))]
+use scalar as imp;
+
+#[derive(Clone, Copy)]
+pub struct F32x8(imp::Inner);
+
+impl F32x8 {
+    /// `[0, 1, .., 7]`
+    pub const fn lanes() -> [f32; LANES] {
+        [0.0, 1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0]
+    }
+
+    #[inline(always)]
+    pub fn splat(v: f32) -> Self {
+        Self(imp::splat(v))
+    }
+
+    #[inline(always)]
+    pub fn from_array(a: [f32; LANES]) -> Self {
+        Self(imp::from_array(a))
+    }
+
+    #[inline(always)]
+    pub fn to_array(self) -> [f32; LANES] {
+        imp::to_array(self.0)
+    }
+
+    /// Bit `i` is set if lane `i` of `self` is greater than lane `i` of `other`.
+    #[inline(always)]
+    pub fn gt(self, other: Self) -> u32 {
+        imp::gt(self.0, other.0)
+    }
+
+    #[inline(always)]
+    pub fn eq(self, other: Self) -> u32 {
+        imp::eq(self.0, other.0)
+    }
+
+    #[inline(always)]
+    pub fn le(self, other: Self) -> u32 {
+        imp::le(self.0, other.0)
+    }
+}
+
+impl Add for F32x8 {
+    type Output = Self;
+
+    #[inline(always)]
+    fn add(self, rhs: Self) -> Self {
+        Self(imp::add(self.0, rhs.0))
+    }
+}
+
+impl Sub for F32x8 {
+    type Output = Self;
+
+    #[inline(always)]
+    fn sub(self, rhs: Self) -> Self {
+        Self(imp::sub(self.0, rhs.0))
+    }
+}
+
+impl Mul for F32x8 {
+    type Output = Self;
+
+    #[inline(always)]
+    fn mul(self, rhs: Self) -> Self {
+        Self(imp::mul(self.0, rhs.0))
+    }
+}
+
+impl Div for F32x8 {
+    type Output = Self;
+
+    #[inline(always)]
+    fn div(self, rhs: Self) -> Self {
+        Self(imp::div(self.0, rhs.0))
+    }
+}
+
+impl Neg for F32x8 {
+    type Output = Self;
+
+    #[inline(always)]
+    fn neg(self) -> Self {
+        Self(imp::neg(self.0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VALUES: [f32; 16] = [
+        0.0,
+        -0.0,
+        1.0,
+        -2.5,
+        0.1,
+        1e-40,
+        -1e-40,
+        3.4e38,
+        -3.4e38,
+        f32::MIN_POSITIVE,
+        f32::EPSILON,
+        f32::INFINITY,
+        f32::NEG_INFINITY,
+        f32::NAN,
+        1.0 / 3.0,
+        -7.0,
+    ];
+
+    // Every pair of values, eight at a time.
+    fn operands() -> impl Iterator<Item = ([f32; LANES], [f32; LANES])> {
+        let pairs: alloc::vec::Vec<_> = VALUES
+            .iter()
+            .flat_map(|&a| VALUES.iter().map(move |&b| (a, b)))
+            .collect();
+        let blocks: alloc::vec::Vec<_> = pairs
+            .chunks_exact(LANES)
+            .map(|pairs| {
+                (
+                    core::array::from_fn(|i| pairs[i].0),
+                    core::array::from_fn(|i| pairs[i].1),
+                )
+            })
+            .collect();
+        blocks.into_iter()
+    }
+
+    fn assert_same(a: [f32; LANES], b: [f32; LANES]) {
+        for (a, b) in a.into_iter().zip(b) {
+            assert!(
+                a.to_bits() == b.to_bits() || (a.is_nan() && b.is_nan()),
+                "{a} {b}"
+            );
+        }
+    }
+
+    #[test]
+    fn lanes_match_scalar_operations() {
+        for (a, b) in operands() {
+            let (x, y) = (F32x8::from_array(a), F32x8::from_array(b));
+            assert_same(x.to_array(), scalar::to_array(scalar::from_array(a)));
+            assert_same((x + y).to_array(), scalar::add(a, b));
+            assert_same((x - y).to_array(), scalar::sub(a, b));
+            assert_same((x * y).to_array(), scalar::mul(a, b));
+            assert_same((x / y).to_array(), scalar::div(a, b));
+            assert_same((-x).to_array(), scalar::neg(a));
+            assert_eq!(x.gt(y), scalar::gt(a, b), "{a:?} > {b:?}");
+            assert_eq!(x.eq(y), scalar::eq(a, b), "{a:?} == {b:?}");
+            assert_eq!(x.le(y), scalar::le(a, b), "{a:?} <= {b:?}");
+        }
+        for v in VALUES {
+            assert_same(F32x8::splat(v).to_array(), scalar::splat(v));
+        }
+    }
+}
@@ -0,0 +1,395 @@
+use crate::Compare;
+
+/// What happens to a stencil value after the stencil and depth tests.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum StencilOp {
+    #[default]
+    Keep,
+    Zero,
+    /// Writes [`StencilState::reference`].
+    Replace,
+    /// Increments, clamping to `255`.
+    IncrementClamp,
+    /// Decrements, clamping to `0`.
+    DecrementClamp,
+    /// Increments, wrapping to `0`.
+    IncrementWrap,
+    /// Decrements, wrapping to `255`.
+    DecrementWrap,
+    /// Inverts every bit.
+    Invert,
+}
+
+impl StencilOp {
+    #[inline]
+    fn apply(self, value: u8, reference: u8) -> u8 {
+        match self {
+            Self::Keep => value,
+            Self::Zero => 0,
+            Self::Replace => reference,
+            Self::IncrementClamp => value.saturating_add(1),
+            Self::DecrementClamp => value.saturating_sub(1),
+            Self::IncrementWrap => value.wrapping_add(1),
+            Self::DecrementWrap => value.wrapping_sub(1),
+            Self::Invert => !value,
+        }
+    }
+}
+
+/// Stencil test and operations for triangles of one facing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StencilFace {
+    /// Compares the masked reference against the masked stencil value, the test passes if
+    /// `reference compare stencil` holds.
+    pub compare: Compare,
+    /// The stencil test failed.
+    pub fail: StencilOp,
+    /// The stencil test passed and the depth test failed.
+    pub depth_fail: StencilOp,
+    /// Both tests passed.
+    pub pass: StencilOp,
+}
+
+impl Default for StencilFace {
+    fn default() -> Self {
+        Self {
+            compare: Compare::Always,
+            fail: StencilOp::Keep,
+            depth_fail: StencilOp::Keep,
+            pass: StencilOp::Keep,
+        }
+    }
+}
+
+/// Per-draw stencil state, used if the target has a stencil attachment.
+///
+/// The default state passes every fragment and never writes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StencilState {
+    pub front: StencilFace,
+    pub back: StencilFace,
+    pub reference: u8,
+    /// Bits of the reference and stencil value that are compared.
+    pub read_mask: u8,
+    /// Bits of the stencil value that are written.
+    pub write_mask: u8,
+}
+
+impl Default for StencilState {
+    fn default() -> Self {
+        Self {
+            front: StencilFace::default(),
+            back: StencilFace::default(),
+            reference: 0,
+            read_mask: 0xff,
+            write_mask: 0xff,
+        }
+    }
+}
+
+impl StencilState {
+    /// Uses the same test and operations for both facings.
+    pub fn both(face: StencilFace, reference: u8) -> Self {
+        Self {
+            front: face,
+            back: face,
+            reference,
+            ..Self::default()
+        }
+    }
+
+    /// Whether the state can reject fragments or change stencil values.
+    #[inline]
+    pub fn is_enabled(&self) -> bool {
+        self.front != StencilFace::default() || self.back != StencilFace::default()
+    }
+
+    /// Runs the stencil test on the fragments in `mask` and updates the stencil values of
+    /// those that failed either test.
+    ///
+    /// `depth_pass` are the fragments that passed the depth test. Returns the fragments
+    /// that passed both tests, see [`StencilState::pass`].
+    #[inline]
+    pub(crate) fn test<'a>(
+        &self,
+        stencil: impl IntoIterator<Item = &'a mut u8>,
+        mask: u32,
+        depth_pass: u32,
+        front_facing: bool,
+    ) -> u32 {
+        let face = if front_facing { self.front } else { self.back };
+        let reference = self.reference & self.read_mask;
+        let mut passed = 0;
+        for (i, value) in stencil.into_iter().enumerate() {
+            let bit = 1 << i;
+            if mask & bit == 0 {
+                continue;
+            }
+
+            let op = if !face.compare.test(reference, *value & self.read_mask) {
+                face.fail
+            } else if depth_pass & bit == 0 {
+                face.depth_fail
+            } else {
+                passed |= bit;
+                continue;
+            };
+            self.write(value, op);
+        }
+        passed
+    }
+
+    /// Updates the stencil values of the fragments in `mask` that passed both tests and were
+    /// not discarded by the fragment stage.
+    #[inline]
+    pub(crate) fn pass<'a>(
+        &self,
+        stencil: impl IntoIterator<Item = &'a mut u8>,
+        mask: u32,
+        front_facing: bool,
+    ) {
+        let face = if front_facing { self.front } else { self.back };
+        for (i, value) in stencil.into_iter().enumerate() {
+            if mask & (1 << i) != 0 {
+                self.write(value, face.pass);
+            }
+        }
+    }
+
+    #[inline]
+    fn write(&self, value: &mut u8, op: StencilOp) {
+        let new = op.apply(*value, self.reference);
+        *value = (*value & !self.write_mask) | (new & self.write_mask);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tint::LinearRgb;
+
+    use super::*;
+    use crate::{ColorShader, DrawState, OwnedFramebuffer, draw_triangle};
+
+    const OPS: [StencilOp; 8] = [
+        StencilOp::Keep,
+        StencilOp::Zero,
+        StencilOp::Replace,
+        StencilOp::IncrementClamp,
+        StencilOp::DecrementClamp,
+        StencilOp::IncrementWrap,
+        StencilOp::DecrementWrap,
+        StencilOp::Invert,
+    ];
+
+    fn face(
+        compare: Compare,
+        fail: StencilOp,
+        depth_fail: StencilOp,
+        pass: StencilOp,
+    ) -> StencilFace {
+        StencilFace {
+            compare,
+            fail,
+            depth_fail,
+            pass,
+        }
+    }
+
+    #[test]
+    fn operations_clamp_and_wrap() {
+        let expected = [
+            // 0, 1, 254, 255 with a reference of 42
+            [0, 1, 254, 255],
+            [0, 0, 0, 0],
+            [42, 42, 42, 42],
+            [1, 2, 255, 255],
+            [0, 0, 253, 254],
+            [1, 2, 255, 0],
+            [255, 0, 253, 254],
+            [255, 254, 1, 0],
+        ];
+        for (op, expected) in OPS.into_iter().zip(expected) {
+            let values = [0, 1, 254, 255].map(|value| op.apply(value, 42));
+            assert_eq!(values, expected, "{op:?}");
+        }
+    }
+
+    #[test]
+    fn failing_tests_pick_their_operation() {
+        let state = StencilState::both(
+            face(
+                Compare::Equal,
+                StencilOp::IncrementClamp,
+                StencilOp::Invert,
+                StencilOp::Replace,
+            ),
+            5,
+        );
+        // fails the stencil test, fails only the depth test, passes both and outside of the mask
+        let mut stencil = [4, 5, 5, 5];
+        let passed = state.test(&mut stencil, 0b0111, 0b0101, true);
+        assert_eq!(passed, 0b0100);
+        assert_eq!(stencil, [5, !5, 5, 5]);
+
+        // the fail operation wins over the depth fail operation
+        let mut stencil = [4];
+        state.test(&mut stencil, 1, 0, true);
+        assert_eq!(stencil, [5]);
+
+        // fragments that passed are written by `pass`, after the fragment stage kept them
+        let state = StencilState {
+            reference: 9,
+            ..state
+        };
+        let mut stencil = [4, 5, 5, 5];
+        state.pass(&mut stencil, passed, true);
+        assert_eq!(stencil, [4, 5, 9, 5]);
+    }
+
+    #[test]
+    fn masks_limit_the_compared_and_written_bits() {
+        let state = StencilState {
+            read_mask: 0x0f,
+            write_mask: 0xf0,
+            ..StencilState::both(
+                face(
+                    Compare::Equal,
+                    StencilOp::Replace,
+                    StencilOp::Keep,
+                    StencilOp::Invert,
+                ),
+                0xa3,
+            )
+        };
+        // only the low bits are compared, so 0x53 passes and 0x54 fails
+        let mut stencil = [0x53, 0x54];
+        let passed = state.test(&mut stencil, 0b11, 0b11, true);
+        assert_eq!(passed, 0b01);
+        // only the high bits of the reference are written
+        assert_eq!(stencil, [0x53, 0xa4]);
+        state.pass(&mut stencil, passed, true);
+        assert_eq!(stencil, [0xa3, 0xa4]);
+    }
+
+    #[test]
+    fn facings_use_their_own_state() {
+        let state = StencilState {
+            front: face(
+                Compare::Always,
+                StencilOp::Keep,
+                StencilOp::Keep,
+                StencilOp::IncrementWrap,
+            ),
+            back: face(
+                Compare::Less,
+                StencilOp::Zero,
+                StencilOp::Keep,
+                StencilOp::DecrementWrap,
+            ),
+            reference: 3,
+            ..Default::default()
+        };
+        let mut stencil = [2, 4];
+        assert_eq!(state.test(&mut stencil, 0b11, 0b11, true), 0b11);
+        assert_eq!(state.test(&mut stencil, 0b11, 0b11, false), 0b10);
+        assert_eq!(stencil, [0, 4]);
+        state.pass(&mut stencil, 0b11, true);
+        state.pass(&mut stencil, 0b10, false);
+        assert_eq!(stencil, [1, 4]);
+    }
+
+    // Draws a triangle covering the whole target at depth `z`, front facing if `ccw`.
+    fn draw_full(target: &mut OwnedFramebuffer<LinearRgb>, state: DrawState, z: f32, ccw: bool) {
+        let color = LinearRgb::rgb(1.0, 1.0, 1.0);
+        let (v1, v2, v3) = (
+            ([-1.0, -1.0, z, 1.0], color),
+            ([3.0, -1.0, z, 1.0], color),
+            ([-1.0, 3.0, z, 1.0], color),
+        );
+        if ccw {
+            draw_triangle(target, state, v1, v2, v3, ColorShader);
+        } else {
+            draw_triangle(target, state, v1, v3, v2, ColorShader);
+        }
+    }
+
+    #[test]
+    fn draws_update_the_stencil_attachment() {
+        let black = LinearRgb::rgb(0.0, 0.0, 0.0);
+        let mut target = OwnedFramebuffer::new(16, 16, black)
+            .with_depth(0.5)
+            .with_stencil(0);
+        let stencil = StencilState::both(
+            face(
+                Compare::Always,
+                StencilOp::Keep,
+                StencilOp::IncrementClamp,
+                StencilOp::Replace,
+            ),
+            7,
+        );
+        let state = DrawState {
+            stencil,
+            ..Default::default()
+        };
+
+        // behind the stored depth
+        draw_full(&mut target, state, 0.75, true);
+        assert!(target.stencil().unwrap().iter().all(|&s| s == 1));
+        assert!(target.color().iter().all(|&c| c == black));
+
+        // in front of it
+        draw_full(&mut target, state, 0.25, true);
+        assert!(target.stencil().unwrap().iter().all(|&s| s == 7));
+
+        // only where the stencil value is not 7, which is nowhere
+        let mut target = OwnedFramebuffer::new(16, 16, black).with_stencil(7);
+        let stencil = StencilState::both(
+            face(
+                Compare::NotEqual,
+                StencilOp::Keep,
+                StencilOp::Keep,
+                StencilOp::Zero,
+            ),
+            7,
+        );
+        let state = DrawState {
+            stencil,
+            ..Default::default()
+        };
+        draw_full(&mut target, state, 0.5, false);
+        assert!(target.stencil().unwrap().iter().all(|&s| s == 7));
+        assert!(target.color().iter().all(|&c| c == black));
+    }
+
+    #[test]
+    fn draws_pick_the_face_of_each_triangle() {
+        let black = LinearRgb::rgb(0.0, 0.0, 0.0);
+        let state = DrawState {
+            stencil: StencilState {
+                front: face(
+                    Compare::Always,
+                    StencilOp::Keep,
+                    StencilOp::Keep,
+                    StencilOp::IncrementWrap,
+                ),
+                back: face(
+                    Compare::Always,
+                    StencilOp::Keep,
+                    StencilOp::Keep,
+                    StencilOp::DecrementWrap,
+                ),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        for (ccw, expected) in [(true, 1), (false, 255)] {
+            let mut target = OwnedFramebuffer::new(16, 16, black).with_stencil(0);
+            draw_full(&mut target, state, 0.5, ccw);
+            assert!(
+                target.stencil().unwrap().iter().all(|&s| s == expected),
+                "{ccw}"
+            );
+        }
+    }
+}
@@ -0,0 +1,555 @@
+use core::f32::consts::PI;
+
+use crate::clip::clip_segment;
+
+/// Shape of the open ends of a stroke.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum LineCap {
+    /// Ends exactly at the end points.
+    #[default]
+    Butt,
+    /// Extends by a half circle.
+    Round,
+    /// Extends by half the width.
+    Square,
+}
+
+/// Shape of the corners where the segments of a stroke meet.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum LineJoin {
+    /// Extends the outer edges until they meet, see [`StrokeStyle::miter_limit`].
+    #[default]
+    Miter,
+    /// Cuts the corner off.
+    Bevel,
+    /// Rounds the corner with a circle.
+    Round,
+}
+
+/// How lines are widened into filled geometry.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StrokeStyle<'a> {
+    /// Width in pixels.
+    pub width: f32,
+    pub cap: LineCap,
+    pub join: LineJoin,
+    /// Longest miter relative to half the width, longer ones fall back to a bevel.
+    pub miter_limit: f32,
+    /// Alternating lengths of dashes and gaps, starting with a dash. Odd patterns repeat
+    /// twice to alternate. Solid if empty.
+    pub dashes: &'a [f32],
+    /// Distance into the dash pattern at the start of the line.
+    pub dash_offset: f32,
+}
+
+impl Default for StrokeStyle<'_> {
+    fn default() -> Self {
+        Self::new(1.0)
+    }
+}
+
+impl StrokeStyle<'_> {
+    pub const fn new(width: f32) -> Self {
+        Self {
+            width,
+            cap: LineCap::Butt,
+            join: LineJoin::Miter,
+            miter_limit: 4.0,
+            dashes: &[],
+            dash_offset: 0.0,
+        }
+    }
+}
+
+type Point = (f32, f32);
+
+// Largest distance of the chords of round caps and joins from the circle, in pixels.
+const TOLERANCE: f32 = 0.1;
+
+/// Widens polylines into triangles, given to `emit` as they are generated.
+///
+/// Triangles of the same stroke overlap, so they are meant for a single color fill.
+pub(crate) struct Stroker<'a, F> {
+    style: StrokeStyle<'a>,
+    half_width: f32,
+    emit: F,
+    start: Point,
+    current: Point,
+    // direction of the first and the last segment of the current polyline
+    first: Option<Point>,
+    last: Option<Point>,
+}
+
+impl<'a, F: FnMut([Point; 3])> Stroker<'a, F> {
+    pub(crate) fn new(style: StrokeStyle<'a>, emit: F) -> Self {
+        Self {
+            style,
+            half_width: style.width * 0.5,
+            emit,
+            start: (0.0, 0.0),
+            current: (0.0, 0.0),
+            first: None,
+            last: None,
+        }
+    }
+
+    /// Strokes `points` with the dash pattern of the style, skipping everything that can't
+    /// reach a target of `size` pixels.
+    pub(crate) fn polyline(&mut self, points: &[Point], closed: bool, size: (usize, usize)) {
+        let (Some(&first), Some(&last)) = (points.first(), points.last()) else {
+            return;
+        };
+        let mut dash = Dash::new(self.style.dashes, self.style.dash_offset);
+        let solid = dash.dashes.is_empty();
+
+        // joins and caps reach at most this far from the polyline
+        let margin = self.half_width * self.style.miter_limit.max(1.0) + 1.0;
+        let bounds = (
+            -margin,
+            -margin,
+            size.0 as f32 + margin,
+            size.1 as f32 + margin,
+        );
+
+        let closing = closed.then_some([last, first]);
+        let segments = points.windows(2).map(|w| [w[0], w[1]]).chain(closing);
+        let mut running = false;
+        let mut clipped = false;
+        for [a, b] in segments {
+            let length = libm::hypotf(b.0 - a.0, b.1 - a.1);
+            let span = clip_segment(a, b, bounds).filter(|_| length.is_finite());
+            let Some((t0, t1)) = span.filter(|(t0, t1)| t0 < t1 || length == 0.0) else {
+                if running {
+                    self.finish(false);
+                    running = false;
+                }
+                clipped = true;
+                dash.skip(length);
+                continue;
+            };
+            let at = |t: f32| (a.0 + (b.0 - a.0) * t, a.1 + (b.1 - a.1) * t);
+            let (a, b) = (at(t0), at(t1));
+            if t0 > 0.0 {
+                if running {
+                    self.finish(false);
+                    running = false;
+                }
+                clipped = true;
+                dash.skip(t0 * length);
+            }
+            if !running && dash.on {
+                self.move_to(a);
+                running = true;
+            }
+
+            // dashes are measured on the visible part, far away points are imprecise
+            let visible = libm::hypotf(b.0 - a.0, b.1 - a.1);
+            let at = |t: f32| {
+                (
+                    a.0 + (b.0 - a.0) * t / visible,
+                    a.1 + (b.1 - a.1) * t / visible,
+                )
+            };
+            let mut t = 0.0;
+            while visible - t > dash.remaining {
+                t += dash.remaining;
+                if dash.on {
+                    self.line_to(at(t));
+                    self.finish(false);
+                    running = false;
+                } else {
+                    self.move_to(at(t));
+                    running = true;
+                }
+                dash.next();
+            }
+            dash.remaining -= visible - t;
+            if dash.on {
+                self.line_to(b);
+            }
+
+            if t1 < 1.0 {
+                if running {
+                    self.finish(false);
+                    running = false;
+                }
+                clipped = true;
+                dash.skip((1.0 - t1) * length);
+            }
+        }
+        if running {
+            self.finish(closed && solid && !clipped);
+        }
+    }
+
+    fn move_to(&mut self, p: Point) {
+        self.start = p;
+        self.current = p;
+        self.first = None;
+        self.last = None;
+    }
+
+    fn line_to(&mut self, p: Point) {
+        let a = self.current;
+        let Some(d) = normalize((p.0 - a.0, p.1 - a.1)) else {
+            return;
+        };
+        match self.last {
+            Some(last) => self.join(a, last, d),
+            None => self.first = Some(d),
+        }
+        let n = (-d.1 * self.half_width, d.0 * self.half_width);
+        let (a1, a2) = ((a.0 + n.0, a.1 + n.1), (a.0 - n.0, a.1 - n.1));
+        let (b1, b2) = ((p.0 + n.0, p.1 + n.1), (p.0 - n.0, p.1 - n.1));
+        (self.emit)([a1, b1, b2]);
+        (self.emit)([a1, b2, a2]);
+        self.current = p;
+        self.last = Some(d);
+    }
+
+    // Ends the current polyline with caps, or joins its end to its start if `closed`.
+    fn finish(&mut self, closed: bool) {
+        if closed {
+            self.line_to(self.start);
+        }
+        let (Some(first), Some(last)) = (self.first, self.last) else {
+            return;
+        };
+        if closed {
+            self.join(self.start, last, first);
+        } else {
+            self.cap(self.start, (-first.0, -first.1));
+            self.cap(self.current, last);
+        }
+        self.first = None;
+        self.last = None;
+    }
+
+    // Caps the end at `p` of a segment pointing away from it in the direction `d`.
+    fn cap(&mut self, p: Point, d: Point) {
+        let hw = self.half_width;
+        let n = (-d.1 * hw, d.0 * hw);
+        match self.style.cap {
+            LineCap::Butt => {}
+            LineCap::Square => {
+                let e = (d.0 * hw, d.1 * hw);
+                let (a1, a2) = ((p.0 + n.0, p.1 + n.1), (p.0 - n.0, p.1 - n.1));
+                let (b1, b2) = ((a1.0 + e.0, a1.1 + e.1), (a2.0 + e.0, a2.1 + e.1));
+                (self.emit)([a1, b1, b2]);
+                (self.emit)([a1, b2, a2]);
+            }
+            LineCap::Round => self.fan(p, n, -PI),
+        }
+    }
+
+    // Joins a segment in the direction `d0` ending at `p` to one in the direction `d1`.
+    fn join(&mut self, p: Point, d0: Point, d1: Point) {
+        let cross = d0.0 * d1.1 - d0.1 * d1.0;
+        let dot = d0.0 * d1.0 + d0.1 * d1.1;
+        if cross == 0.0 && dot > 0.0 {
+            return;
+        }
+
+        // the inner corner is covered by the segments, only the outer one is filled
+        let hw = self.half_width;
+        let side = if cross > 0.0 { -hw } else { hw };
+        let o0 = (-d0.1 * side, d0.0 * side);
+        let o1 = (-d1.1 * side, d1.0 * side);
+        let (a, b) = ((p.0 + o0.0, p.1 + o0.1), (p.0 + o1.0, p.1 + o1.1));
+        match self.style.join {
+            LineJoin::Bevel => (self.emit)([p, a, b]),
+            LineJoin::Round => {
+                // the arc bulges forward, which decides the direction of a full reversal
+                let sweep = libm::atan2f(o0.0 * o1.1 - o0.1 * o1.0, o0.0 * o1.0 + o0.1 * o1.1);
+                let sweep = if cross == 0.0 {
+                    if -o0.1 * d0.0 + o0.0 * d0.1 > 0.0 {
+                        PI
+                    } else {
+                        -PI
+                    }
+                } else {
+                    sweep
+                };
+                self.fan(p, o0, sweep);
+            }
+            LineJoin::Miter => {
+                // the miter is `1 / cos(angle / 2)` half widths long
+                let cos = libm::sqrtf((1.0 + dot) * 0.5);
+                let limit = self.style.miter_limit;
+                if cos * limit < 1.0 {
+                    (self.emit)([p, a, b]);
+                    return;
+                }
+                let Some(m) = normalize((o0.0 + o1.0, o0.1 + o1.1)) else {
+                    return;
+                };
+                let length = hw / cos;
+                let tip = (p.0 + m.0 * length, p.1 + m.1 * length);
+                (self.emit)([p, a, tip]);
+                (self.emit)([p, tip, b]);
+            }
+        }
+    }
+
+    // Fills the circular sector around `p` from the offset `from` by `sweep` radians.
+    fn fan(&mut self, p: Point, from: Point, sweep: f32) {
+        let count = arc_segments(self.half_width, sweep);
+        let (sin, cos) = libm::sincosf(sweep / count as f32);
+        let mut v = from;
+        for _ in 0..count {
+            let next = (v.0 * cos - v.1 * sin, v.0 * sin + v.1 * cos);
+            (self.emit)([p, (p.0 + v.0, p.1 + v.1), (p.0 + next.0, p.1 + next.1)]);
+            v = next;
+        }
+    }
+}
+
+/// Number of chords that approximate an arc of `sweep` radians closely enough.
+pub(crate) fn arc_segments(radius: f32, sweep: f32) -> usize {
+    let step = if radius > TOLERANCE {
+        2.0 * libm::acosf(1.0 - TOLERANCE / radius)
+    } else {
+        PI
+    };
+    libm::ceilf(sweep.abs() / step).max(1.0) as usize
+}
+
+#[inline]
+fn normalize(v: Point) -> Option<Point> {
+    let length = libm::hypotf(v.0, v.1);
+    (length > 0.0 && length.is_finite()).then(|| (v.0 / length, v.1 / length))
+}
+
+// Position in a dash pattern, always on without one.
+struct Dash<'a> {
+    dashes: &'a [f32],
+    period: f32,
+    index: usize,
+    remaining: f32,
+    on: bool,
+}
+
+impl<'a> Dash<'a> {
+    // Patterns that can't be walked are solid.
+    fn new(dashes: &'a [f32], offset: f32) -> Self {
+        let sum: f32 = dashes.iter().sum();
+        let valid = dashes.iter().all(|d| *d >= 0.0) && sum > 0.0 && sum.is_finite();
+        if !valid {
+            return Self {
+                dashes: &[],
+                period: 0.0,
+                index: 0,
+                remaining: f32::INFINITY,
+                on: true,
+            };
+        }
+
+        // odd patterns alternate over two repetitions
+        let period = if dashes.len().is_multiple_of(2) {
+            sum
+        } else {
+            sum * 2.0
+        };
+        let mut dash = Self {
+            dashes,
+            period,
+            index: 0,
+            remaining: dashes[0],
+            on: true,
+        };
+        let offset = offset % period;
+        dash.skip(if offset < 0.0 {
+            offset + period
+        } else {
+            offset
+        });
+        dash
+    }
+
+    fn next(&mut self) {
+        self.index += 1;
+        self.remaining = self.dashes[self.index % self.dashes.len()];
+        self.on = self.index.is_multiple_of(2);
+    }
+
+    fn skip(&mut self, distance: f32) {
+        if self.dashes.is_empty() || !distance.is_finite() {
+            return;
+        }
+        let mut distance = distance % self.period;
+        while distance >= self.remaining {
+            distance -= self.remaining;
+            self.next();
+        }
+        self.remaining -= distance;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+
+    use super::*;
+
+    fn stroke(points: &[Point], closed: bool, style: StrokeStyle) -> Vec<[Point; 3]> {
+        let mut triangles = Vec::new();
+        Stroker::new(style, |triangle| triangles.push(triangle)).polyline(points, closed, (64, 64));
+        triangles
+    }
+
+    fn covers(triangles: &[[Point; 3]], p: Point) -> bool {
+        let side = |a: Point, b: Point| (b.0 - a.0) * (p.1 - a.1) - (b.1 - a.1) * (p.0 - a.0);
+        triangles.iter().any(|&[a, b, c]| {
+            let (s1, s2, s3) = (side(a, b), side(b, c), side(c, a));
+            (s1 >= 0.0 && s2 >= 0.0 && s3 >= 0.0) || (s1 <= 0.0 && s2 <= 0.0 && s3 <= 0.0)
+        })
+    }
+
+    #[test]
+    fn caps_extend_the_ends() {
+        let line = [(10.0, 10.0), (20.0, 10.0)];
+        for (cap, covered, uncovered) in [
+            (
+                LineCap::Butt,
+                [(10.2, 11.8), (19.8, 8.2)],
+                [(9.8, 10.0), (20.2, 10.0)],
+            ),
+            (
+                LineCap::Square,
+                [(8.2, 11.8), (21.8, 8.2)],
+                [(7.8, 10.0), (22.2, 10.0)],
+            ),
+            (
+                LineCap::Round,
+                [(8.2, 10.0), (21.2, 11.2)],
+                [(8.5, 11.5), (21.5, 8.5)],
+            ),
+        ] {
+            let style = StrokeStyle {
+                cap,
+                ..StrokeStyle::new(4.0)
+            };
+            let triangles = stroke(&line, false, style);
+            for p in covered {
+                assert!(covers(&triangles, p), "{cap:?} {p:?}");
+            }
+            for p in uncovered {
+                assert!(!covers(&triangles, p), "{cap:?} {p:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn joins_fill_the_outer_corner() {
+        // turns from right to down, the outer corner is at the top right
+        let corner = [(10.0, 10.0), (20.0, 10.0), (20.0, 20.0)];
+        for (join, miter_limit, tip, edge, round) in [
+            (LineJoin::Miter, 4.0, true, true, true),
+            // right angles need a limit of `sqrt(2)`
+            (LineJoin::Miter, 1.5, true, true, true),
+            (LineJoin::Miter, 1.4, false, true, false),
+            (LineJoin::Bevel, 4.0, false, true, false),
+            (LineJoin::Round, 4.0, false, true, true),
+        ] {
+            let style = StrokeStyle {
+                join,
+                miter_limit,
+                ..StrokeStyle::new(4.0)
+            };
+            let triangles = stroke(&corner, false, style);
+            let name = (join, miter_limit);
+            assert_eq!(covers(&triangles, (21.8, 8.2)), tip, "{name:?}");
+            assert_eq!(covers(&triangles, (21.0, 9.5)), edge, "{name:?}");
+            assert_eq!(covers(&triangles, (21.3, 8.7)), round, "{name:?}");
+            // the inner corner is always covered
+            assert!(covers(&triangles, (18.2, 11.8)), "{name:?}");
+        }
+    }
+
+    #[test]
+    fn sharp_miters_are_limited() {
+        // turns back by all but about 11 degrees, the miter is about 10 half widths long
+        let corner = [(10.0, 10.0), (30.0, 10.0), (10.0, 14.0)];
+        for (miter_limit, tip) in [(11.0, true), (9.0, false)] {
+            let style = StrokeStyle {
+                miter_limit,
+                ..StrokeStyle::new(2.0)
+            };
+            let triangles = stroke(&corner, false, style);
+            assert_eq!(covers(&triangles, (37.5, 9.3)), tip, "{miter_limit}");
+        }
+    }
+
+    // Which of the points along `x` are covered by a dashed horizontal line.
+    fn dashed(dashes: &[f32], dash_offset: f32, xs: &[f32]) -> Vec<bool> {
+        let style = StrokeStyle {
+            dashes,
+            dash_offset,
+            ..StrokeStyle::new(2.0)
+        };
+        let triangles = stroke(&[(0.0, 10.0), (40.0, 10.0)], false, style);
+        xs.iter().map(|&x| covers(&triangles, (x, 10.0))).collect()
+    }
+
+    #[test]
+    fn dashes_alternate_from_their_offset() {
+        let xs = [2.5, 7.5, 12.5, 17.5];
+        assert_eq!(dashed(&[5.0, 5.0], 0.0, &xs), [true, false, true, false]);
+        // starts two into the first dash
+        let xs = [1.5, 5.0, 10.0, 15.0];
+        assert_eq!(dashed(&[5.0, 5.0], 2.0, &xs), [true, false, true, false]);
+        // negative offsets start before the pattern, two from the end of the gap
+        let xs = [1.0, 4.5, 9.5, 14.5];
+        assert_eq!(dashed(&[5.0, 5.0], -2.0, &xs), [false, true, false, true]);
+        // offsets wrap around the period
+        assert_eq!(dashed(&[5.0, 5.0], 28.0, &xs), [false, true, false, true]);
+    }
+
+    #[test]
+    fn odd_dash_patterns_repeat_twice() {
+        // dash 4, gap 2, dash 6, gap 4, dash 2, gap 6
+        let xs = [2.0, 5.0, 9.0, 14.0, 17.0, 21.0, 26.0, 29.0];
+        let expected = [true, false, true, false, true, false, true, false];
+        assert_eq!(dashed(&[4.0, 2.0, 6.0], 0.0, &xs), expected);
+    }
+
+    #[test]
+    fn invalid_dash_patterns_are_solid() {
+        let xs = [2.5, 7.5, 12.5, 17.5];
+        for dashes in [
+            &[0.0, 0.0][..],
+            &[-1.0, 5.0],
+            &[f32::INFINITY, 1.0],
+            &[f32::NAN],
+        ] {
+            assert_eq!(dashed(dashes, 0.0, &xs), [true; 4], "{dashes:?}");
+        }
+    }
+
+    #[test]
+    fn closed_polylines_join_their_ends() {
+        let square = [(10.0, 10.0), (20.0, 10.0), (20.0, 20.0), (10.0, 20.0)];
+        let style = StrokeStyle::new(4.0);
+        let closed = stroke(&square, true, style);
+        let open = stroke(&square, false, style);
+        // the outer corner of the first point, and the closing segment
+        assert!(covers(&closed, (8.2, 8.2)));
+        assert!(covers(&closed, (10.0, 15.0)));
+        assert!(!covers(&open, (8.2, 8.2)));
+        assert!(!covers(&open, (10.0, 15.0)));
+        // the other corners are joined either way
+        for triangles in [&closed, &open] {
+            assert!(covers(triangles, (21.8, 8.2)));
+            assert!(covers(triangles, (21.8, 21.8)));
+        }
+
+        // dashed outlines have no start to join to
+        let style = StrokeStyle {
+            dashes: &[100.0, 1.0],
+            ..style
+        };
+        let dashed = stroke(&square, true, style);
+        assert!(covers(&dashed, (10.0, 15.0)));
+        assert!(!covers(&dashed, (8.2, 8.2)));
+    }
+}
@@ -0,0 +1,589 @@
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+
+use tint::Color;
+
+use crate::{DepthFormat, SampleCount, multisample};
+
+/// Something that can be drawn into, see [`Framebuffer`].
+pub trait RenderTarget {
+    type Pixel: Copy;
+    type Depth: DepthFormat;
+
+    /// Borrows the attachments of the target.
+    fn framebuffer(&mut self) -> Framebuffer<'_, Self::Pixel, Self::Depth>;
+}
+
+/// Borrowed color and optional depth and stencil attachments of `width` by `height`
+/// pixels, stored row by row.
+///
+/// The depth attachment is in the format `D`, see [`DepthFormat`]. Multisampled
+/// framebuffers store the samples of every pixel next to each other, see [`SampleCount`].
+/// The length of every attachment is checked when it is attached.
+#[derive(Debug)]
+pub struct Framebuffer<'a, P, D = f32> {
+    pub(crate) color: &'a mut [P],
+    pub(crate) depth: Option<&'a mut [D]>,
+    pub(crate) stencil: Option<&'a mut [u8]>,
+    pub(crate) width: usize,
+    pub(crate) height: usize,
+    pub(crate) samples: SampleCount,
+}
+
+impl<'a, P> Framebuffer<'a, P> {
+    /// Fails if `color` is not `width * height` long.
+    pub fn new(color: &'a mut [P], width: usize, height: usize) -> Result<Self, SizeError> {
+        Self::new_multisample(color, width, height, SampleCount::X1)
+    }
+
+    /// Fails if `color` is not `width * height * samples` long.
+    pub fn new_multisample(
+        color: &'a mut [P],
+        width: usize,
+        height: usize,
+        samples: SampleCount,
+    ) -> Result<Self, SizeError> {
+        check_size(color.len(), width, height, samples)?;
+        Ok(Self {
+            color,
+            depth: None,
+            stencil: None,
+            width,
+            height,
+            samples,
+        })
+    }
+}
+
+impl<'a, P, D: DepthFormat> Framebuffer<'a, P, D> {
+    /// Attaches a depth buffer in any [`DepthFormat`], failing if it is not as long as the
+    /// color attachment.
+    pub fn with_depth<E: DepthFormat>(
+        self,
+        depth: &'a mut [E],
+    ) -> Result<Framebuffer<'a, P, E>, SizeError> {
+        check_size(depth.len(), self.width, self.height, self.samples)?;
+        Ok(Framebuffer {
+            color: self.color,
+            depth: Some(depth),
+            stencil: self.stencil,
+            width: self.width,
+            height: self.height,
+            samples: self.samples,
+        })
+    }
+
+    /// Attaches a stencil buffer, failing if it is not as long as the color attachment.
+    pub fn with_stencil(mut self, stencil: &'a mut [u8]) -> Result<Self, SizeError> {
+        check_size(stencil.len(), self.width, self.height, self.samples)?;
+        self.stencil = Some(stencil);
+        Ok(self)
+    }
+
+    // Detaches the depth and stencil attachments, for draws that don't test them.
+    pub(crate) fn color_only(self) -> Framebuffer<'a, P> {
+        Framebuffer {
+            color: self.color,
+            depth: None,
+            stencil: None,
+            width: self.width,
+            height: self.height,
+            samples: self.samples,
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn samples(&self) -> SampleCount {
+        self.samples
+    }
+
+    pub fn color(&mut self) -> &mut [P] {
+        self.color
+    }
+
+    pub fn depth(&mut self) -> Option<&mut [D]> {
+        self.depth.as_deref_mut()
+    }
+
+    pub fn stencil(&mut self) -> Option<&mut [u8]> {
+        self.stencil.as_deref_mut()
+    }
+
+    /// Fills the color attachment with `color` and the depth attachment with `depth`.
+    pub fn clear(&mut self, color: P, depth: f32)
+    where
+        P: Copy,
+    {
+        self.clear_color(color);
+        self.clear_depth(depth);
+    }
+
+    /// Fills the color attachment with `color`.
+    pub fn clear_color(&mut self, color: P)
+    where
+        P: Copy,
+    {
+        self.color.fill(color);
+    }
+
+    /// Fills the depth attachment with `value`, see [`DepthState::clear_value`].
+    ///
+    /// [`DepthState::clear_value`]: crate::DepthState::clear_value
+    pub fn clear_depth(&mut self, value: f32) {
+        if let Some(zbuffer) = self.depth.as_deref_mut() {
+            zbuffer.fill(D::from_f32(value));
+        }
+    }
+
+    /// Fills the stencil attachment with `value`.
+    pub fn clear_stencil(&mut self, value: u8) {
+        if let Some(stencil) = self.stencil.as_deref_mut() {
+            stencil.fill(value);
+        }
+    }
+
+    /// Averages the samples of every pixel in linear space and writes them to the color
+    /// attachment of `target`.
+    ///
+    /// Panics if `target` is not the same size or is multisampled.
+    pub fn resolve<Q: Color>(&self, target: &mut impl RenderTarget<Pixel = Q>)
+    where
+        P: Color,
+    {
+        let target = target.framebuffer();
+        assert_eq!((target.width, target.height), (self.width, self.height));
+        assert_eq!(target.samples, SampleCount::X1);
+        multisample::resolve(self.color, self.samples.count(), target.color);
+    }
+}
+
+impl<P: Copy, D: DepthFormat> RenderTarget for Framebuffer<'_, P, D> {
+    type Pixel = P;
+    type Depth = D;
+
+    fn framebuffer(&mut self) -> Framebuffer<'_, P, D> {
+        Framebuffer {
+            color: self.color,
+            depth: self.depth.as_deref_mut(),
+            stencil: self.stencil.as_deref_mut(),
+            width: self.width,
+            height: self.height,
+            samples: self.samples,
+        }
+    }
+}
+
+/// Heap allocated color and optional depth and stencil attachments.
+#[derive(Debug, Clone)]
+pub struct OwnedFramebuffer<P, D = f32> {
+    color: Vec<P>,
+    depth: Option<Vec<D>>,
+    stencil: Option<Vec<u8>>,
+    width: usize,
+    height: usize,
+    samples: SampleCount,
+}
+
+impl<P: Copy> OwnedFramebuffer<P> {
+    /// Allocates a color attachment filled with `clear`.
+    ///
+    /// Panics if `width * height` overflows.
+    pub fn new(width: usize, height: usize, clear: P) -> Self {
+        Self::new_multisample(width, height, SampleCount::X1, clear)
+    }
+
+    /// Allocates a color attachment with `samples` per pixel filled with `clear`.
+    ///
+    /// Panics if `width * height * samples` overflows.
+    pub fn new_multisample(width: usize, height: usize, samples: SampleCount, clear: P) -> Self {
+        let len = width
+            .checked_mul(height)
+            .and_then(|size| size.checked_mul(samples.count()))
+            .expect("framebuffer size overflows");
+        Self {
+            color: vec![clear; len],
+            depth: None,
+            stencil: None,
+            width,
+            height,
+            samples,
+        }
+    }
+}
+
+impl<P: Copy, D: DepthFormat> OwnedFramebuffer<P, D> {
+    /// Allocates a depth attachment filled with `clear`.
+    pub fn with_depth(self, clear: f32) -> Self {
+        self.with_depth_format(clear)
+    }
+
+    /// Allocates a depth attachment in any [`DepthFormat`] filled with `clear`.
+    pub fn with_depth_format<E: DepthFormat>(self, clear: f32) -> OwnedFramebuffer<P, E> {
+        OwnedFramebuffer {
+            depth: Some(vec![E::from_f32(clear); self.color.len()]),
+            color: self.color,
+            stencil: self.stencil,
+            width: self.width,
+            height: self.height,
+            samples: self.samples,
+        }
+    }
+
+    /// Allocates a stencil attachment filled with `clear`.
+    pub fn with_stencil(mut self, clear: u8) -> Self {
+        self.stencil = Some(vec![clear; self.color.len()]);
+        self
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn samples(&self) -> SampleCount {
+        self.samples
+    }
+
+    pub fn color(&self) -> &[P] {
+        &self.color
+    }
+
+    pub fn depth(&self) -> Option<&[D]> {
+        self.depth.as_deref()
+    }
+
+    pub fn stencil(&self) -> Option<&[u8]> {
+        self.stencil.as_deref()
+    }
+
+    pub fn clear(&mut self, color: P, depth: f32) {
+        self.framebuffer().clear(color, depth);
+    }
+
+    pub fn clear_color(&mut self, color: P) {
+        self.framebuffer().clear_color(color);
+    }
+
+    pub fn clear_depth(&mut self, value: f32) {
+        self.framebuffer().clear_depth(value);
+    }
+
+    pub fn clear_stencil(&mut self, value: u8) {
+        self.framebuffer().clear_stencil(value);
+    }
+
+    /// See [`Framebuffer::resolve`].
+    pub fn resolve<Q: Color>(&self, target: &mut impl RenderTarget<Pixel = Q>)
+    where
+        P: Color,
+    {
+        let target = target.framebuffer();
+        assert_eq!((target.width, target.height), (self.width, self.height));
+        assert_eq!(target.samples, SampleCount::X1);
+        multisample::resolve(&self.color, self.samples.count(), target.color);
+    }
+}
+
+impl<P: Copy, D: DepthFormat> RenderTarget for OwnedFramebuffer<P, D> {
+    type Pixel = P;
+    type Depth = D;
+
+    fn framebuffer(&mut self) -> Framebuffer<'_, P, D> {
+        Framebuffer {
+            color: &mut self.color,
+            depth: self.depth.as_deref_mut(),
+            stencil: self.stencil.as_deref_mut(),
+            width: self.width,
+            height: self.height,
+            samples: self.samples,
+        }
+    }
+}
+
+/// Color and optional depth and stencil attachments with dimensions known at compile time,
+/// for targets without an allocator.
+///
+/// `D` is `()` without a depth attachment or `[[E; W]; H]` for one in the [`DepthFormat`]
+/// `E`, and `S` is `()` or `[[u8; W]; H]` for a stencil attachment, so a target only
+/// stores the attachments it has. Start with [`StaticFramebuffer::new`] and add them with
+/// [`StaticFramebuffer::with_depth`] and [`StaticFramebuffer::with_stencil`].
+#[derive(Debug, Clone)]
+pub struct StaticFramebuffer<P, const W: usize, const H: usize, D = (), S = ()> {
+    pub color: [[P; W]; H],
+    pub depth: D,
+    pub stencil: S,
+}
+
+/// Depth attachment of a [`StaticFramebuffer`] of `W` by `H` pixels.
+pub trait StaticDepth<const W: usize, const H: usize> {
+    type Format: DepthFormat;
+
+    fn depth(&mut self) -> Option<&mut [Self::Format]>;
+}
+
+impl<const W: usize, const H: usize> StaticDepth<W, H> for () {
+    type Format = f32;
+
+    fn depth(&mut self) -> Option<&mut [f32]> {
+        None
+    }
+}
+
+impl<E: DepthFormat, const W: usize, const H: usize> StaticDepth<W, H> for [[E; W]; H] {
+    type Format = E;
+
+    fn depth(&mut self) -> Option<&mut [E]> {
+        Some(self.as_flattened_mut())
+    }
+}
+
+/// Stencil attachment of a [`StaticFramebuffer`] of `W` by `H` pixels.
+pub trait StaticStencil<const W: usize, const H: usize> {
+    fn stencil(&mut self) -> Option<&mut [u8]>;
+}
+
+impl<const W: usize, const H: usize> StaticStencil<W, H> for () {
+    fn stencil(&mut self) -> Option<&mut [u8]> {
+        None
+    }
+}
+
+impl<const W: usize, const H: usize> StaticStencil<W, H> for [[u8; W]; H] {
+    fn stencil(&mut self) -> Option<&mut [u8]> {
+        Some(self.as_flattened_mut())
+    }
+}
+
+impl<P: Copy, const W: usize, const H: usize> StaticFramebuffer<P, W, H> {
+    /// A color attachment filled with `color`.
+    pub const fn new(color: P) -> Self {
+        Self {
+            color: [[color; W]; H],
+            depth: (),
+            stencil: (),
+        }
+    }
+}
+
+impl<P: Copy, const W: usize, const H: usize, S: Copy> StaticFramebuffer<P, W, H, (), S> {
+    /// Adds a depth attachment filled with `depth`, which is already in the format of the
+    /// attachment, see [`DepthFormat::from_f32`].
+    pub const fn with_depth<E: DepthFormat>(
+        self,
+        depth: E,
+    ) -> StaticFramebuffer<P, W, H, [[E; W]; H], S> {
+        StaticFramebuffer {
+            color: self.color,
+            depth: [[depth; W]; H],
+            stencil: self.stencil,
+        }
+    }
+}
+
+impl<P: Copy, const W: usize, const H: usize, D: Copy> StaticFramebuffer<P, W, H, D, ()> {
+    /// Adds a stencil attachment filled with `stencil`.
+    pub const fn with_stencil(self, stencil: u8) -> StaticFramebuffer<P, W, H, D, [[u8; W]; H]> {
+        StaticFramebuffer {
+            color: self.color,
+            depth: self.depth,
+            stencil: [[stencil; W]; H],
+        }
+    }
+}
+
+impl<P: Copy, const W: usize, const H: usize, D, S> StaticFramebuffer<P, W, H, D, S>
+where
+    D: StaticDepth<W, H>,
+    S: StaticStencil<W, H>,
+{
+    pub fn clear(&mut self, color: P, depth: f32) {
+        self.framebuffer().clear(color, depth);
+    }
+
+    pub fn clear_color(&mut self, color: P) {
+        self.framebuffer().clear_color(color);
+    }
+
+    pub fn clear_depth(&mut self, value: f32) {
+        self.framebuffer().clear_depth(value);
+    }
+
+    pub fn clear_stencil(&mut self, value: u8) {
+        self.framebuffer().clear_stencil(value);
+    }
+}
+
+impl<P: Copy, const W: usize, const H: usize, D, S> RenderTarget
+    for StaticFramebuffer<P, W, H, D, S>
+where
+    D: StaticDepth<W, H>,
+    S: StaticStencil<W, H>,
+{
+    type Pixel = P;
+    type Depth = D::Format;
+
+    fn framebuffer(&mut self) -> Framebuffer<'_, P, D::Format> {
+        Framebuffer {
+            color: self.color.as_flattened_mut(),
+            depth: self.depth.depth(),
+            stencil: self.stencil.stencil(),
+            width: W,
+            height: H,
+            samples: SampleCount::X1,
+        }
+    }
+}
+
+/// An attachment is not `width * height * samples` long.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SizeError {
+    pub len: usize,
+    pub width: usize,
+    pub height: usize,
+    pub samples: SampleCount,
+}
+
+impl fmt::Display for SizeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "attachment of length {} does not match {}x{}",
+            self.len, self.width, self.height
+        )?;
+        match self.samples {
+            SampleCount::X1 => Ok(()),
+            samples => write!(f, " with {} samples", samples.count()),
+        }
+    }
+}
+
+impl core::error::Error for SizeError {}
+
+fn check_size(
+    len: usize,
+    width: usize,
+    height: usize,
+    samples: SampleCount,
+) -> Result<(), SizeError> {
+    let size = width
+        .checked_mul(height)
+        .and_then(|size| size.checked_mul(samples.count()));
+    match size {
+        Some(size) if size == len => Ok(()),
+        _ => Err(SizeError {
+            len,
+            width,
+            height,
+            samples,
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::ToString;
+
+    use super::*;
+
+    #[test]
+    fn attachments_must_match_the_size() {
+        let mut color = [0u8; 12];
+        assert!(Framebuffer::new(&mut color, 4, 3).is_ok());
+        assert!(Framebuffer::new(&mut color, 3, 4).is_ok());
+        assert_eq!(
+            Framebuffer::new(&mut color, 4, 4).unwrap_err(),
+            SizeError {
+                len: 12,
+                width: 4,
+                height: 4,
+                samples: SampleCount::X1
+            }
+        );
+        assert!(Framebuffer::new(&mut [0u8; 0], 0, 7).is_ok());
+
+        let mut depth = [0.0f32; 11];
+        let framebuffer = Framebuffer::new(&mut color, 4, 3).unwrap();
+        assert_eq!(framebuffer.with_depth(&mut depth).unwrap_err().len, 11);
+        let mut stencil = [0u8; 13];
+        let framebuffer = Framebuffer::new(&mut color, 4, 3).unwrap();
+        assert_eq!(framebuffer.with_stencil(&mut stencil).unwrap_err().len, 13);
+    }
+
+    #[test]
+    fn multisampled_attachments_store_every_sample() {
+        let mut color = [0u8; 48];
+        assert!(Framebuffer::new_multisample(&mut color, 4, 3, SampleCount::X4).is_ok());
+        assert!(Framebuffer::new_multisample(&mut color, 4, 3, SampleCount::X1).is_err());
+        assert!(Framebuffer::new_multisample(&mut color, 4, 3, SampleCount::X8).is_err());
+        assert!(Framebuffer::new_multisample(&mut color[..24], 4, 3, SampleCount::X2).is_ok());
+
+        let mut depth = [0u16; 12];
+        let framebuffer = Framebuffer::new_multisample(&mut color, 4, 3, SampleCount::X4);
+        let error = framebuffer.unwrap().with_depth(&mut depth).unwrap_err();
+        assert_eq!(error.samples, SampleCount::X4);
+        assert_eq!(
+            error.to_string(),
+            "attachment of length 12 does not match 4x3 with 4 samples"
+        );
+    }
+
+    #[test]
+    fn overflowing_sizes_are_errors() {
+        let mut color = [0u8; 4];
+        let error = Framebuffer::new(&mut color, usize::MAX, 2).unwrap_err();
+        assert_eq!((error.len, error.width, error.height), (4, usize::MAX, 2));
+        assert_eq!(
+            error.to_string(),
+            alloc::format!("attachment of length 4 does not match {}x2", usize::MAX)
+        );
+
+        // wrapping multiplication would match an empty attachment
+        let width = usize::MAX / 4 + 1;
+        assert_eq!(width.wrapping_mul(4), 0);
+        let samples = SampleCount::X4;
+        assert!(check_size(0, width, 1, SampleCount::X1).is_err());
+        assert!(check_size(0, width, 1, samples).is_err());
+        assert!(check_size(0, width / 2, 2, samples).is_err());
+        assert!(check_size(0, 0, usize::MAX, samples).is_ok());
+    }
+
+    #[test]
+    #[should_panic(expected = "framebuffer size overflows")]
+    fn owned_framebuffers_panic_on_overflowing_sizes() {
+        // would wrap to an empty attachment
+        OwnedFramebuffer::new_multisample(usize::MAX / 4 + 1, 1, SampleCount::X4, 0u8);
+    }
+
+    #[test]
+    fn static_framebuffers_only_have_the_attachments_they_store() {
+        const TARGET: StaticFramebuffer<u8, 4, 2> = StaticFramebuffer::new(1);
+        let mut target = TARGET;
+        let mut framebuffer = target.framebuffer();
+        assert_eq!((framebuffer.width(), framebuffer.height()), (4, 2));
+        assert_eq!(framebuffer.color(), &[1; 8]);
+        assert!(framebuffer.depth().is_none());
+        assert!(framebuffer.stencil().is_none());
+
+        let mut target = TARGET.with_depth(u16::MAX);
+        assert_eq!(target.framebuffer().depth(), Some(&mut [u16::MAX; 8][..]));
+        assert!(target.framebuffer().stencil().is_none());
+
+        let mut target = TARGET.with_stencil(3).with_depth(0.5f32);
+        target.clear(2, 0.25);
+        target.clear_stencil(4);
+        assert_eq!(target.color, [[2; 4]; 2]);
+        assert_eq!(target.depth, [[0.25; 4]; 2]);
+        assert_eq!(target.stencil, [[4; 4]; 2]);
+    }
+}
@@ -0,0 +1,41 @@
+//! Helpers shared by the tests of several modules.
+
+use alloc::vec::Vec;
+
+use tint::LinearRgb;
+
+use crate::{BlendState, DrawState, OwnedFramebuffer, SampleCount};
+
+/// Seeded generator of numbers in `0..1`, the same sequence on every run.
+pub fn random(seed: u64) -> impl FnMut() -> f32 {
+    let mut seed = seed;
+    move || {
+        seed = seed
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        (seed >> 40) as f32 / (1 << 24) as f32
+    }
+}
+
+/// Number of primitives covering each sample of a `width` by `height` target.
+///
+/// `draw` draws white primitives with the given state, which adds them up.
+pub fn hits(
+    (width, height): (usize, usize),
+    samples: SampleCount,
+    state: DrawState,
+    draw: impl FnOnce(&mut OwnedFramebuffer<LinearRgb>, DrawState),
+) -> Vec<u32> {
+    let black = LinearRgb::rgb(0.0, 0.0, 0.0);
+    let mut target = OwnedFramebuffer::new_multisample(width, height, samples, black);
+    let state = DrawState {
+        blend: BlendState::ADDITIVE,
+        ..state
+    };
+    draw(&mut target, state);
+    target
+        .color()
+        .iter()
+        .map(|c| libm::roundf(c.r) as u32)
+        .collect()
+}
@@ -0,0 +1,381 @@
+//! Tile binned rendering across a thread pool.
+//!
+//! Triangles are clipped and binned into screen tiles as they are submitted. Rendering
+//! rasterizes rows of tiles concurrently, each writing to its own disjoint region of the
+//! attachments.
+
+use crate::{
+    DepthFormat, DrawState, Framebuffer, RenderTarget, Shader, Tile, VertexCache, VertexShader,
+    clip, rast_triangle_inner,
+};
+use alloc::vec::Vec;
+use rayon::prelude::*;
+use tint::Color;
+
+/// Width and height of a tile in pixels.
+pub const TILE_SIZE: usize = 32;
+
+struct BinnedTriangle<D> {
+    vertices: [([f32; 4], D); 3],
+    state: DrawState,
+    front_facing: bool,
+}
+
+/// Bins triangles into screen tiles and rasterizes the tiles in parallel.
+///
+/// Triangles within a tile are drawn in submission order, so the output matches drawing
+/// every triangle with [`crate::draw_triangle`].
+///
+/// The vertex stage runs on the shader of the renderer as triangles are submitted, but every
+/// row of tiles is shaded by its own clone of it. The fragment stage of a shader that keeps
+/// state, such as a counter or an accumulator behind a reference, only sees the fragments of
+/// one row per clone and in a different order than with [`crate::draw_triangle`], and
+/// changes to the clones are lost after [`TiledRenderer::render`].
+pub struct TiledRenderer<S: Shader> {
+    shader: S,
+    width: usize,
+    height: usize,
+    tiles_x: usize,
+    triangles: Vec<BinnedTriangle<S::VertexData>>,
+    bins: Vec<Vec<u32>>,
+}
+
+impl<S: Shader> TiledRenderer<S> {
+    pub fn new(width: usize, height: usize, shader: S) -> Self {
+        let tiles_x = width.div_ceil(TILE_SIZE);
+        let tiles_y = height.div_ceil(TILE_SIZE);
+        Self {
+            shader,
+            width,
+            height,
+            tiles_x,
+            triangles: Vec::new(),
+            bins: (0..tiles_x * tiles_y).map(|_| Vec::new()).collect(),
+        }
+    }
+
+    pub fn shader(&self) -> &S {
+        &self.shader
+    }
+
+    pub fn shader_mut(&mut self) -> &mut S {
+        &mut self.shader
+    }
+
+    /// Submits a triangle, running the vertex stage of the shader on each vertex.
+    ///
+    /// See [`crate::draw_triangle`].
+    pub fn draw_triangle<V>(&mut self, state: DrawState, v1: V, v2: V, v3: V)
+    where
+        S: VertexShader<V>,
+    {
+        let vertices = [v1, v2, v3].map(|v| self.shader.vertex(&v));
+        self.bin_triangle(state, vertices);
+    }
+
+    /// Submits a triangle list.
+    ///
+    /// See [`crate::draw_indexed`].
+    pub fn draw_indexed<V>(&mut self, state: DrawState, vertices: &[V], indices: &[u32])
+    where
+        S: VertexShader<V>,
+    {
+        let mut cache = VertexCache::new(indices);
+        for triangle in indices.chunks_exact(3) {
+            let vertices = [triangle[0], triangle[1], triangle[2]]
+                .map(|i| cache.get_or_insert(i, || self.shader.vertex(&vertices[i as usize])));
+            self.bin_triangle(state, vertices);
+        }
+    }
+
+    // Culls, clips and bins a triangle that went through the vertex stage.
+    fn bin_triangle(&mut self, state: DrawState, [v1, v2, v3]: [([f32; 4], S::VertexData); 3]) {
+        let Some(front_facing) = state.facing(v1.0, v2.0, v3.0) else {
+            return;
+        };
+
+        let Some(polygon) =
+            clip::clip_and_project(&self.shader, self.width, self.height, v1, v2, v3)
+        else {
+            return;
+        };
+
+        for i in 1..polygon.len - 1 {
+            let vertices = [
+                polygon.vertices[0],
+                polygon.vertices[i],
+                polygon.vertices[i + 1],
+            ];
+
+            let [v1, v2, v3] = vertices.map(|(v, _)| v);
+            let minx = v1[0].min(v2[0]).min(v3[0]).max(0.0) as usize / TILE_SIZE;
+            let miny = v1[1].min(v2[1]).min(v3[1]).max(0.0) as usize / TILE_SIZE;
+            let maxx = (libm::ceilf(v1[0].max(v2[0]).max(v3[0])).max(0.0) as usize)
+                .min(self.width)
+                .div_ceil(TILE_SIZE);
+            let maxy = (libm::ceilf(v1[1].max(v2[1]).max(v3[1])).max(0.0) as usize)
+                .min(self.height)
+                .div_ceil(TILE_SIZE);
+            if minx >= maxx || miny >= maxy {
+                continue;
+            }
+
+            let index = self.triangles.len() as u32;
+            self.triangles.push(BinnedTriangle {
+                vertices,
+                state,
+                front_facing,
+            });
+            for ty in miny..maxy {
+                for tx in minx..maxx {
+                    self.bins[ty * self.tiles_x + tx].push(index);
+                }
+            }
+        }
+    }
+
+    /// Rasterizes every submitted triangle and clears the bins.
+    ///
+    /// Every row of tiles is shaded by a clone of the shader, see [`TiledRenderer`]. Panics if
+    /// the `target` is not the size the renderer was created with.
+    pub fn render<Pixel, D>(&mut self, target: &mut impl RenderTarget<Pixel = Pixel, Depth = D>)
+    where
+        Pixel: Color + Send,
+        D: DepthFormat + Send,
+        S: Clone + Send + Sync,
+        S::VertexData: Send + Sync,
+    {
+        let Framebuffer {
+            color,
+            depth,
+            stencil,
+            width,
+            height,
+            samples,
+        } = target.framebuffer();
+        assert_eq!((width, height), (self.width, self.height));
+        if width == 0 || height == 0 {
+            // nothing was binned, and there are no rows of tiles to split the target into
+            return;
+        }
+
+        let band = TILE_SIZE * self.width * samples.count();
+        let Self {
+            shader,
+            width,
+            height,
+            tiles_x,
+            triangles,
+            bins,
+        } = self;
+        let (width, height, tiles_x) = (*width, *height, *tiles_x);
+        let bands = height.div_ceil(TILE_SIZE);
+        let mut depth = split_bands(depth, band, bands);
+        let mut stencil = split_bands(stencil, band, bands);
+
+        // A row of tiles is contiguous in memory, which hands every thread its own slices.
+        color
+            .par_chunks_mut(band)
+            .zip(depth.par_iter_mut())
+            .zip(stencil.par_iter_mut())
+            .zip(bins.par_chunks_mut(tiles_x))
+            .enumerate()
+            .for_each(|(ty, (((pixels, zbuffer), stencil), bins))| {
+                let mut shader = shader.clone();
+                // the attachments of the band start at the first row of its tiles
+                let mut band = Framebuffer {
+                    color: pixels,
+                    depth: zbuffer.as_deref_mut(),
+                    stencil: stencil.as_deref_mut(),
+                    width,
+                    height,
+                    samples,
+                };
+                for (tx, bin) in bins.iter_mut().enumerate() {
+                    let tile = Tile {
+                        minx: tx * TILE_SIZE,
+                        miny: ty * TILE_SIZE,
+                        maxx: ((tx + 1) * TILE_SIZE).min(width),
+                        maxy: ((ty + 1) * TILE_SIZE).min(height),
+                    };
+                    for &index in bin.iter() {
+                        let BinnedTriangle {
+                            vertices,
+                            state,
+                            front_facing,
+                        } = triangles[index as usize];
+                        rast_triangle_inner(
+                            &mut band,
+                            vertices,
+                            &mut shader,
+                            state,
+                            front_facing,
+                            tile,
+                        );
+                    }
+                    bin.clear();
+                }
+            });
+        triangles.clear();
+    }
+}
+
+// Splits an optional attachment into rows of tiles.
+fn split_bands<T>(
+    attachment: Option<&mut [T]>,
+    band: usize,
+    bands: usize,
+) -> Vec<Option<&mut [T]>> {
+    match attachment {
+        Some(attachment) => attachment.chunks_mut(band).map(Some).collect(),
+        None => (0..bands).map(|_| None).collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+
+    use tint::LinearRgb;
+
+    use super::*;
+    use crate::{
+        ColorShader, Compare, DepthState, OwnedFramebuffer, SampleCount, StencilFace, StencilOp,
+        StencilState, Subpixel, draw_triangle, testing,
+    };
+
+    const WIDTH: usize = 97;
+    const HEIGHT: usize = 83;
+
+    type Vertex = ([f32; 4], LinearRgb);
+
+    // Overlapping triangles of random colors and depths, partly outside of the viewport.
+    fn triangles() -> Vec<[Vertex; 3]> {
+        let mut random = testing::random(7);
+        (0..200)
+            .map(|_| {
+                [(); 3].map(|_| {
+                    let position = [random() * 2.4 - 1.2, random() * 2.4 - 1.2, random(), 1.0];
+                    (position, LinearRgb::rgb(random(), random(), random()))
+                })
+            })
+            .collect()
+    }
+
+    #[test]
+    fn tiles_match_drawing_every_triangle() {
+        let triangles = triangles();
+        for samples in [SampleCount::X1, SampleCount::X4] {
+            for subpixel in [Subpixel::Float, Subpixel::Fixed(4)] {
+                let state = DrawState {
+                    subpixel,
+                    ..Default::default()
+                };
+                let clear = LinearRgb::rgb(0.0, 0.0, 0.0);
+                let mut expected = OwnedFramebuffer::new_multisample(WIDTH, HEIGHT, samples, clear)
+                    .with_depth(1.0)
+                    .with_stencil(0);
+                let mut tiled = expected.clone();
+
+                let mut renderer = TiledRenderer::new(WIDTH, HEIGHT, ColorShader);
+                for &[v1, v2, v3] in &triangles {
+                    draw_triangle(&mut expected, state, v1, v2, v3, ColorShader);
+                    renderer.draw_triangle(state, v1, v2, v3);
+                }
+                renderer.render(&mut tiled);
+
+                assert!(
+                    expected.color() == tiled.color(),
+                    "{samples:?} {subpixel:?}"
+                );
+                assert!(
+                    expected.depth() == tiled.depth(),
+                    "{samples:?} {subpixel:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn tiles_match_drawing_across_tile_boundaries_with_every_attachment() {
+        // triangles around the corners of tiles, both facings and overlapping at different
+        // depths, then the random ones
+        let ndc = |x: f32, y: f32| [x / WIDTH as f32 * 2.0 - 1.0, 1.0 - y / HEIGHT as f32 * 2.0];
+        let mut triangles: Vec<[Vertex; 3]> = Vec::new();
+        for (i, (cx, cy)) in [(32.0, 32.0), (64.0, 32.0), (32.0, 64.0), (64.0, 64.0)]
+            .into_iter()
+            .enumerate()
+        {
+            let z = 0.2 + 0.15 * i as f32;
+            let color = LinearRgb::rgb(z, 1.0 - z, 0.5);
+            let vertex = |x: f32, y: f32| {
+                let [x, y] = ndc(x, y);
+                ([x, y, z, 1.0], color)
+            };
+            let (a, b, c) = (
+                vertex(cx - 20.3, cy - 9.7),
+                vertex(cx + 17.1, cy - 3.2),
+                vertex(cx - 2.4, cy + 21.9),
+            );
+            triangles.push([a, b, c]);
+            triangles.push([a, c, b]);
+        }
+        triangles.extend(self::triangles());
+
+        let face = |compare, pass| StencilFace {
+            compare,
+            fail: StencilOp::Keep,
+            depth_fail: StencilOp::Invert,
+            pass,
+        };
+        let state = DrawState {
+            depth: DepthState {
+                compare: Compare::LessEqual,
+                write: true,
+            },
+            stencil: StencilState {
+                front: face(Compare::GreaterEqual, StencilOp::IncrementWrap),
+                back: face(Compare::Always, StencilOp::DecrementClamp),
+                reference: 200,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        for samples in [SampleCount::X1, SampleCount::X4] {
+            let clear = LinearRgb::rgb(0.0, 0.0, 0.0);
+            let mut expected = OwnedFramebuffer::new_multisample(WIDTH, HEIGHT, samples, clear)
+                .with_depth(1.0)
+                .with_stencil(3);
+            let mut tiled = expected.clone();
+
+            let mut renderer = TiledRenderer::new(WIDTH, HEIGHT, ColorShader);
+            for &[v1, v2, v3] in &triangles {
+                draw_triangle(&mut expected, state, v1, v2, v3, ColorShader);
+                renderer.draw_triangle(state, v1, v2, v3);
+            }
+            renderer.render(&mut tiled);
+
+            assert!(expected.color() == tiled.color(), "{samples:?}");
+            assert!(expected.depth() == tiled.depth(), "{samples:?}");
+            assert!(expected.stencil() == tiled.stencil(), "{samples:?}");
+            // the stencil test rejected some fragments and both facings wrote values
+            let stencil = tiled.stencil().unwrap();
+            assert!(stencil.iter().any(|&s| s > 3), "{samples:?}");
+            assert!(stencil.iter().any(|&s| s < 3), "{samples:?}");
+        }
+    }
+
+    #[test]
+    fn empty_targets_render_nothing() {
+        for (width, height) in [(0, 0), (0, 40), (40, 0)] {
+            let clear = LinearRgb::rgb(0.0, 0.0, 0.0);
+            let mut target = OwnedFramebuffer::new(width, height, clear).with_depth(1.0);
+            let mut renderer = TiledRenderer::new(width, height, ColorShader);
+            for [v1, v2, v3] in triangles().into_iter().take(10) {
+                renderer.draw_triangle(DrawState::default(), v1, v2, v3);
+            }
+            renderer.render(&mut target);
+            assert!(target.color().is_empty());
+        }
+    }
+}
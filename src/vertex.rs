@@ -0,0 +1,112 @@
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// Vertices that went through the vertex stage, keyed by their index.
+///
+/// Every index is transformed once per draw, however far apart its uses are.
+pub struct VertexCache<D> {
+    entries: Vec<Option<([f32; 4], D)>>,
+}
+
+impl<D: Copy> VertexCache<D> {
+    /// Allocates an entry for every index up to the largest of the complete triangles in
+    /// `indices`.
+    pub fn new(indices: &[u32]) -> Self {
+        let len = indices
+            .chunks_exact(3)
+            .flatten()
+            .max()
+            .map_or(0, |&max| max as usize + 1);
+        Self {
+            entries: vec![None; len],
+        }
+    }
+
+    /// Returns the vertex at `index`, calling `transform` on a miss.
+    ///
+    /// Panics if `index` is larger than every index the cache was created with.
+    #[inline]
+    pub fn get_or_insert(
+        &mut self,
+        index: u32,
+        transform: impl FnOnce() -> ([f32; 4], D),
+    ) -> ([f32; 4], D) {
+        *self.entries[index as usize].get_or_insert_with(transform)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+    use core::cell::Cell;
+
+    use tint::LinearRgb;
+
+    use crate::{ColorShader, DrawState, OwnedFramebuffer, Shader, VertexShader, draw_indexed};
+
+    // Counts the vertices that go through the vertex stage.
+    #[derive(Clone, Copy)]
+    struct CountingShader<'a>(&'a Cell<usize>);
+
+    impl Shader for CountingShader<'_> {
+        type VertexData = LinearRgb;
+
+        fn interpolate(
+            &self,
+            bcx: f32,
+            bcy: f32,
+            bcz: f32,
+            d1: LinearRgb,
+            d2: LinearRgb,
+            d3: LinearRgb,
+        ) -> LinearRgb {
+            ColorShader.interpolate(bcx, bcy, bcz, d1, d2, d3)
+        }
+    }
+
+    impl VertexShader<[f32; 2]> for CountingShader<'_> {
+        fn vertex(&mut self, &[x, y]: &[f32; 2]) -> ([f32; 4], LinearRgb) {
+            self.0.set(self.0.get() + 1);
+            ([x, y, 0.5, 1.0], LinearRgb::rgb(x, y, 1.0))
+        }
+    }
+
+    fn draw(vertices: &[[f32; 2]], indices: &[u32]) -> usize {
+        let calls = Cell::new(0);
+        let mut target = OwnedFramebuffer::new(16, 16, LinearRgb::rgb(0.0, 0.0, 0.0));
+        let shader = CountingShader(&calls);
+        draw_indexed(&mut target, DrawState::default(), vertices, indices, shader);
+        calls.get()
+    }
+
+    #[test]
+    fn shared_vertices_of_a_grid_are_transformed_once() {
+        // 8 by 8 quads of two triangles each, row by row
+        const N: u32 = 8;
+        let vertices: Vec<[f32; 2]> = (0..=N)
+            .flat_map(|y| (0..=N).map(move |x| [x as f32 / 4.0 - 1.0, y as f32 / 4.0 - 1.0]))
+            .collect();
+        let mut indices = Vec::new();
+        for y in 0..N {
+            for x in 0..N {
+                let i = y * (N + 1) + x;
+                indices.extend([i, i + 1, i + N + 1, i + 1, i + N + 2, i + N + 1]);
+            }
+        }
+
+        assert_eq!(indices.len(), 6 * 64);
+        assert_eq!(draw(&vertices, &indices), vertices.len());
+    }
+
+    #[test]
+    fn every_index_is_transformed_once_per_draw() {
+        let vertices: Vec<[f32; 2]> = (0..64).map(|i| [i as f32 / 64.0, 0.5]).collect();
+        assert_eq!(draw(&vertices, &[0, 1, 2, 0, 2, 3]), 4);
+        // indices that are far apart or would collide in a small cache
+        assert_eq!(draw(&vertices, &[0, 1, 2, 32, 33, 34, 0, 1, 2]), 6);
+        assert_eq!(draw(&vertices, &[63, 31, 0, 32, 0, 63, 31, 32, 1]), 5);
+        // a trailing incomplete triangle is not transformed, nor allocated for
+        assert_eq!(draw(&vertices, &[0, 1, 2, 3]), 3);
+        assert_eq!(draw(&vertices, &[0, 1, 2, u32::MAX]), 3);
+    }
+}